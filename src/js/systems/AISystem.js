@@ -1,6 +1,16 @@
 import * as THREE from 'three';
 import { initWasm } from '../wasm/wasm-loader';
 
+// Stable non-zero 64-bit seed derived from an entity id (FNV-1a)
+function seedFromEntityId(entityId) {
+  let hash = 0xcbf29ce484222325n;
+  for (const char of String(entityId)) {
+    hash ^= BigInt(char.codePointAt(0));
+    hash = (hash * 0x100000001b3n) & 0xffffffffffffffffn;
+  }
+  return hash;
+}
+
 export class AISystem {
   constructor() {
     this.gameEngine = null;
@@ -44,8 +54,8 @@ export class AISystem {
   // Create a behavior tree for an entity
   createBehaviorTree(entityId, enemyType) {
     if (this.wasmInterface && this.wasmInterface.isLoaded) {
-      // Create behavior context
-      const context = new this.wasmInterface.wasmModule.BehaviorContext();
+      // Create behavior context, seeded per entity so random choices differ
+      const context = this.wasmInterface.wasmModule.BehaviorContext.with_seed(seedFromEntityId(entityId));
       this.behaviorContexts.set(entityId, context);
       
       // Create behavior tree
@@ -0,0 +1,95 @@
+use wasm_bindgen::prelude::*;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+struct CoordinatorState {
+    max_tokens: u32,
+    tokens_in_use: u32,
+}
+
+// Shared pool of attack tokens used to stagger enemy attacks.
+// Clones share the same pool, so every context handed this coordinator
+// competes for the same limited number of simultaneous attackers.
+#[wasm_bindgen]
+#[derive(Clone)]
+pub struct AttackCoordinator {
+    state: Rc<RefCell<CoordinatorState>>,
+}
+
+#[wasm_bindgen]
+impl AttackCoordinator {
+    #[wasm_bindgen(constructor)]
+    pub fn new(max_tokens: u32) -> AttackCoordinator {
+        AttackCoordinator {
+            state: Rc::new(RefCell::new(CoordinatorState {
+                max_tokens,
+                tokens_in_use: 0,
+            })),
+        }
+    }
+
+    pub fn set_max_tokens(&mut self, max_tokens: u32) {
+        self.state.borrow_mut().max_tokens = max_tokens;
+    }
+
+    pub fn max_tokens(&self) -> u32 {
+        self.state.borrow().max_tokens
+    }
+
+    pub fn tokens_in_use(&self) -> u32 {
+        self.state.borrow().tokens_in_use
+    }
+
+    pub fn available_tokens(&self) -> u32 {
+        let state = self.state.borrow();
+        state.max_tokens.saturating_sub(state.tokens_in_use)
+    }
+
+    // Return every token to the pool, e.g. when a wave is cleared
+    pub fn reset(&mut self) {
+        self.state.borrow_mut().tokens_in_use = 0;
+    }
+}
+
+impl AttackCoordinator {
+    pub(crate) fn try_acquire(&self) -> bool {
+        let mut state = self.state.borrow_mut();
+        if state.tokens_in_use < state.max_tokens {
+            state.tokens_in_use += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    pub(crate) fn release(&self) {
+        let mut state = self.state.borrow_mut();
+        state.tokens_in_use = state.tokens_in_use.saturating_sub(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clones_share_a_limited_token_pool() {
+        let coordinator = AttackCoordinator::new(2);
+        let shared = coordinator.clone();
+        assert!(coordinator.try_acquire());
+        assert!(shared.try_acquire());
+        assert!(!coordinator.try_acquire());
+        assert_eq!(shared.tokens_in_use(), 2);
+        assert_eq!(shared.available_tokens(), 0);
+
+        shared.release();
+        assert_eq!(coordinator.available_tokens(), 1);
+        assert!(coordinator.try_acquire());
+
+        let mut coordinator = coordinator;
+        coordinator.reset();
+        assert_eq!(shared.tokens_in_use(), 0);
+        coordinator.release();
+        assert_eq!(shared.tokens_in_use(), 0);
+    }
+}
@@ -1,9 +1,16 @@
 use wasm_bindgen::prelude::*;
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use super::attack_coordinator::AttackCoordinator;
+use super::forest::WorldContext;
+use super::squad::SquadState;
+use super::stimulus::StimulusMap;
+use crate::math::Vector3;
+use crate::rng::Rng;
 
 // AI behavior tree node types
 #[wasm_bindgen]
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub enum NodeType {
     Sequence,
     Selector,
@@ -13,8 +20,80 @@ pub enum NodeType {
     Action,
     Condition,
     Parallel,
+    RateLimit,
+    MemorySelector,
+    Timeout,
+    Race,
+    Commit,
 }
 
+// Comparison operator for blackboard conditions
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum CompareOp {
+    Less,
+    LessEqual,
+    Equal,
+    GreaterEqual,
+    Greater,
+}
+
+impl CompareOp {
+    fn apply(self, left: f64, right: f64) -> bool {
+        match self {
+            CompareOp::Less => left < right,
+            CompareOp::LessEqual => left <= right,
+            CompareOp::Equal => (left - right).abs() <= f64::EPSILON,
+            CompareOp::GreaterEqual => left >= right,
+            CompareOp::Greater => left > right,
+        }
+    }
+}
+
+// Number of NodeType variants, used to size per-type tallies
+const NODE_TYPE_COUNT: usize = 13;
+
+// Node id used for runtime keys of the default action, which has no node
+const DEFAULT_ACTION_ID: usize = usize::MAX;
+
+// Shapes of the context keys holding per-life runtime state written during
+// evaluation, with `#` standing for a decimal number such as a node id.
+// Every key the evaluator writes must match one of these so that
+// `reset_runtime_state` clears it without touching user-set keys.
+const RUNTIME_KEY_SHAPES: [&str; 31] = [
+    "cooldown_#",
+    "cooldown_group_#",
+    "repeater_#_count",
+    "node_#",
+    "rate_limit_#_#",
+    "arrived_#",
+    "resume_#",
+    "timeout_#_ticks",
+    "charge_#_active",
+    "commit_#_prior",
+    "commit_#_ticks",
+    "commit_#_action",
+    "commit_#_parameter",
+    "commit_#_status",
+    "commit_#_damage",
+    "commit_#_ability",
+    "commit_#_move_x",
+    "commit_#_move_y",
+    "commit_#_move_z",
+    "attack_#_start",
+    "attack_#_hit",
+    "attack_#_phase",
+    "phase_#",
+    "action",
+    "action_parameter",
+    "move_x",
+    "move_y",
+    "move_z",
+    "investigate_x",
+    "investigate_y",
+    "investigate_z",
+];
+
 // AI behavior tree node status
 #[wasm_bindgen]
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -24,6 +103,100 @@ pub enum NodeStatus {
     Running,
 }
 
+// Typed per-evaluation results, reset at the start of every evaluate.
+// Fields stay None when no action produced them this tick.
+#[wasm_bindgen]
+#[derive(Clone, Debug, Default)]
+pub struct BehaviorOutput {
+    move_vector: Option<Vector3>,
+    attack_damage: Option<f64>,
+    ability_id: Option<u32>,
+}
+
+#[wasm_bindgen]
+impl BehaviorOutput {
+    pub fn move_vector(&self) -> Option<Vector3> {
+        self.move_vector
+    }
+
+    pub fn attack_damage(&self) -> Option<f64> {
+        self.attack_damage
+    }
+
+    pub fn ability_id(&self) -> Option<u32> {
+        self.ability_id
+    }
+}
+
+// Kinds of event actions queue for the game to act on
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BehaviorEventKind {
+    ProjectileSpawn,
+    ChargeHit,
+    AttackHit,
+    PhaseChange,
+}
+
+// Request raised by an action, queued on the context until the game drains
+// it with `take_events`. A projectile spawn carries its launch position,
+// velocity and speed; a charge hit carries the target position, the charge
+// velocity and the damage dealt; an attack hit carries the target position
+// and the damage dealt; a phase change carries the entity position and the
+// phase now active.
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct BehaviorEvent {
+    kind: BehaviorEventKind,
+    position: Vector3,
+    velocity: Vector3,
+    value: f64,
+}
+
+#[wasm_bindgen]
+impl BehaviorEvent {
+    pub fn kind(&self) -> BehaviorEventKind {
+        self.kind
+    }
+
+    pub fn position(&self) -> Vector3 {
+        self.position
+    }
+
+    pub fn velocity(&self) -> Vector3 {
+        self.velocity
+    }
+
+    pub fn value(&self) -> f64 {
+        self.value
+    }
+}
+
+// One recorded set_value or remove_value call; `old_value` is None when the
+// key was unset and `new_value` is None when it was removed
+#[wasm_bindgen]
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChangeRecord {
+    key: String,
+    old_value: Option<f64>,
+    new_value: Option<f64>,
+}
+
+#[wasm_bindgen]
+impl ChangeRecord {
+    pub fn key(&self) -> String {
+        self.key.clone()
+    }
+
+    pub fn old_value(&self) -> Option<f64> {
+        self.old_value
+    }
+
+    pub fn new_value(&self) -> Option<f64> {
+        self.new_value
+    }
+}
+
 // Context for behavior tree execution
 #[wasm_bindgen]
 pub struct BehaviorContext {
@@ -32,12 +205,38 @@ pub struct BehaviorContext {
     target_x: f64,
     target_y: f64,
     target_z: f64,
+    target_velocity: [f64; 3],
     entity_x: f64,
     entity_y: f64,
     entity_z: f64,
     entity_health: f64,
     entity_max_health: f64,
+    displayed_health: f64,
+    last_damage_time: Option<f64>,
+    suppression: f64,
+    suppression_decay: f64,
+    home_x: f64,
+    home_y: f64,
+    home_z: f64,
     entity_type: u32,
+    elapsed_time: f64,
+    think_interval: f64,
+    think_accumulator: f64,
+    attack_coordinator: Option<AttackCoordinator>,
+    holds_attack_token: bool,
+    // Whether an attack action ran during the current evaluation
+    attacked: bool,
+    squad: Option<SquadState>,
+    stimulus: Option<StimulusMap>,
+    world: Option<WorldContext>,
+    hearing_range: f64,
+    path_reachable: bool,
+    incoming_projectile: Option<(Vector3, Vector3)>,
+    rng: Rng,
+    // Recorded set_value calls; None while recording is off
+    change_log: Option<Vec<ChangeRecord>>,
+    events: Vec<BehaviorEvent>,
+    output: BehaviorOutput,
 }
 
 #[wasm_bindgen]
@@ -49,17 +248,77 @@ impl BehaviorContext {
             target_x: 0.0,
             target_y: 0.0,
             target_z: 0.0,
+            target_velocity: [0.0; 3],
             entity_x: 0.0,
             entity_y: 0.0,
             entity_z: 0.0,
             entity_health: 100.0,
             entity_max_health: 100.0,
+            displayed_health: 100.0,
+            last_damage_time: None,
+            suppression: 0.0,
+            suppression_decay: 1.0,
+            home_x: 0.0,
+            home_y: 0.0,
+            home_z: 0.0,
             entity_type: 0,
+            elapsed_time: 0.0,
+            think_interval: 0.0,
+            think_accumulator: 0.0,
+            attack_coordinator: None,
+            holds_attack_token: false,
+            attacked: false,
+            squad: None,
+            stimulus: None,
+            world: None,
+            hearing_range: 30.0,
+            path_reachable: true,
+            incoming_projectile: None,
+            rng: Rng::new(0),
+            change_log: None,
+            events: Vec::new(),
+            output: BehaviorOutput::default(),
         }
     }
 
     pub fn set_value(&mut self, key: &str, value: f64) {
-        self.values.insert(key.to_string(), value);
+        let old_value = self.values.insert(key.to_string(), value);
+        if let Some(log) = &mut self.change_log {
+            log.push(ChangeRecord {
+                key: key.to_string(),
+                old_value,
+                new_value: Some(value),
+            });
+        }
+    }
+
+    // Unset `key`, returning its old value; recorded like set_value when the
+    // key was present
+    pub fn remove_value(&mut self, key: &str) -> Option<f64> {
+        let old_value = self.values.remove(key);
+        if let (Some(log), Some(_)) = (&mut self.change_log, old_value) {
+            log.push(ChangeRecord {
+                key: key.to_string(),
+                old_value,
+                new_value: None,
+            });
+        }
+        old_value
+    }
+
+    // Log every set_value for replay debugging. Off by default; turning it
+    // off discards anything not yet taken.
+    pub fn set_change_recording(&mut self, enabled: bool) {
+        if !enabled {
+            self.change_log = None;
+        } else if self.change_log.is_none() {
+            self.change_log = Some(Vec::new());
+        }
+    }
+
+    // Drain the changes recorded since the last call
+    pub fn take_change_log(&mut self) -> Vec<ChangeRecord> {
+        self.change_log.as_mut().map(std::mem::take).unwrap_or_default()
     }
 
     pub fn get_value(&self, key: &str) -> f64 {
@@ -78,15 +337,120 @@ impl BehaviorContext {
         self.entity_z = z;
     }
 
+    // Target velocity, used to lead projectiles
+    pub fn set_target_velocity(&mut self, x: f64, y: f64, z: f64) {
+        self.target_velocity = [x, y, z];
+    }
+
+    // Spawn point or post the entity returns to when leashed
+    pub fn set_home_position(&mut self, x: f64, y: f64, z: f64) {
+        self.home_x = x;
+        self.home_y = y;
+        self.home_z = z;
+    }
+
     pub fn set_entity_health(&mut self, health: f64, max_health: f64) {
         self.entity_health = health;
         self.entity_max_health = max_health;
     }
 
+    // Record a hit at the current context time, restarting the regen delay
+    pub fn note_damage_taken(&mut self) {
+        self.last_damage_time = Some(self.elapsed_time);
+    }
+
+    // True once `delay` seconds of tick time have passed since the last hit
+    pub fn can_regen(&self, delay: f64) -> bool {
+        match self.last_damage_time {
+            Some(time) => self.elapsed_time - time >= delay,
+            None => true,
+        }
+    }
+
+    // Build up suppression when shots pass close to the entity
+    pub fn add_suppression(&mut self, amount: f64) {
+        self.suppression = (self.suppression + amount).max(0.0);
+    }
+
+    pub fn suppression(&self) -> f64 {
+        self.suppression
+    }
+
+    // Suppression lost per second of tick time
+    pub fn set_suppression_decay(&mut self, per_second: f64) {
+        self.suppression_decay = per_second.max(0.0);
+    }
+
+    // Health value for UI bars, eased toward the real health by tick_displayed_health
+    pub fn displayed_health(&self) -> f64 {
+        self.displayed_health
+    }
+
+    // Move the displayed health toward the real health by at most `rate` per second
+    pub fn tick_displayed_health(&mut self, dt: f64, rate: f64) {
+        let difference = self.entity_health - self.displayed_health;
+        let max_step = (rate * dt).abs();
+        if difference.abs() <= max_step {
+            self.displayed_health = self.entity_health;
+        } else {
+            self.displayed_health += max_step.copysign(difference);
+        }
+    }
+
     pub fn set_entity_type(&mut self, entity_type: u32) {
         self.entity_type = entity_type;
     }
 
+    // Advance the context clock, count down all cooldowns and let suppression
+    // decay. Cooldown writes go through set_value and are recorded.
+    pub fn tick(&mut self, delta_time: f64) {
+        self.elapsed_time += delta_time;
+        self.suppression = (self.suppression - self.suppression_decay * delta_time).max(0.0);
+        let running: Vec<(String, f64)> = self
+            .values
+            .iter()
+            .filter(|(key, &value)| key.starts_with("cooldown_") && value > 0.0)
+            .map(|(key, &value)| (key.clone(), value))
+            .collect();
+        for (key, value) in running {
+            self.set_value(&key, (value - delta_time).max(0.0));
+        }
+    }
+
+    // Start the shared cooldown that blocks every ability in `group`; it
+    // counts down with the other cooldowns in tick
+    pub fn start_group_cooldown(&mut self, group: u32, duration: f64) {
+        self.set_value(&format!("cooldown_group_{}", group), duration);
+    }
+
+    pub fn get_elapsed_time(&self) -> f64 {
+        self.elapsed_time
+    }
+
+    // Minimum time between tree evaluations; 0 thinks every frame
+    pub fn set_think_interval(&mut self, seconds: f64) {
+        self.think_interval = seconds.max(0.0);
+    }
+
+    // Accumulate frame time and report whether the entity should evaluate its
+    // tree this frame. Callers skip `evaluate` when this returns false.
+    pub fn should_think(&mut self, dt: f64) -> bool {
+        if self.think_interval <= 0.0 {
+            return true;
+        }
+        self.think_accumulator += dt;
+        if self.think_accumulator < self.think_interval {
+            return false;
+        }
+        // Carry the remainder to keep the average rate, but never let a long
+        // frame queue up extra thinks
+        self.think_accumulator -= self.think_interval;
+        if self.think_accumulator >= self.think_interval {
+            self.think_accumulator = 0.0;
+        }
+        true
+    }
+
     pub fn get_distance_to_target(&self) -> f64 {
         let dx = self.target_x - self.entity_x;
         let dy = self.target_y - self.entity_y;
@@ -94,453 +458,3374 @@ impl BehaviorContext {
         (dx * dx + dy * dy + dz * dz).sqrt()
     }
 
+    pub fn get_distance_to_home(&self) -> f64 {
+        let dx = self.home_x - self.entity_x;
+        let dy = self.home_y - self.entity_y;
+        let dz = self.home_z - self.entity_z;
+        (dx * dx + dy * dy + dz * dz).sqrt()
+    }
+
     pub fn get_health_percentage(&self) -> f64 {
         if self.entity_max_health <= 0.0 {
             return 0.0;
         }
         self.entity_health / self.entity_max_health
     }
-}
-
-// AI behavior tree for enemy decision making
-#[wasm_bindgen]
-pub struct BehaviorTree {
-    root_id: usize,
-    nodes: HashMap<usize, Node>,
-    next_id: usize,
-}
-
-struct Node {
-    node_type: NodeType,
-    children: Vec<usize>,
-    condition_type: u32,
-    action_type: u32,
-    parameter: f64,
-    success_threshold: usize,
-    repeat_times: usize,
-}
 
-#[wasm_bindgen]
-impl BehaviorTree {
-    #[wasm_bindgen(constructor)]
-    pub fn new() -> BehaviorTree {
-        BehaviorTree {
-            root_id: 0,
-            nodes: HashMap::new(),
-            next_id: 0,
-        }
+    // Share an attack coordinator with this entity; attack-token conditions
+    // always succeed while no coordinator is set. A held token is returned
+    // automatically at the end of any evaluation that runs no attack action,
+    // so a branch that fails between acquiring and releasing it cannot leak it.
+    pub fn set_attack_coordinator(&mut self, coordinator: &AttackCoordinator) {
+        self.release_attack_token();
+        self.attack_coordinator = Some(coordinator.clone());
     }
 
-    pub fn create_sequence_node(&mut self) -> usize {
-        let id = self.next_id;
-        self.next_id += 1;
-
-        self.nodes.insert(
-            id,
-            Node {
-                node_type: NodeType::Sequence,
-                children: Vec::new(),
-                condition_type: 0,
-                action_type: 0,
-                parameter: 0.0,
-                success_threshold: 0,
-                repeat_times: 0,
-            },
-        );
+    pub fn clear_attack_coordinator(&mut self) {
+        self.release_attack_token();
+        self.attack_coordinator = None;
+    }
 
-        id
+    pub fn has_attack_token(&self) -> bool {
+        self.holds_attack_token
     }
 
-    pub fn create_selector_node(&mut self) -> usize {
-        let id = self.next_id;
-        self.next_id += 1;
+    // Share the squad this entity belongs to for morale conditions
+    pub fn set_squad_state(&mut self, squad: &SquadState) {
+        self.squad = Some(squad.clone());
+    }
 
-        self.nodes.insert(
-            id,
-            Node {
-                node_type: NodeType::Selector,
-                children: Vec::new(),
-                condition_type: 0,
-                action_type: 0,
-                parameter: 0.0,
-                success_threshold: 0,
-                repeat_times: 0,
-            },
-        );
+    pub fn clear_squad_state(&mut self) {
+        self.squad = None;
+    }
 
-        id
+    // Share the noise map this entity listens to
+    pub fn set_stimulus_map(&mut self, stimulus: &StimulusMap) {
+        self.stimulus = Some(stimulus.clone());
     }
 
-    pub fn create_inverter_node(&mut self) -> usize {
-        let id = self.next_id;
-        self.next_id += 1;
+    pub fn clear_stimulus_map(&mut self) {
+        self.stimulus = None;
+    }
 
-        self.nodes.insert(
-            id,
-            Node {
-                node_type: NodeType::Inverter,
-                children: Vec::new(),
-                condition_type: 0,
-                action_type: 0,
-                parameter: 0.0,
-                success_threshold: 0,
-                repeat_times: 0,
-            },
-        );
+    // Shared world data; its squad and noise map are used when this context
+    // has none of its own, and its occluders decide target visibility
+    pub fn set_world_context(&mut self, world: &WorldContext) {
+        self.world = Some(world.clone());
+    }
 
-        id
+    pub fn clear_world_context(&mut self) {
+        self.world = None;
     }
 
-    pub fn create_succeeder_node(&mut self) -> usize {
-        let id = self.next_id;
-        self.next_id += 1;
+    pub fn set_hearing_range(&mut self, range: f64) {
+        self.hearing_range = range;
+    }
 
-        self.nodes.insert(
-            id,
-            Node {
-                node_type: NodeType::Succeeder,
-                children: Vec::new(),
-                condition_type: 0,
-                action_type: 0,
-                parameter: 0.0,
-                success_threshold: 0,
-                repeat_times: 0,
-            },
-        );
+    // Whether the last path query to the target found a route; written by
+    // whoever runs the nav module and checked by condition 17
+    pub fn set_path_reachable(&mut self, reachable: bool) {
+        self.path_reachable = reachable;
+    }
 
-        id
+    // Most threatening projectile this frame, checked by the dodge condition
+    pub fn set_incoming_projectile(&mut self, position: &Vector3, velocity: &Vector3) {
+        self.incoming_projectile = Some((*position, *velocity));
     }
 
-    pub fn create_repeater_node(&mut self, times: usize) -> usize {
-        let id = self.next_id;
-        self.next_id += 1;
+    pub fn clear_incoming_projectile(&mut self) {
+        self.incoming_projectile = None;
+    }
 
-        self.nodes.insert(
-            id,
-            Node {
-                node_type: NodeType::Repeater,
-                children: Vec::new(),
-                condition_type: 0,
-                action_type: 0,
-                parameter: 0.0,
-                success_threshold: 0,
-                repeat_times: times,
-            },
-        );
+    // Reseed the generator behind random-chance conditions for reproducible runs
+    pub fn set_random_seed(&mut self, seed: u64) {
+        self.rng = Rng::new(seed);
+    }
 
-        id
+    // New context whose random-chance conditions roll from `seed`. Give every
+    // entity its own, e.g. derived from its id; contexts sharing a seed roll
+    // identically.
+    pub fn with_seed(seed: u64) -> BehaviorContext {
+        let mut context = BehaviorContext::new();
+        context.set_random_seed(seed);
+        context
     }
 
-    pub fn create_parallel_node(&mut self, success_threshold: usize) -> usize {
-        let id = self.next_id;
-        self.next_id += 1;
+    // Typed results of the most recent evaluation
+    pub fn output(&self) -> BehaviorOutput {
+        self.output.clone()
+    }
 
-        self.nodes.insert(
-            id,
-            Node {
-                node_type: NodeType::Parallel,
-                children: Vec::new(),
-                condition_type: 0,
-                action_type: 0,
-                parameter: 0.0,
-                success_threshold,
-                repeat_times: 0,
-            },
-        );
+    // Drain the events queued by actions since the last call
+    pub fn take_events(&mut self) -> Vec<BehaviorEvent> {
+        std::mem::take(&mut self.events)
+    }
 
-        id
+    // Serialize the per-entity runtime state for save games. Shared handles
+    // (attack coordinator, squad, stimulus map) are not included and must be
+    // attached again after loading.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(&ContextSnapshot::capture(self)).unwrap_or_default()
     }
 
-    pub fn create_condition_node(&mut self, condition_type: u32, parameter: f64) -> usize {
-        let id = self.next_id;
-        self.next_id += 1;
+    // Restore a context saved with `to_json`. Missing fields keep their
+    // defaults and unknown fields are ignored.
+    pub fn from_json(json: &str) -> Result<BehaviorContext, JsValue> {
+        BehaviorContext::parse_json(json).map_err(|err| JsValue::from_str(&err))
+    }
+}
 
-        self.nodes.insert(
-            id,
-            Node {
-                node_type: NodeType::Condition,
-                children: Vec::new(),
-                condition_type,
-                action_type: 0,
-                parameter,
-                success_threshold: 0,
-                repeat_times: 0,
-            },
-        );
+impl BehaviorContext {
+    // `from_json` without the JS error type, usable outside wasm
+    pub(crate) fn parse_json(json: &str) -> Result<BehaviorContext, String> {
+        serde_json::from_str::<ContextSnapshot>(json)
+            .map(ContextSnapshot::restore)
+            .map_err(|err| err.to_string())
+    }
 
-        id
+    // Unit vector from the entity toward the target, or zero when they coincide
+    fn direction_to_target(&self) -> (f64, f64, f64) {
+        let dx = self.target_x - self.entity_x;
+        let dy = self.target_y - self.entity_y;
+        let dz = self.target_z - self.entity_z;
+        let length = (dx * dx + dy * dy + dz * dz).sqrt();
+        if length > 0.0 {
+            (dx / length, dy / length, dz / length)
+        } else {
+            (0.0, 0.0, 0.0)
+        }
     }
 
-    pub fn create_action_node(&mut self, action_type: u32, parameter: f64) -> usize {
-        let id = self.next_id;
-        self.next_id += 1;
+    // Velocity of a projectile launched from the entity at `speed` toward the
+    // target, or toward where the moving target will be when it arrives if
+    // `lead` is set and an intercept exists
+    fn launch_velocity(&self, speed: f64, lead: bool) -> Vector3 {
+        let offset = [
+            self.target_x - self.entity_x,
+            self.target_y - self.entity_y,
+            self.target_z - self.entity_z,
+        ];
+        let velocity = self.target_velocity;
+        let dot = |a: [f64; 3], b: [f64; 3]| a[0] * b[0] + a[1] * b[1] + a[2] * b[2];
 
-        self.nodes.insert(
-            id,
-            Node {
-                node_type: NodeType::Action,
-                children: Vec::new(),
-                condition_type: 0,
-                action_type,
-                parameter,
-                success_threshold: 0,
-                repeat_times: 0,
-            },
+        // Smallest positive t with |offset + velocity t| == speed t
+        let intercept_time = if lead {
+            let a = dot(velocity, velocity) - speed * speed;
+            let b = 2.0 * dot(offset, velocity);
+            let c = dot(offset, offset);
+            if a.abs() < 1e-9 {
+                Some(-c / b).filter(|&t| t > 0.0)
+            } else {
+                let discriminant = b * b - 4.0 * a * c;
+                if discriminant < 0.0 {
+                    None
+                } else {
+                    let root = discriminant.sqrt();
+                    let (t1, t2) = ((-b - root) / (2.0 * a), (-b + root) / (2.0 * a));
+                    [t1.min(t2), t1.max(t2)].into_iter().find(|&t| t > 0.0)
+                }
+            }
+        } else {
+            None
+        };
+
+        let t = intercept_time.unwrap_or(0.0);
+        let mut aim = Vector3::new(
+            (offset[0] + velocity[0] * t) as f32,
+            (offset[1] + velocity[1] * t) as f32,
+            (offset[2] + velocity[2] * t) as f32,
         );
+        aim.normalize();
+        aim.multiply(speed as f32)
+    }
 
-        id
+    // Publish the movement an action wants this tick
+    fn set_move(&mut self, x: f64, y: f64, z: f64) {
+        self.set_value("move_x", x);
+        self.set_value("move_y", y);
+        self.set_value("move_z", z);
+        self.output.move_vector = Some(Vector3::new(x as f32, y as f32, z as f32));
     }
 
-    pub fn add_child(&mut self, parent_id: usize, child_id: usize) {
-        if let Some(parent) = self.nodes.get_mut(&parent_id) {
-            parent.children.push(child_id);
-        }
+    // Closest point on the incoming projectile's forward path to the entity
+    fn projectile_closest_point(&self) -> Option<Vector3> {
+        let (position, velocity) = self.incoming_projectile?;
+        let entity = Vector3::new(self.entity_x as f32, self.entity_y as f32, self.entity_z as f32);
+        let speed_squared = velocity.dot(&velocity);
+        let t = if speed_squared > 0.0 {
+            (entity.subtract(&position).dot(&velocity) / speed_squared).max(0.0)
+        } else {
+            0.0
+        };
+        Some(position.add(&velocity.multiply(t)))
     }
 
-    pub fn set_root(&mut self, node_id: usize) {
-        self.root_id = node_id;
+    fn squad_state(&self) -> Option<SquadState> {
+        self.squad.clone().or_else(|| self.world.as_ref().and_then(|world| world.squad()))
     }
 
-    pub fn evaluate(&self, context: &mut BehaviorContext) -> i32 {
-        match self.evaluate_node(self.root_id, context) {
-            NodeStatus::Success => 1,
-            NodeStatus::Failure => 0,
-            NodeStatus::Running => 2,
-        }
+    fn stimulus_map(&self) -> Option<StimulusMap> {
+        self.stimulus.clone().or_else(|| self.world.as_ref().and_then(|world| world.stimulus()))
     }
 
-    fn evaluate_node(&self, node_id: usize, context: &mut BehaviorContext) -> NodeStatus {
-        if let Some(node) = self.nodes.get(&node_id) {
-            let result = match node.node_type {
-                NodeType::Sequence => self.evaluate_sequence(node, context),
-                NodeType::Selector => self.evaluate_selector(node, context),
-                NodeType::Inverter => self.evaluate_inverter(node, context),
-                NodeType::Succeeder => self.evaluate_succeeder(node, context),
-                NodeType::Repeater => self.evaluate_repeater(node, context, node_id),
-                NodeType::Parallel => self.evaluate_parallel(node, context),
-                NodeType::Condition => self.evaluate_condition(node, context),
-                NodeType::Action => self.evaluate_action(node, context),
-            };
-            
-            // Store the result in the context for composite nodes
-            context.set_value(&format!("node_{}", node_id), match result {
-                NodeStatus::Success => 1.0,
-                NodeStatus::Failure => 0.0,
-                NodeStatus::Running => 2.0,
-            });
-            
-            result
-        } else {
-            NodeStatus::Failure
+    fn acquire_attack_token(&mut self) -> bool {
+        if self.holds_attack_token {
+            return true;
+        }
+        match &self.attack_coordinator {
+            Some(coordinator) => {
+                self.holds_attack_token = coordinator.try_acquire();
+                self.holds_attack_token
+            },
+            None => true,
         }
     }
 
-    fn evaluate_sequence(&self, node: &Node, context: &mut BehaviorContext) -> NodeStatus {
-        for &child_id in &node.children {
-            match self.evaluate_node(child_id, context) {
-                NodeStatus::Failure => return NodeStatus::Failure,
-                NodeStatus::Running => return NodeStatus::Running,
-                NodeStatus::Success => continue,
+    fn release_attack_token(&mut self) {
+        if self.holds_attack_token {
+            if let Some(coordinator) = &self.attack_coordinator {
+                coordinator.release();
             }
+            self.holds_attack_token = false;
         }
-        NodeStatus::Success
     }
 
-    fn evaluate_selector(&self, node: &Node, context: &mut BehaviorContext) -> NodeStatus {
-        for &child_id in &node.children {
-            match self.evaluate_node(child_id, context) {
-                NodeStatus::Success => return NodeStatus::Success,
-                NodeStatus::Running => return NodeStatus::Running,
-                NodeStatus::Failure => continue,
-            }
-        }
-        NodeStatus::Failure
+    fn begin_evaluation(&mut self) {
+        self.output = BehaviorOutput::default();
+        self.attacked = false;
     }
 
-    fn evaluate_inverter(&self, node: &Node, context: &mut BehaviorContext) -> NodeStatus {
-        if node.children.is_empty() {
-            return NodeStatus::Failure;
-        }
-        
-        match self.evaluate_node(node.children[0], context) {
-            NodeStatus::Success => NodeStatus::Failure,
-            NodeStatus::Failure => NodeStatus::Success,
-            NodeStatus::Running => NodeStatus::Running,
+    // Expire the attack token of an entity that did not attack this evaluation
+    fn end_evaluation(&mut self) {
+        if !self.attacked {
+            self.release_attack_token();
         }
     }
+}
 
-    fn evaluate_succeeder(&self, node: &Node, context: &mut BehaviorContext) -> NodeStatus {
+impl Default for BehaviorContext {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Serialized form of a BehaviorContext's own state
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+struct ContextSnapshot {
+    values: HashMap<String, f64>,
+    target: [f64; 3],
+    target_velocity: [f64; 3],
+    entity: [f64; 3],
+    home: [f64; 3],
+    entity_health: f64,
+    entity_max_health: f64,
+    displayed_health: f64,
+    last_damage_time: Option<f64>,
+    suppression: f64,
+    suppression_decay: f64,
+    entity_type: u32,
+    elapsed_time: f64,
+    think_interval: f64,
+    think_accumulator: f64,
+    hearing_range: f64,
+    path_reachable: bool,
+    rng_state: u64,
+}
+
+impl ContextSnapshot {
+    fn capture(context: &BehaviorContext) -> ContextSnapshot {
+        ContextSnapshot {
+            values: context.values.clone(),
+            target: [context.target_x, context.target_y, context.target_z],
+            target_velocity: context.target_velocity,
+            entity: [context.entity_x, context.entity_y, context.entity_z],
+            home: [context.home_x, context.home_y, context.home_z],
+            entity_health: context.entity_health,
+            entity_max_health: context.entity_max_health,
+            displayed_health: context.displayed_health,
+            last_damage_time: context.last_damage_time,
+            suppression: context.suppression,
+            suppression_decay: context.suppression_decay,
+            entity_type: context.entity_type,
+            elapsed_time: context.elapsed_time,
+            think_interval: context.think_interval,
+            think_accumulator: context.think_accumulator,
+            hearing_range: context.hearing_range,
+            path_reachable: context.path_reachable,
+            rng_state: context.rng.state(),
+        }
+    }
+
+    fn restore(self) -> BehaviorContext {
+        BehaviorContext {
+            values: self.values,
+            target_x: self.target[0],
+            target_y: self.target[1],
+            target_z: self.target[2],
+            target_velocity: self.target_velocity,
+            entity_x: self.entity[0],
+            entity_y: self.entity[1],
+            entity_z: self.entity[2],
+            home_x: self.home[0],
+            home_y: self.home[1],
+            home_z: self.home[2],
+            entity_health: self.entity_health,
+            entity_max_health: self.entity_max_health,
+            displayed_health: self.displayed_health,
+            last_damage_time: self.last_damage_time,
+            suppression: self.suppression,
+            suppression_decay: self.suppression_decay,
+            entity_type: self.entity_type,
+            elapsed_time: self.elapsed_time,
+            think_interval: self.think_interval,
+            think_accumulator: self.think_accumulator,
+            hearing_range: self.hearing_range,
+            path_reachable: self.path_reachable,
+            rng: Rng::new(self.rng_state),
+            ..BehaviorContext::new()
+        }
+    }
+}
+
+impl Default for ContextSnapshot {
+    fn default() -> Self {
+        ContextSnapshot::capture(&BehaviorContext::new())
+    }
+}
+
+// Complexity summary of a behavior tree, counted over nodes reachable from the root
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct TreeStats {
+    pub node_count: usize,
+    pub max_depth: usize,
+    pub leaf_count: usize,
+    type_counts: [usize; NODE_TYPE_COUNT],
+}
+
+#[wasm_bindgen]
+impl TreeStats {
+    // Number of reachable nodes of the given type
+    pub fn count_of(&self, node_type: NodeType) -> usize {
+        self.type_counts[node_type as usize]
+    }
+}
+
+// AI behavior tree for enemy decision making
+#[wasm_bindgen]
+#[derive(Serialize, Deserialize)]
+pub struct BehaviorTree {
+    root_id: usize,
+    nodes: HashMap<usize, Node>,
+    next_id: usize,
+    // Action run when evaluation fails, so the entity idles instead of freezing
+    #[serde(default)]
+    default_action: Option<(u32, f64)>,
+}
+
+impl Default for BehaviorTree {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Explicit-stack frame used by the iterative evaluator
+struct Frame {
+    node_id: usize,
+    next_child: usize,
+    success_count: usize,
+    failure_count: usize,
+    slot: usize,
+    resume_index: Option<usize>,
+    settled: Option<NodeStatus>,
+}
+
+impl Frame {
+    fn new(node_id: usize) -> Frame {
+        Frame {
+            node_id,
+            next_child: 0,
+            success_count: 0,
+            failure_count: 0,
+            slot: 0,
+            resume_index: None,
+            settled: None,
+        }
+    }
+
+    fn next_child(&mut self, node: &Node) -> Option<usize> {
+        let child_id = node.children.get(self.next_child).copied();
+        self.next_child += 1;
+        child_id
+    }
+}
+
+enum Step {
+    Descend(usize),
+    Done(NodeStatus),
+}
+
+fn status_code(status: NodeStatus) -> i32 {
+    match status {
+        NodeStatus::Success => 1,
+        NodeStatus::Failure => 0,
+        NodeStatus::Running => 2,
+    }
+}
+
+// Whether `key` has the shape `shape`, where each `#` matches one or more
+// ASCII digits and everything else matches literally
+fn key_matches_shape(key: &str, shape: &str) -> bool {
+    let mut rest = key;
+    for (index, literal) in shape.split('#').enumerate() {
+        if index > 0 {
+            let digits = rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+            if digits == 0 {
+                return false;
+            }
+            rest = &rest[digits..];
+        }
+        match rest.strip_prefix(literal) {
+            Some(remaining) => rest = remaining,
+            None => return false,
+        }
+    }
+    rest.is_empty()
+}
+
+fn status_from_code(code: f64) -> Option<NodeStatus> {
+    match code as i32 {
+        1 => Some(NodeStatus::Success),
+        0 => Some(NodeStatus::Failure),
+        2 => Some(NodeStatus::Running),
+        _ => None,
+    }
+}
+
+// Store the result in the context so tools can inspect it after evaluation
+fn store_status(context: &mut BehaviorContext, node_id: usize, status: NodeStatus) {
+    context.set_value(&format!("node_{}", node_id), status_code(status) as f64);
+}
+
+fn parallel_result(node: &Node, success_count: usize, failure_count: usize) -> NodeStatus {
+    if success_count >= node.success_threshold {
+        NodeStatus::Success
+    } else if failure_count > node.children.len() - node.success_threshold {
+        NodeStatus::Failure
+    } else {
+        NodeStatus::Running
+    }
+}
+
+// Result of a node that ticks all of its children: Parallel or Race
+fn concurrent_result(node: &Node, success_count: usize, failure_count: usize) -> NodeStatus {
+    match node.node_type {
+        NodeType::Race => race_result(node, success_count, failure_count),
+        _ => parallel_result(node, success_count, failure_count),
+    }
+}
+
+// Race: the first success wins; fails only once every child has failed
+fn race_result(node: &Node, success_count: usize, failure_count: usize) -> NodeStatus {
+    if success_count > 0 {
+        NodeStatus::Success
+    } else if failure_count == node.children.len() {
+        NodeStatus::Failure
+    } else {
+        NodeStatus::Running
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+struct Node {
+    node_type: NodeType,
+    children: Vec<usize>,
+    condition_type: u32,
+    action_type: u32,
+    parameter: f64,
+    success_threshold: usize,
+    repeat_times: usize,
+    // Additional per-node parameters for node kinds that need more than one
+    extra_parameters: Vec<f64>,
+    // Context keys read by blackboard conditions
+    keys: Vec<String>,
+    compare_op: CompareOp,
+    // Re-checked ahead of a running sibling under a memory selector
+    interrupting: bool,
+    // Sequences and selectors run every child instead of short-circuiting
+    evaluate_all_children: bool,
+    // Conditions report the opposite of their check
+    negate: bool,
+    // Named user data for custom action handlers
+    data: HashMap<String, f64>,
+}
+
+impl Default for Node {
+    fn default() -> Self {
+        Node::new(NodeType::Sequence)
+    }
+}
+
+impl Node {
+    fn new(node_type: NodeType) -> Node {
+        Node {
+            node_type,
+            children: Vec::new(),
+            condition_type: 0,
+            action_type: 0,
+            parameter: 0.0,
+            success_threshold: 0,
+            repeat_times: 0,
+            extra_parameters: Vec::new(),
+            keys: Vec::new(),
+            compare_op: CompareOp::Equal,
+            interrupting: false,
+            evaluate_all_children: false,
+            negate: false,
+            data: HashMap::new(),
+        }
+    }
+
+    // Key parameters shown by to_outline
+    fn outline_details(&self) -> String {
+        let mut details = match self.node_type {
+            NodeType::Condition => format!(" condition={} param={}", self.condition_type, self.parameter),
+            NodeType::Action => format!(" action={} param={}", self.action_type, self.parameter),
+            NodeType::Parallel => format!(" threshold={}", self.success_threshold),
+            NodeType::Repeater => format!(" times={}", self.repeat_times),
+            NodeType::Timeout => format!(" max_ticks={}", self.repeat_times),
+            NodeType::Commit => format!(" min_ticks={}", self.repeat_times),
+            NodeType::RateLimit => format!(" max={} window={}", self.success_threshold, self.parameter),
+            _ => String::new(),
+        };
+        if !self.extra_parameters.is_empty() {
+            details.push_str(&format!(" extra={:?}", self.extra_parameters));
+        }
+        if !self.keys.is_empty() {
+            details.push_str(&format!(" keys={:?} op={:?}", self.keys, self.compare_op));
+        }
+        if self.negate {
+            details.push_str(" negated");
+        }
+        if self.interrupting {
+            details.push_str(" interrupting");
+        }
+        if self.evaluate_all_children {
+            details.push_str(" all_children");
+        }
+        details
+    }
+
+    fn extra_parameter(&self, index: usize) -> f64 {
+        self.extra_parameters.get(index).copied().unwrap_or(0.0)
+    }
+}
+
+#[wasm_bindgen]
+impl BehaviorTree {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> BehaviorTree {
+        BehaviorTree {
+            root_id: 0,
+            nodes: HashMap::new(),
+            next_id: 0,
+            default_action: None,
+        }
+    }
+
+    // Action (type code and parameter) to run whenever the tree evaluates to
+    // Failure, including when no valid root is set. The returned status is
+    // still Failure.
+    pub fn set_default_action(&mut self, action_type: u32, parameter: f64) {
+        self.default_action = Some((action_type, parameter));
+    }
+
+    pub fn clear_default_action(&mut self) {
+        self.default_action = None;
+    }
+
+    pub fn create_sequence_node(&mut self) -> usize {
+        self.insert_node(Node::new(NodeType::Sequence))
+    }
+
+    pub fn create_selector_node(&mut self) -> usize {
+        self.insert_node(Node::new(NodeType::Selector))
+    }
+
+    // Selector that remembers a Running child and resumes it on the next tick
+    // instead of re-checking higher-priority children. Children flagged with
+    // `set_interrupting` are still re-checked first and abort the running
+    // branch if they succeed or start running.
+    pub fn create_memory_selector_node(&mut self) -> usize {
+        self.insert_node(Node::new(NodeType::MemorySelector))
+    }
+
+    pub fn create_inverter_node(&mut self) -> usize {
+        self.insert_node(Node::new(NodeType::Inverter))
+    }
+
+    pub fn create_succeeder_node(&mut self) -> usize {
+        self.insert_node(Node::new(NodeType::Succeeder))
+    }
+
+    pub fn create_repeater_node(&mut self, times: usize) -> usize {
+        self.insert_node(Node {
+            repeat_times: times,
+            ..Node::new(NodeType::Repeater)
+        })
+    }
+
+    // Pass the child's result through for at most `max_ticks` consecutive
+    // evaluations while it keeps Running, then force Failure and start over
+    pub fn create_timeout_node(&mut self, max_ticks: u32) -> usize {
+        self.insert_node(Node {
+            repeat_times: max_ticks as usize,
+            ..Node::new(NodeType::Timeout)
+        })
+    }
+
+    // Once the child settles on an action, keep that action for at least
+    // `min_ticks` evaluations before letting the child switch to a different
+    // one. Wrap a top-level selector with it to stop enemies at the edge of a
+    // range flipping between moving and attacking. The child still runs every
+    // tick; while committed, a switch is overridden by replaying the outputs
+    // and status saved when the action was chosen, not by re-running it.
+    pub fn create_commit_node(&mut self, min_ticks: u32) -> usize {
+        self.insert_node(Node {
+            repeat_times: min_ticks as usize,
+            ..Node::new(NodeType::Commit)
+        })
+    }
+
+    // Tick every child each evaluation and succeed as soon as any of them
+    // succeeds. Unlike a selector, later children still run on the same tick
+    // instead of being skipped, and unlike a parallel node there is no
+    // threshold: one success wins, and it fails only when all children fail.
+    pub fn create_race_node(&mut self) -> usize {
+        self.insert_node(Node::new(NodeType::Race))
+    }
+
+    pub fn create_parallel_node(&mut self, success_threshold: usize) -> usize {
+        self.insert_node(Node {
+            success_threshold,
+            ..Node::new(NodeType::Parallel)
+        })
+    }
+
+    // Allow the child to succeed at most `max_successes` times per sliding
+    // `window_seconds`, measured on the context clock advanced by `tick`
+    pub fn create_rate_limit_node(&mut self, max_successes: u32, window_seconds: f64) -> usize {
+        self.insert_node(Node {
+            parameter: window_seconds,
+            success_threshold: max_successes as usize,
+            ..Node::new(NodeType::RateLimit)
+        })
+    }
+
+    pub fn create_condition_node(&mut self, condition_type: u32, parameter: f64) -> usize {
+        self.insert_node(Node {
+            condition_type,
+            parameter,
+            ..Node::new(NodeType::Condition)
+        })
+    }
+
+    // Condition whose Success and Failure are swapped, in place of wrapping
+    // it in an inverter
+    pub fn create_condition_node_negated(&mut self, condition_type: u32, parameter: f64) -> usize {
+        self.insert_node(Node {
+            condition_type,
+            parameter,
+            negate: true,
+            ..Node::new(NodeType::Condition)
+        })
+    }
+
+    pub fn create_action_node(&mut self, action_type: u32, parameter: f64) -> usize {
+        self.insert_node(Node {
+            action_type,
+            parameter,
+            ..Node::new(NodeType::Action)
+        })
+    }
+
+    // Attack whose damage falls off linearly from `damage` at point-blank
+    // range to `min_damage` at `max_range` and beyond
+    pub fn create_falloff_attack_node(&mut self, damage: f64, min_damage: f64, max_range: f64) -> usize {
+        self.insert_node(Node {
+            action_type: 7,
+            parameter: damage,
+            extra_parameters: vec![min_damage, max_range],
+            ..Node::new(NodeType::Action)
+        })
+    }
+
+    // Condition comparing two context values, e.g. "ammo < max_ammo"
+    pub fn create_compare_condition_node(&mut self, left_key: &str, op: CompareOp, right_key: &str) -> usize {
+        self.insert_node(Node {
+            condition_type: 7,
+            keys: vec![left_key.to_string(), right_key.to_string()],
+            compare_op: op,
+            ..Node::new(NodeType::Condition)
+        })
+    }
+
+    // Keep the target within [min_range, max_range]: approach when too far,
+    // back off when too close and strafe sideways inside the band.
+    // The movement is written to move_x/move_y/move_z scaled by `speed`.
+    pub fn create_strafe_node(&mut self, min_range: f64, max_range: f64, speed: f64) -> usize {
+        self.insert_node(Node {
+            action_type: 8,
+            parameter: speed,
+            extra_parameters: vec![min_range, max_range],
+            ..Node::new(NodeType::Action)
+        })
+    }
+
+    // Arrival check with hysteresis: succeeds once within `arrive_radius` and
+    // keeps succeeding until the target is farther than `leave_radius`, so
+    // small movements do not immediately re-trigger pursuit
+    pub fn create_arrival_hysteresis_node(&mut self, arrive_radius: f64, leave_radius: f64) -> usize {
+        self.insert_node(Node {
+            condition_type: 10,
+            parameter: arrive_radius,
+            extra_parameters: vec![leave_radius],
+            ..Node::new(NodeType::Condition)
+        })
+    }
+
+    // Target distance within [min_range, max_range], for attacks that cannot
+    // be used on a target that is too close
+    pub fn create_range_band_condition_node(&mut self, min_range: f64, max_range: f64) -> usize {
+        self.insert_node(Node {
+            condition_type: 13,
+            parameter: min_range,
+            extra_parameters: vec![max_range],
+            ..Node::new(NodeType::Condition)
+        })
+    }
+
+    // Health fraction <= `health_threshold`, like condition 1, that also
+    // queues a PhaseChange event on the tick its result flips: carrying
+    // `phase` when health drops into it and `phase - 1` if health recovers.
+    // Before its first evaluation the entity counts as outside the phase.
+    pub fn create_phase_condition_node(&mut self, health_threshold: f64, phase: u32) -> usize {
+        self.insert_node(Node {
+            condition_type: 18,
+            parameter: health_threshold,
+            extra_parameters: vec![phase as f64],
+            ..Node::new(NodeType::Condition)
+        })
+    }
+
+    // Heal `amount` per evaluation (capped at max health), but only once
+    // `delay` seconds have passed without damage; fails while blocked
+    pub fn create_regen_node(&mut self, amount: f64, delay: f64) -> usize {
+        self.insert_node(Node {
+            action_type: 11,
+            parameter: amount,
+            extra_parameters: vec![delay],
+            ..Node::new(NodeType::Action)
+        })
+    }
+
+    // Start cooldown `cooldown_id` for `duration` seconds; pair with
+    // condition 4 on the same id to gate the ability it guards
+    pub fn create_cooldown_node(&mut self, cooldown_id: u32, duration: f64) -> usize {
+        self.insert_node(Node {
+            action_type: 5,
+            parameter: cooldown_id as f64,
+            extra_parameters: vec![duration],
+            ..Node::new(NodeType::Action)
+        })
+    }
+
+    // Start the shared cooldown of an ability group; pair with condition 15
+    // on the same group to keep its abilities from firing back-to-back
+    pub fn create_group_cooldown_node(&mut self, group: u32, duration: f64) -> usize {
+        self.insert_node(Node {
+            action_type: 12,
+            parameter: group as f64,
+            extra_parameters: vec![duration],
+            ..Node::new(NodeType::Action)
+        })
+    }
+
+    // Rush the target at `speed` (Running), then once within `attack_range`
+    // deal `damage` as a ChargeHit event and succeed. `charge_{id}_active` is
+    // 1 while the charge is under way; a target already in range when no
+    // charge is under way fails the node instead of being hit.
+    pub fn create_charge_node(&mut self, speed: f64, attack_range: f64, damage: f64) -> usize {
+        self.insert_node(Node {
+            action_type: 14,
+            parameter: speed,
+            extra_parameters: vec![attack_range, damage],
+            ..Node::new(NodeType::Action)
+        })
+    }
+
+    // Attack in three timed phases (seconds on the context clock): Running
+    // through `windup`, Success with an AttackHit event for `damage` on the
+    // first evaluation inside the `active` window, then Running through the
+    // rest of it and `recovery`, which keep the next attack from starting.
+    // An attack not evaluated during its active window misses.
+    pub fn create_timed_attack_node(&mut self, damage: f64, windup: f64, active: f64, recovery: f64) -> usize {
+        self.insert_node(Node {
+            action_type: 15,
+            parameter: damage,
+            extra_parameters: vec![windup, active, recovery],
+            ..Node::new(NodeType::Action)
+        })
+    }
+
+    // Queue a ProjectileSpawn event aimed at the target at `speed`, leading
+    // a moving target when `lead` is set
+    pub fn create_projectile_node(&mut self, speed: f64, lead: bool) -> usize {
+        self.insert_node(Node {
+            action_type: 13,
+            parameter: speed,
+            extra_parameters: vec![if lead { 1.0 } else { 0.0 }],
+            ..Node::new(NodeType::Action)
+        })
+    }
+
+    pub fn add_child(&mut self, parent_id: usize, child_id: usize) {
+        if let Some(parent) = self.nodes.get_mut(&parent_id) {
+            parent.children.push(child_id);
+        }
+    }
+
+    // Mark a node as able to interrupt a running lower-priority sibling
+    // under a memory selector
+    pub fn set_interrupting(&mut self, node_id: usize, interrupting: bool) {
+        if let Some(node) = self.nodes.get_mut(&node_id) {
+            node.interrupting = interrupting;
+        }
+    }
+
+    // Swap a condition's Success and Failure; Running is passed through
+    pub fn set_negated(&mut self, node_id: usize, negate: bool) {
+        if let Some(node) = self.nodes.get_mut(&node_id) {
+            node.negate = negate;
+        }
+    }
+
+    // Attach a named value to a node for custom action handlers
+    pub fn set_node_data(&mut self, node_id: usize, key: &str, value: f64) {
+        if let Some(node) = self.nodes.get_mut(&node_id) {
+            node.data.insert(key.to_string(), value);
+        }
+    }
+
+    // Value stored by set_node_data, or 0.0 when unset
+    pub fn node_data(&self, node_id: usize, key: &str) -> f64 {
+        self.nodes
+            .get(&node_id)
+            .and_then(|node| node.data.get(key).copied())
+            .unwrap_or(0.0)
+    }
+
+    // Every node ID in the tree, reachable from the root or not, in ascending order
+    pub fn node_ids(&self) -> Vec<u32> {
+        let mut ids: Vec<u32> = self.nodes.keys().map(|&id| id as u32).collect();
+        ids.sort_unstable();
+        ids
+    }
+
+    // NodeType discriminant of a node, or -1 if there is no such node
+    pub fn node_type(&self, node_id: usize) -> i32 {
+        self.nodes.get(&node_id).map_or(-1, |node| node.node_type as i32)
+    }
+
+    // Serialize the tree structure, node parameters and node data
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+
+    // Rebuild a tree saved with `to_json`. Fails when a root or child id
+    // names a missing node; new nodes get ids above every loaded one.
+    pub fn from_json(json: &str) -> Result<BehaviorTree, JsValue> {
+        BehaviorTree::parse_json(json).map_err(|err| JsValue::from_str(&err))
+    }
+
+    // `from_json` without the JS error type, usable outside wasm
+    pub(crate) fn parse_json(json: &str) -> Result<BehaviorTree, String> {
+        let mut tree: BehaviorTree = serde_json::from_str(json).map_err(|err| err.to_string())?;
+        if !tree.nodes.is_empty() && !tree.nodes.contains_key(&tree.root_id) {
+            return Err(format!("root references missing node {}", tree.root_id));
+        }
+        for (id, node) in &tree.nodes {
+            if let Some(child) = node.children.iter().find(|child| !tree.nodes.contains_key(child)) {
+                return Err(format!("node {} references missing child {}", id, child));
+            }
+        }
+        tree.next_id = tree.nodes.keys().max().map_or(0, |&id| id + 1);
+        Ok(tree)
+    }
+
+    // New tree whose root joins the roots of `a` and `b` under a sequence
+    // (mode 0), selector (mode 1) or parallel requiring both (mode 2); other
+    // modes fall back to a sequence. Nodes are copied with fresh IDs, and a
+    // tree without a valid root contributes nothing.
+    pub fn combine(a: &BehaviorTree, b: &BehaviorTree, mode: u32) -> BehaviorTree {
+        let mut tree = BehaviorTree::new();
+        let children: Vec<usize> = [a, b].into_iter().filter_map(|source| tree.append_tree(source)).collect();
+        let root = match mode {
+            1 => tree.create_selector_node(),
+            2 => tree.create_parallel_node(children.len()),
+            _ => tree.create_sequence_node(),
+        };
+        for child_id in children {
+            tree.add_child(root, child_id);
+        }
+        tree.set_root(root);
+        tree.default_action = a.default_action.or(b.default_action);
+        tree
+    }
+
+    // Make a sequence or selector run all of its children every evaluation
+    // for their side effects. The result is still the status of the first
+    // child that would have short-circuited.
+    pub fn set_evaluate_all_children(&mut self, node_id: usize, evaluate_all: bool) {
+        if let Some(node) = self.nodes.get_mut(&node_id) {
+            node.evaluate_all_children = evaluate_all;
+        }
+    }
+
+    // Create a sequence node and attach the given children in order
+    pub fn create_sequence_with_children(&mut self, children: &[usize]) -> usize {
+        let id = self.create_sequence_node();
+        for &child_id in children {
+            self.add_child(id, child_id);
+        }
+        id
+    }
+
+    // Create a selector node and attach the given children in priority order
+    pub fn create_selector_with_children(&mut self, children: &[usize]) -> usize {
+        let id = self.create_selector_node();
+        for &child_id in children {
+            self.add_child(id, child_id);
+        }
+        id
+    }
+
+    pub fn set_root(&mut self, node_id: usize) {
+        self.root_id = node_id;
+    }
+
+    fn insert_node(&mut self, node: Node) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.nodes.insert(id, node);
+        id
+    }
+
+    // Copy every node of `source` into this tree under new IDs, returning the
+    // new ID of its root, or None when it has no valid root
+    fn append_tree(&mut self, source: &BehaviorTree) -> Option<usize> {
+        if !source.nodes.contains_key(&source.root_id) {
+            return None;
+        }
+
+        let mut source_ids: Vec<usize> = source.nodes.keys().copied().collect();
+        source_ids.sort_unstable();
+        let remap: HashMap<usize, usize> = source_ids
+            .iter()
+            .enumerate()
+            .map(|(offset, &id)| (id, self.next_id + offset))
+            .collect();
+
+        for id in source_ids {
+            let mut node = source.nodes[&id].clone();
+            node.children = node.children.iter().filter_map(|child| remap.get(child).copied()).collect();
+            self.insert_node(node);
+        }
+        remap.get(&source.root_id).copied()
+    }
+
+    // Clear cooldowns, repeater counts, cached node results, the last chosen
+    // action and other transient state from the context, i.e. every key
+    // matching RUNTIME_KEY_SHAPES, e.g. when an enemy is recycled from a pool.
+    // Position, health and user-set values are preserved.
+    pub fn reset_runtime_state(&self, context: &mut BehaviorContext) {
+        let runtime_keys: Vec<String> = context
+            .values
+            .keys()
+            .filter(|key| RUNTIME_KEY_SHAPES.iter().any(|shape| key_matches_shape(key, shape)))
+            .cloned()
+            .collect();
+        for key in runtime_keys {
+            context.remove_value(&key);
+        }
+        context.release_attack_token();
+        context.last_damage_time = None;
+        context.suppression = 0.0;
+    }
+
+    // Status a node produced during the most recent evaluation, or None if it
+    // was not reached since the runtime state was last cleared
+    pub fn last_status(&self, context: &BehaviorContext, node_id: usize) -> Option<NodeStatus> {
+        context
+            .values
+            .get(&format!("node_{}", node_id))
+            .and_then(|&code| status_from_code(code))
+    }
+
+    // Count nodes, leaves and per-type totals and measure depth from the root
+    pub fn stats(&self) -> TreeStats {
+        let mut stats = TreeStats {
+            node_count: 0,
+            max_depth: 0,
+            leaf_count: 0,
+            type_counts: [0; NODE_TYPE_COUNT],
+        };
+        let mut visited = HashSet::new();
+        let mut stack = vec![(self.root_id, 1)];
+
+        while let Some((node_id, depth)) = stack.pop() {
+            // Guard against shared children and cycles
+            if !visited.insert(node_id) {
+                continue;
+            }
+            let node = match self.nodes.get(&node_id) {
+                Some(node) => node,
+                None => continue,
+            };
+
+            stats.node_count += 1;
+            stats.max_depth = stats.max_depth.max(depth);
+            stats.type_counts[node.node_type as usize] += 1;
+            if node.children.is_empty() {
+                stats.leaf_count += 1;
+            }
+            for &child_id in &node.children {
+                stack.push((child_id, depth + 1));
+            }
+        }
+
+        stats
+    }
+
+    // Farthest target distance at which the tree can attack, over nodes
+    // reachable from the root: the largest range condition (types 0 and 13)
+    // gating an attack action (types 1, 7, 13, 14 and 15) in the same
+    // sequence, and the strike range of any charge. 0 when no attack is
+    // range-gated.
+    pub(crate) fn attack_range(&self) -> f64 {
+        let reachable = self.reachable_ids();
+        let mut range: f64 = 0.0;
+        for node in reachable.iter().filter_map(|id| self.nodes.get(id)) {
+            if matches!(node.node_type, NodeType::Action) && node.action_type == 14 {
+                range = range.max(node.extra_parameter(0));
+            }
+            if !matches!(node.node_type, NodeType::Sequence) {
+                continue;
+            }
+            let children: Vec<&Node> = node.children.iter().filter_map(|id| self.nodes.get(id)).collect();
+            let attacks = children
+                .iter()
+                .any(|child| matches!(child.node_type, NodeType::Action) && matches!(child.action_type, 1 | 7 | 13 | 14 | 15));
+            if !attacks {
+                continue;
+            }
+            for child in children.iter().filter(|child| matches!(child.node_type, NodeType::Condition) && !child.negate) {
+                match child.condition_type {
+                    0 => range = range.max(child.parameter),
+                    13 => range = range.max(child.extra_parameter(0)),
+                    _ => {},
+                }
+            }
+        }
+        range
+    }
+
+    fn reachable_ids(&self) -> HashSet<usize> {
+        let mut visited = HashSet::new();
+        let mut stack = vec![self.root_id];
+        while let Some(node_id) = stack.pop() {
+            if let Some(node) = self.nodes.get(&node_id) {
+                if visited.insert(node_id) {
+                    stack.extend(&node.children);
+                }
+            }
+        }
+        visited
+    }
+
+    // Indented text dump of the tree from the root, one node per line with
+    // its id and key parameters. A node reached a second time (a shared child
+    // or a cycle) is marked "(repeat)" and not expanded again.
+    pub fn to_outline(&self) -> String {
+        let mut outline = String::new();
+        let mut visited = HashSet::new();
+        let mut stack = vec![(self.root_id, 0)];
+
+        while let Some((node_id, depth)) = stack.pop() {
+            let indent = "  ".repeat(depth);
+            let node = match self.nodes.get(&node_id) {
+                Some(node) => node,
+                None => {
+                    outline.push_str(&format!("{}Missing #{}\n", indent, node_id));
+                    continue;
+                },
+            };
+            if !visited.insert(node_id) {
+                outline.push_str(&format!("{}{:?} #{} (repeat)\n", indent, node.node_type, node_id));
+                continue;
+            }
+
+            outline.push_str(&format!("{}{:?} #{}{}\n", indent, node.node_type, node_id, node.outline_details()));
+            for &child_id in node.children.iter().rev() {
+                stack.push((child_id, depth + 1));
+            }
+        }
+        outline
+    }
+
+    pub fn evaluate(&self, context: &mut BehaviorContext) -> i32 {
+        context.begin_evaluation();
+        let status = self.evaluate_node(self.root_id, context);
+        self.apply_default_action(context, status);
+        context.end_evaluation();
+        status_code(status)
+    }
+
+    // Evaluate from `node_id` as if it were the root, leaving `root_id`
+    // untouched, to exercise one branch in isolation. The default action is
+    // not applied.
+    pub fn evaluate_subtree(&self, node_id: usize, context: &mut BehaviorContext) -> i32 {
+        context.begin_evaluation();
+        let status = self.evaluate_node(node_id, context);
+        context.end_evaluation();
+        status_code(status)
+    }
+
+    // Evaluate with an explicit stack instead of native recursion, for deep
+    // trees on WASM. Produces the same results as `evaluate`, which stays the
+    // reference implementation.
+    pub fn evaluate_iterative(&self, context: &mut BehaviorContext) -> i32 {
+        context.begin_evaluation();
+        let status = self.evaluate_node_iterative(self.root_id, context);
+        self.apply_default_action(context, status);
+        context.end_evaluation();
+        status_code(status)
+    }
+
+    fn evaluate_node(&self, node_id: usize, context: &mut BehaviorContext) -> NodeStatus {
+        if let Some(node) = self.nodes.get(&node_id) {
+            let result = match node.node_type {
+                NodeType::Sequence => self.evaluate_sequence(node, context),
+                NodeType::Selector => self.evaluate_selector(node, context),
+                NodeType::MemorySelector => self.evaluate_memory_selector(node, context, node_id),
+                NodeType::Inverter => self.evaluate_inverter(node, context),
+                NodeType::Succeeder => self.evaluate_succeeder(node, context),
+                NodeType::Repeater => self.evaluate_repeater(node, context, node_id),
+                NodeType::Parallel | NodeType::Race => self.evaluate_parallel(node, context),
+                NodeType::RateLimit => self.evaluate_rate_limit(node, context, node_id),
+                NodeType::Timeout => self.evaluate_timeout(node, context, node_id),
+                NodeType::Commit => self.evaluate_commit(node, context, node_id),
+                NodeType::Condition => self.evaluate_condition(node, context, node_id),
+                NodeType::Action => self.evaluate_action(node, context, node_id),
+            };
+            
+            store_status(context, node_id, result);
+            result
+        } else {
+            NodeStatus::Failure
+        }
+    }
+
+    fn evaluate_node_iterative(&self, node_id: usize, context: &mut BehaviorContext) -> NodeStatus {
+        let mut stack = vec![Frame::new(node_id)];
+        let mut child_result = None;
+
+        while let Some(frame) = stack.last_mut() {
+            let node = match self.nodes.get(&frame.node_id) {
+                Some(node) => node,
+                None => {
+                    stack.pop();
+                    child_result = Some(NodeStatus::Failure);
+                    continue;
+                }
+            };
+
+            match self.step_node(node, frame, child_result.take(), context) {
+                Step::Descend(child_id) => stack.push(Frame::new(child_id)),
+                Step::Done(status) => {
+                    let finished_id = frame.node_id;
+                    stack.pop();
+                    store_status(context, finished_id, status);
+                    child_result = Some(status);
+                },
+            }
+        }
+
+        child_result.unwrap_or(NodeStatus::Failure)
+    }
+
+    // Advance one node of the iterative evaluator. `child_result` is the status
+    // of the child it descended into last, or None on first entry.
+    fn step_node(
+        &self,
+        node: &Node,
+        frame: &mut Frame,
+        child_result: Option<NodeStatus>,
+        context: &mut BehaviorContext,
+    ) -> Step {
+        match node.node_type {
+            NodeType::Sequence if node.evaluate_all_children => {
+                self.step_every_child(node, frame, child_result, NodeStatus::Success)
+            },
+            NodeType::Selector if node.evaluate_all_children => {
+                self.step_every_child(node, frame, child_result, NodeStatus::Failure)
+            },
+            NodeType::Sequence => match child_result {
+                Some(NodeStatus::Failure) => Step::Done(NodeStatus::Failure),
+                Some(NodeStatus::Running) => Step::Done(NodeStatus::Running),
+                _ => frame.next_child(node).map_or(Step::Done(NodeStatus::Success), Step::Descend),
+            },
+            NodeType::Selector => match child_result {
+                Some(NodeStatus::Success) => Step::Done(NodeStatus::Success),
+                Some(NodeStatus::Running) => Step::Done(NodeStatus::Running),
+                _ => frame.next_child(node).map_or(Step::Done(NodeStatus::Failure), Step::Descend),
+            },
+            NodeType::MemorySelector => {
+                let resume_key = format!("resume_{}", frame.node_id);
+                match child_result {
+                    None => frame.resume_index = self.resume_index(context, frame.node_id),
+                    Some(NodeStatus::Success) => {
+                        context.set_value(&resume_key, 0.0);
+                        return Step::Done(NodeStatus::Success);
+                    },
+                    Some(NodeStatus::Running) => {
+                        context.set_value(&resume_key, frame.next_child as f64);
+                        return Step::Done(NodeStatus::Running);
+                    },
+                    Some(NodeStatus::Failure) => {},
+                }
+                while let Some(&child_id) = node.children.get(frame.next_child) {
+                    let index = frame.next_child;
+                    frame.next_child += 1;
+                    if !self.skipped_while_resuming(frame.resume_index, index, child_id) {
+                        return Step::Descend(child_id);
+                    }
+                }
+                context.set_value(&resume_key, 0.0);
+                Step::Done(NodeStatus::Failure)
+            },
+            NodeType::Inverter => match child_result {
+                None if node.children.is_empty() => Step::Done(NodeStatus::Failure),
+                None => Step::Descend(node.children[0]),
+                Some(NodeStatus::Success) => Step::Done(NodeStatus::Failure),
+                Some(NodeStatus::Failure) => Step::Done(NodeStatus::Success),
+                Some(NodeStatus::Running) => Step::Done(NodeStatus::Running),
+            },
+            NodeType::Succeeder => match child_result {
+                None if node.children.is_empty() => Step::Done(NodeStatus::Success),
+                None => Step::Descend(node.children[0]),
+                Some(NodeStatus::Running) => Step::Done(NodeStatus::Running),
+                Some(_) => Step::Done(NodeStatus::Success),
+            },
+            NodeType::Repeater => match child_result {
+                None if node.children.is_empty() => Step::Done(NodeStatus::Failure),
+                None => match self.begin_repeater(node, context, frame.node_id) {
+                    Some(status) => Step::Done(status),
+                    None => Step::Descend(node.children[0]),
+                },
+                Some(status) => Step::Done(self.finish_repeater(node, context, frame.node_id, status)),
+            },
+            NodeType::Timeout => match child_result {
+                None if node.children.is_empty() => Step::Done(NodeStatus::Failure),
+                None => match self.begin_timeout(node, context, frame.node_id) {
+                    Some(status) => Step::Done(status),
+                    None => Step::Descend(node.children[0]),
+                },
+                Some(status) => Step::Done(self.finish_timeout(context, frame.node_id, status)),
+            },
+            NodeType::Commit => match child_result {
+                None if node.children.is_empty() => Step::Done(NodeStatus::Failure),
+                None => {
+                    self.begin_commit(context, frame.node_id);
+                    Step::Descend(node.children[0])
+                },
+                Some(status) => Step::Done(self.finish_commit(node, context, frame.node_id, status)),
+            },
+            NodeType::Parallel | NodeType::Race => {
+                match child_result {
+                    Some(NodeStatus::Success) => frame.success_count += 1,
+                    Some(NodeStatus::Failure) => frame.failure_count += 1,
+                    _ => {},
+                }
+                match frame.next_child(node) {
+                    Some(child_id) => Step::Descend(child_id),
+                    None => Step::Done(concurrent_result(node, frame.success_count, frame.failure_count)),
+                }
+            },
+            NodeType::RateLimit => match child_result {
+                None if node.children.is_empty() => Step::Done(NodeStatus::Failure),
+                None => match self.rate_limit_free_slot(node, context, frame.node_id) {
+                    Some(slot) => {
+                        frame.slot = slot;
+                        Step::Descend(node.children[0])
+                    },
+                    None => Step::Done(NodeStatus::Failure),
+                },
+                Some(status) => Step::Done(self.finish_rate_limit(context, frame.node_id, frame.slot, status)),
+            },
+            NodeType::Condition => Step::Done(self.evaluate_condition(node, context, frame.node_id)),
+            NodeType::Action => Step::Done(self.evaluate_action(node, context, frame.node_id)),
+        }
+    }
+
+    fn evaluate_sequence(&self, node: &Node, context: &mut BehaviorContext) -> NodeStatus {
+        if node.evaluate_all_children {
+            return self.evaluate_every_child(node, context, NodeStatus::Success);
+        }
+        for &child_id in &node.children {
+            match self.evaluate_node(child_id, context) {
+                NodeStatus::Failure => return NodeStatus::Failure,
+                NodeStatus::Running => return NodeStatus::Running,
+                NodeStatus::Success => continue,
+            }
+        }
+        NodeStatus::Success
+    }
+
+    fn evaluate_selector(&self, node: &Node, context: &mut BehaviorContext) -> NodeStatus {
+        if node.evaluate_all_children {
+            return self.evaluate_every_child(node, context, NodeStatus::Failure);
+        }
+        for &child_id in &node.children {
+            match self.evaluate_node(child_id, context) {
+                NodeStatus::Success => return NodeStatus::Success,
+                NodeStatus::Running => return NodeStatus::Running,
+                NodeStatus::Failure => continue,
+            }
+        }
+        NodeStatus::Failure
+    }
+
+    // Run every child and report the first status other than `pass`, which
+    // is what the short-circuiting composite would have returned
+    fn evaluate_every_child(&self, node: &Node, context: &mut BehaviorContext, pass: NodeStatus) -> NodeStatus {
+        let mut result = pass;
+        for &child_id in &node.children {
+            let status = self.evaluate_node(child_id, context);
+            if result == pass {
+                result = status;
+            }
+        }
+        result
+    }
+
+    fn step_every_child(&self, node: &Node, frame: &mut Frame, child_result: Option<NodeStatus>, pass: NodeStatus) -> Step {
+        if let Some(status) = child_result {
+            if frame.settled.is_none() && status != pass {
+                frame.settled = Some(status);
+            }
+        }
+        match frame.next_child(node) {
+            Some(child_id) => Step::Descend(child_id),
+            None => Step::Done(frame.settled.unwrap_or(pass)),
+        }
+    }
+
+    fn evaluate_memory_selector(&self, node: &Node, context: &mut BehaviorContext, node_id: usize) -> NodeStatus {
+        let resume_key = format!("resume_{}", node_id);
+        let resume_index = self.resume_index(context, node_id);
+
+        for (index, &child_id) in node.children.iter().enumerate() {
+            if self.skipped_while_resuming(resume_index, index, child_id) {
+                continue;
+            }
+            match self.evaluate_node(child_id, context) {
+                NodeStatus::Success => {
+                    context.set_value(&resume_key, 0.0);
+                    return NodeStatus::Success;
+                },
+                NodeStatus::Running => {
+                    // Stored one-based so that 0 means nothing is running
+                    context.set_value(&resume_key, (index + 1) as f64);
+                    return NodeStatus::Running;
+                },
+                NodeStatus::Failure => continue,
+            }
+        }
+        context.set_value(&resume_key, 0.0);
+        NodeStatus::Failure
+    }
+
+    // Index of the child a memory selector left Running, if any
+    fn resume_index(&self, context: &BehaviorContext, node_id: usize) -> Option<usize> {
+        let stored = context.get_value(&format!("resume_{}", node_id)) as usize;
+        stored.checked_sub(1)
+    }
+
+    // While resuming, children ahead of the running one are skipped unless
+    // they are flagged as interrupting
+    fn skipped_while_resuming(&self, resume_index: Option<usize>, index: usize, child_id: usize) -> bool {
+        match resume_index {
+            Some(running) if index < running => {
+                !self.nodes.get(&child_id).is_some_and(|child| child.interrupting)
+            },
+            _ => false,
+        }
+    }
+
+    fn evaluate_inverter(&self, node: &Node, context: &mut BehaviorContext) -> NodeStatus {
+        if node.children.is_empty() {
+            return NodeStatus::Failure;
+        }
+        
+        match self.evaluate_node(node.children[0], context) {
+            NodeStatus::Success => NodeStatus::Failure,
+            NodeStatus::Failure => NodeStatus::Success,
+            NodeStatus::Running => NodeStatus::Running,
+        }
+    }
+
+    fn evaluate_succeeder(&self, node: &Node, context: &mut BehaviorContext) -> NodeStatus {
         if node.children.is_empty() {
             return NodeStatus::Success;
         }
-        
-        match self.evaluate_node(node.children[0], context) {
-            NodeStatus::Running => NodeStatus::Running,
-            _ => NodeStatus::Success,
+        
+        match self.evaluate_node(node.children[0], context) {
+            NodeStatus::Running => NodeStatus::Running,
+            _ => NodeStatus::Success,
+        }
+    }
+
+    fn evaluate_repeater(&self, node: &Node, context: &mut BehaviorContext, node_id: usize) -> NodeStatus {
+        if node.children.is_empty() {
+            return NodeStatus::Failure;
+        }
+        
+        if let Some(status) = self.begin_repeater(node, context, node_id) {
+            return status;
+        }
+        
+        let result = self.evaluate_node(node.children[0], context);
+        self.finish_repeater(node, context, node_id, result)
+    }
+
+    // Returns Some(status) when the repeater completes without ticking its child
+    fn begin_repeater(&self, node: &Node, context: &mut BehaviorContext, node_id: usize) -> Option<NodeStatus> {
+        let count_key = format!("repeater_{}_count", node_id);
+        let count = context.get_value(&count_key) as usize;
+        
+        if count >= node.repeat_times {
+            context.set_value(&count_key, 0.0);
+            return Some(NodeStatus::Success);
+        }
+        None
+    }
+
+    fn finish_repeater(&self, node: &Node, context: &mut BehaviorContext, node_id: usize, result: NodeStatus) -> NodeStatus {
+        let count_key = format!("repeater_{}_count", node_id);
+        match result {
+            NodeStatus::Failure => {
+                context.set_value(&count_key, 0.0);
+                NodeStatus::Failure
+            },
+            NodeStatus::Success => {
+                let count = context.get_value(&count_key) as usize + 1;
+                context.set_value(&count_key, count as f64);
+                if count >= node.repeat_times {
+                    context.set_value(&count_key, 0.0);
+                    NodeStatus::Success
+                } else {
+                    NodeStatus::Running
+                }
+            },
+            NodeStatus::Running => NodeStatus::Running,
+        }
+    }
+
+    fn evaluate_timeout(&self, node: &Node, context: &mut BehaviorContext, node_id: usize) -> NodeStatus {
+        if node.children.is_empty() {
+            return NodeStatus::Failure;
+        }
+
+        if let Some(status) = self.begin_timeout(node, context, node_id) {
+            return status;
+        }
+
+        let result = self.evaluate_node(node.children[0], context);
+        self.finish_timeout(context, node_id, result)
+    }
+
+    // Count this tick against the budget; returns Some(Failure) once exhausted
+    fn begin_timeout(&self, node: &Node, context: &mut BehaviorContext, node_id: usize) -> Option<NodeStatus> {
+        let ticks_key = format!("timeout_{}_ticks", node_id);
+        let ticks = context.get_value(&ticks_key) as usize + 1;
+
+        if ticks > node.repeat_times {
+            context.set_value(&ticks_key, 0.0);
+            return Some(NodeStatus::Failure);
+        }
+        context.set_value(&ticks_key, ticks as f64);
+        None
+    }
+
+    // A finished child ends the current entry, so the next one gets a full budget
+    fn finish_timeout(&self, context: &mut BehaviorContext, node_id: usize, result: NodeStatus) -> NodeStatus {
+        if result != NodeStatus::Running {
+            context.set_value(&format!("timeout_{}_ticks", node_id), 0.0);
+        }
+        result
+    }
+
+    fn evaluate_commit(&self, node: &Node, context: &mut BehaviorContext, node_id: usize) -> NodeStatus {
+        if node.children.is_empty() {
+            return NodeStatus::Failure;
+        }
+
+        self.begin_commit(context, node_id);
+        let result = self.evaluate_node(node.children[0], context);
+        self.finish_commit(node, context, node_id, result)
+    }
+
+    // Set aside the action chosen earlier so that only an action the child
+    // picks during this evaluation counts as its choice
+    fn begin_commit(&self, context: &mut BehaviorContext, node_id: usize) {
+        let prior_key = format!("commit_{}_prior", node_id);
+        match context.remove_value("action") {
+            Some(prior) => context.set_value(&prior_key, prior),
+            None => {
+                context.remove_value(&prior_key);
+            },
+        }
+    }
+
+    // Compare the action the child chose this evaluation with the committed
+    // one. A switch during the commitment window is overridden by replaying
+    // the committed action; otherwise a new action becomes the commitment.
+    // A child that failed or chose nothing leaves the commitment untouched.
+    fn finish_commit(&self, node: &Node, context: &mut BehaviorContext, node_id: usize, result: NodeStatus) -> NodeStatus {
+        let prefix = format!("commit_{}", node_id);
+        let action = match context.values.get("action") {
+            Some(&action) if result != NodeStatus::Failure => action,
+            Some(_) => return result,
+            None => {
+                if let Some(&prior) = context.values.get(&format!("{}_prior", prefix)) {
+                    context.set_value("action", prior);
+                }
+                return result;
+            },
+        };
+        let ticks_key = format!("{}_ticks", prefix);
+        let remaining = context.get_value(&ticks_key);
+        let committed = context.values.get(&format!("{}_action", prefix)).copied();
+
+        if committed.is_some_and(|committed| committed != action) && remaining > 0.0 {
+            context.set_value(&ticks_key, remaining - 1.0);
+            return self.replay_commitment(context, &prefix);
+        }
+
+        if committed != Some(action) {
+            self.save_commitment(context, &prefix, action, result);
+            context.set_value(&ticks_key, node.repeat_times.saturating_sub(1) as f64);
+        } else {
+            context.set_value(&ticks_key, (remaining - 1.0).max(0.0));
+        }
+        result
+    }
+
+    // Record the chosen action, its outputs and its status under `prefix`.
+    // Absent outputs leave their keys unset.
+    fn save_commitment(&self, context: &mut BehaviorContext, prefix: &str, action: f64, result: NodeStatus) {
+        let output = context.output.clone();
+        let parameter = context.get_value("action_parameter");
+        let move_vector = output.move_vector.map(|m| (m.x as f64, m.y as f64, m.z as f64));
+        let fields = [
+            ("action", Some(action)),
+            ("parameter", Some(parameter)),
+            ("status", Some(status_code(result) as f64)),
+            ("damage", output.attack_damage),
+            ("ability", output.ability_id.map(|id| id as f64)),
+            ("move_x", move_vector.map(|m| m.0)),
+            ("move_y", move_vector.map(|m| m.1)),
+            ("move_z", move_vector.map(|m| m.2)),
+        ];
+        for (field, value) in fields {
+            let key = format!("{}_{}", prefix, field);
+            match value {
+                Some(value) => context.set_value(&key, value),
+                None => {
+                    context.remove_value(&key);
+                },
+            }
+        }
+    }
+
+    // Restore the committed action's values and outputs over whatever the
+    // child produced this tick, returning its status
+    fn replay_commitment(&self, context: &mut BehaviorContext, prefix: &str) -> NodeStatus {
+        let field = |context: &BehaviorContext, name: &str| context.values.get(&format!("{}_{}", prefix, name)).copied();
+
+        let action = field(context, "action").unwrap_or_default();
+        let parameter = field(context, "parameter").unwrap_or_default();
+        context.set_value("action", action);
+        context.set_value("action_parameter", parameter);
+        context.output.attack_damage = field(context, "damage");
+        context.output.ability_id = field(context, "ability").map(|id| id as u32);
+        match field(context, "move_x") {
+            Some(move_x) => {
+                let move_y = field(context, "move_y").unwrap_or_default();
+                let move_z = field(context, "move_z").unwrap_or_default();
+                context.set_move(move_x, move_y, move_z);
+            },
+            None => {
+                context.set_move(0.0, 0.0, 0.0);
+                context.output.move_vector = None;
+            },
+        }
+        field(context, "status").and_then(status_from_code).unwrap_or(NodeStatus::Failure)
+    }
+
+    fn evaluate_parallel(&self, node: &Node, context: &mut BehaviorContext) -> NodeStatus {
+        let mut success_count = 0;
+        let mut failure_count = 0;
+        
+        for &child_id in &node.children {
+            match self.evaluate_node(child_id, context) {
+                NodeStatus::Success => success_count += 1,
+                NodeStatus::Failure => failure_count += 1,
+                NodeStatus::Running => {},
+            }
+        }
+        
+        concurrent_result(node, success_count, failure_count)
+    }
+
+    fn evaluate_rate_limit(&self, node: &Node, context: &mut BehaviorContext, node_id: usize) -> NodeStatus {
+        if node.children.is_empty() {
+            return NodeStatus::Failure;
+        }
+
+        let free_slot = match self.rate_limit_free_slot(node, context, node_id) {
+            Some(slot) => slot,
+            None => return NodeStatus::Failure,
+        };
+
+        let result = self.evaluate_node(node.children[0], context);
+        self.finish_rate_limit(context, node_id, free_slot, result)
+    }
+
+    // Each recent success occupies a timestamp slot until it leaves the window;
+    // returns None when the budget is exhausted
+    fn rate_limit_free_slot(&self, node: &Node, context: &BehaviorContext, node_id: usize) -> Option<usize> {
+        let window_start = context.elapsed_time - node.parameter;
+        (0..node.success_threshold).find(|slot| {
+            let key = format!("rate_limit_{}_{}", node_id, slot);
+            match context.values.get(&key) {
+                Some(&timestamp) => timestamp <= window_start,
+                None => true,
+            }
+        })
+    }
+
+    fn finish_rate_limit(&self, context: &mut BehaviorContext, node_id: usize, slot: usize, result: NodeStatus) -> NodeStatus {
+        if result == NodeStatus::Success {
+            let key = format!("rate_limit_{}_{}", node_id, slot);
+            let now = context.elapsed_time;
+            context.set_value(&key, now);
+        }
+        result
+    }
+
+    fn evaluate_condition(&self, node: &Node, context: &mut BehaviorContext, node_id: usize) -> NodeStatus {
+        let status = self.check_condition(node, context, node_id);
+        match status {
+            NodeStatus::Success if node.negate => NodeStatus::Failure,
+            NodeStatus::Failure if node.negate => NodeStatus::Success,
+            _ => status,
+        }
+    }
+
+    fn check_condition(&self, node: &Node, context: &mut BehaviorContext, node_id: usize) -> NodeStatus {
+        match node.condition_type {
+            // Is target in range?
+            0 => {
+                let distance = context.get_distance_to_target();
+                if distance <= node.parameter {
+                    NodeStatus::Success
+                } else {
+                    NodeStatus::Failure
+                }
+            },
+            // Is health below threshold?
+            1 => {
+                let health_percentage = context.get_health_percentage();
+                if health_percentage <= node.parameter {
+                    NodeStatus::Success
+                } else {
+                    NodeStatus::Failure
+                }
+            },
+            // Is entity type match?
+            2 => {
+                if context.entity_type == node.parameter as u32 {
+                    NodeStatus::Success
+                } else {
+                    NodeStatus::Failure
+                }
+            },
+            // Is target visible?
+            3 => {
+                // Line of sight against the world's occluders when attached,
+                // otherwise a value supplied by the caller
+                let visible = match &context.world {
+                    Some(world) => {
+                        let entity = Vector3::new(context.entity_x as f32, context.entity_y as f32, context.entity_z as f32);
+                        let target = Vector3::new(context.target_x as f32, context.target_y as f32, context.target_z as f32);
+                        !world.line_blocked(&entity, &target)
+                    },
+                    None => context.get_value("target_visible") > 0.5,
+                };
+                if visible {
+                    NodeStatus::Success
+                } else {
+                    NodeStatus::Failure
+                }
+            },
+            // Has cooldown expired?
+            4 => {
+                let cooldown_key = format!("cooldown_{}", node.parameter as u32);
+                if context.get_value(&cooldown_key) <= 0.0 {
+                    NodeStatus::Success
+                } else {
+                    NodeStatus::Failure
+                }
+            },
+            // Can acquire an attack token from the shared coordinator?
+            5 => {
+                if context.acquire_attack_token() {
+                    NodeStatus::Success
+                } else {
+                    NodeStatus::Failure
+                }
+            },
+            // Has the squad's alive fraction dropped to the morale threshold?
+            6 => {
+                match context.squad_state() {
+                    Some(squad) if squad.alive_fraction() <= node.parameter => NodeStatus::Success,
+                    _ => NodeStatus::Failure,
+                }
+            },
+            // Does comparing two context values hold?
+            7 => {
+                if node.keys.len() < 2 {
+                    return NodeStatus::Failure;
+                }
+                let left = context.get_value(&node.keys[0]);
+                let right = context.get_value(&node.keys[1]);
+                if node.compare_op.apply(left, right) {
+                    NodeStatus::Success
+                } else {
+                    NodeStatus::Failure
+                }
+            },
+            // Has the entity strayed beyond its leash from home?
+            8 => {
+                if context.get_distance_to_home() > node.parameter {
+                    NodeStatus::Success
+                } else {
+                    NodeStatus::Failure
+                }
+            },
+            // Has the entity arrived at the target? Inclusive: a distance of
+            // exactly `parameter` counts as arrived
+            9 => {
+                if context.get_distance_to_target() <= node.parameter {
+                    NodeStatus::Success
+                } else {
+                    NodeStatus::Failure
+                }
+            },
+            // Arrived, with hysteresis between the arrive and leave radii
+            10 => {
+                let state_key = format!("arrived_{}", node_id);
+                let radius = if context.get_value(&state_key) > 0.5 {
+                    node.extra_parameter(0).max(node.parameter)
+                } else {
+                    node.parameter
+                };
+                if context.get_distance_to_target() <= radius {
+                    context.set_value(&state_key, 1.0);
+                    NodeStatus::Success
+                } else {
+                    context.set_value(&state_key, 0.0);
+                    NodeStatus::Failure
+                }
+            },
+            // Heard a noise louder than `parameter`? Stores the loudest source
+            // in investigate_x/investigate_y/investigate_z
+            11 => {
+                let listener = Vector3::new(context.entity_x as f32, context.entity_y as f32, context.entity_z as f32);
+                let heard = context.stimulus_map().and_then(|stimulus| {
+                    stimulus.loudest_audible(&listener, context.hearing_range, context.elapsed_time)
+                });
+                match heard {
+                    Some((source, loudness)) if loudness > node.parameter => {
+                        context.set_value("investigate_x", source.x as f64);
+                        context.set_value("investigate_y", source.y as f64);
+                        context.set_value("investigate_z", source.z as f64);
+                        NodeStatus::Success
+                    },
+                    _ => NodeStatus::Failure,
+                }
+            },
+            // Random chance: succeeds with probability `parameter`
+            12 => {
+                if (context.rng.next_f32() as f64) < node.parameter {
+                    NodeStatus::Success
+                } else {
+                    NodeStatus::Failure
+                }
+            },
+            // Is target distance inside the [parameter, extra 0] band?
+            13 => {
+                let distance = context.get_distance_to_target();
+                if distance >= node.parameter && distance <= node.extra_parameter(0) {
+                    NodeStatus::Success
+                } else {
+                    NodeStatus::Failure
+                }
+            },
+            // Will the incoming projectile pass within `parameter` of the entity?
+            14 => {
+                let entity = Vector3::new(context.entity_x as f32, context.entity_y as f32, context.entity_z as f32);
+                match context.projectile_closest_point() {
+                    Some(closest) if closest.distance(&entity) as f64 <= node.parameter => NodeStatus::Success,
+                    _ => NodeStatus::Failure,
+                }
+            },
+            // Has the shared cooldown of ability group `parameter` expired?
+            15 => {
+                let cooldown_key = format!("cooldown_group_{}", node.parameter as u32);
+                if context.get_value(&cooldown_key) <= 0.0 {
+                    NodeStatus::Success
+                } else {
+                    NodeStatus::Failure
+                }
+            },
+            // Is the entity suppressed beyond `parameter`?
+            16 => {
+                if context.suppression > node.parameter {
+                    NodeStatus::Success
+                } else {
+                    NodeStatus::Failure
+                }
+            },
+            // Did the last path query reach the target?
+            17 => {
+                if context.path_reachable {
+                    NodeStatus::Success
+                } else {
+                    NodeStatus::Failure
+                }
+            },
+            // Health phase gate; announces entering or leaving the phase
+            18 => {
+                let in_phase = context.get_health_percentage() <= node.parameter;
+                let phase_key = format!("phase_{}", node_id);
+                let was_in_phase = context.get_value(&phase_key) != 0.0;
+                if in_phase != was_in_phase {
+                    context.set_value(&phase_key, if in_phase { 1.0 } else { 0.0 });
+                    let phase = node.extra_parameter(0);
+                    context.events.push(BehaviorEvent {
+                        kind: BehaviorEventKind::PhaseChange,
+                        position: Vector3::new(context.entity_x as f32, context.entity_y as f32, context.entity_z as f32),
+                        velocity: Vector3::new(0.0, 0.0, 0.0),
+                        value: if in_phase { phase } else { phase - 1.0 },
+                    });
+                }
+                if in_phase {
+                    NodeStatus::Success
+                } else {
+                    NodeStatus::Failure
+                }
+            },
+            // Default
+            _ => NodeStatus::Failure,
+        }
+    }
+
+    fn apply_default_action(&self, context: &mut BehaviorContext, status: NodeStatus) {
+        if let (NodeStatus::Failure, Some((action_type, parameter))) = (status, self.default_action) {
+            let node = Node {
+                action_type,
+                parameter,
+                ..Node::new(NodeType::Action)
+            };
+            self.evaluate_action(&node, context, DEFAULT_ACTION_ID);
+        }
+    }
+
+    fn evaluate_action(&self, node: &Node, context: &mut BehaviorContext, node_id: usize) -> NodeStatus {
+        let status = self.perform_action(node, context, node_id);
+        // Attack, falloff attack, projectile, charge and timed attack keep the token
+        if status != NodeStatus::Failure && matches!(node.action_type, 1 | 7 | 13 | 14 | 15) {
+            context.attacked = true;
+        }
+        status
+    }
+
+    fn perform_action(&self, node: &Node, context: &mut BehaviorContext, node_id: usize) -> NodeStatus {
+        match node.action_type {
+            // Move towards target
+            0 => {
+                // The caller applies the movement written by set_move
+                let (dx, dy, dz) = context.direction_to_target();
+                let speed = node.parameter;
+                context.set_move(dx * speed, dy * speed, dz * speed);
+                context.set_value("action", 0.0); // Move action
+                context.set_value("action_parameter", node.parameter);
+                NodeStatus::Success
+            },
+            // Attack target
+            1 => {
+                // In a real implementation, we would actually attack
+                // For now, just return success if in range
+                let distance = context.get_distance_to_target();
+                if distance <= node.parameter {
+                    context.set_value("action", 1.0); // Attack action
+                    context.set_value("action_parameter", node.parameter);
+                    context.output.attack_damage = Some(node.parameter);
+                    NodeStatus::Success
+                } else {
+                    NodeStatus::Failure
+                }
+            },
+            // Flee from target
+            2 => {
+                // The caller applies the movement written by set_move
+                let (dx, dy, dz) = context.direction_to_target();
+                let speed = node.parameter;
+                context.set_move(-dx * speed, -dy * speed, -dz * speed);
+                context.set_value("action", 2.0); // Flee action
+                context.set_value("action_parameter", node.parameter);
+                NodeStatus::Success
+            },
+            // Wait for a duration
+            3 => {
+                // In a real implementation, we would actually wait
+                // For now, just return success
+                context.set_value("action", 3.0); // Wait action
+                context.set_value("action_parameter", node.parameter);
+                NodeStatus::Success
+            },
+            // Special ability
+            4 => {
+                // In a real implementation, we would use a special ability
+                // For now, just return success
+                context.set_value("action", 4.0); // Special ability action
+                context.set_value("action_parameter", node.parameter);
+                context.output.ability_id = Some(node.parameter as u32);
+                NodeStatus::Success
+            },
+            // Set cooldown `parameter`, for extra[0] seconds when given and
+            // otherwise for `parameter` seconds
+            5 => {
+                let cooldown_key = format!("cooldown_{}", node.parameter as u32);
+                let duration = node.extra_parameters.first().copied().unwrap_or(node.parameter);
+                context.set_value(&cooldown_key, duration);
+                NodeStatus::Success
+            },
+            // Release the attack token back to the coordinator
+            6 => {
+                context.release_attack_token();
+                NodeStatus::Success
+            },
+            // Attack with linear damage falloff over distance; reported as a
+            // regular attack carrying the computed damage
+            7 => {
+                let min_damage = node.extra_parameter(0);
+                let max_range = node.extra_parameter(1);
+                let falloff = if max_range > 0.0 {
+                    (context.get_distance_to_target() / max_range).clamp(0.0, 1.0)
+                } else {
+                    0.0
+                };
+                let damage = node.parameter + (min_damage - node.parameter) * falloff;
+                context.set_value("action", 1.0); // Attack action
+                context.set_value("action_parameter", damage);
+                context.output.attack_damage = Some(damage);
+                NodeStatus::Success
+            },
+            // Strafe to hold the target inside an ideal range band
+            8 => {
+                let min_range = node.extra_parameter(0);
+                let max_range = node.extra_parameter(1);
+                let distance = context.get_distance_to_target();
+                let (dx, dy, dz) = context.direction_to_target();
+                let (mx, my, mz) = if distance > max_range {
+                    (dx, dy, dz)
+                } else if distance < min_range {
+                    (-dx, -dy, -dz)
+                } else {
+                    // Tangent around the target in the horizontal plane
+                    let length = (dx * dx + dz * dz).sqrt();
+                    if length > 0.0 {
+                        (dz / length, 0.0, -dx / length)
+                    } else {
+                        (0.0, 0.0, 0.0)
+                    }
+                };
+                let speed = node.parameter;
+                context.set_move(mx * speed, my * speed, mz * speed);
+                context.set_value("action", 8.0); // Strafe action
+                context.set_value("action_parameter", speed);
+                NodeStatus::Success
+            },
+            // Return toward the home position
+            9 => {
+                let dx = context.home_x - context.entity_x;
+                let dy = context.home_y - context.entity_y;
+                let dz = context.home_z - context.entity_z;
+                let length = (dx * dx + dy * dy + dz * dz).sqrt();
+                let scale = if length > 0.0 { node.parameter / length } else { 0.0 };
+                context.set_move(dx * scale, dy * scale, dz * scale);
+                context.set_value("action", 9.0); // Return home action
+                context.set_value("action_parameter", node.parameter);
+                NodeStatus::Success
+            },
+            // Sidestep the incoming projectile, moving `parameter` units
+            // sideways across its path on the side the entity already leans to
+            10 => {
+                let (closest, velocity) = match (context.projectile_closest_point(), context.incoming_projectile) {
+                    (Some(closest), Some((_, velocity))) => (closest, velocity),
+                    _ => return NodeStatus::Failure,
+                };
+                let mut lateral = Vector3::new(velocity.z, 0.0, -velocity.x);
+                lateral.normalize();
+                let entity = Vector3::new(context.entity_x as f32, context.entity_y as f32, context.entity_z as f32);
+                if entity.subtract(&closest).dot(&lateral) < 0.0 {
+                    lateral = lateral.multiply(-1.0);
+                }
+                let distance = node.parameter;
+                context.set_move(
+                    lateral.x as f64 * distance,
+                    lateral.y as f64 * distance,
+                    lateral.z as f64 * distance,
+                );
+                context.set_value("action", 10.0); // Dodge action
+                context.set_value("action_parameter", distance);
+                NodeStatus::Success
+            },
+            // Regenerate health once the regen delay has passed
+            11 => {
+                if !context.can_regen(node.extra_parameter(0)) {
+                    return NodeStatus::Failure;
+                }
+                context.entity_health = (context.entity_health + node.parameter).min(context.entity_max_health);
+                context.set_value("action", 11.0); // Regen action
+                context.set_value("action_parameter", node.parameter);
+                NodeStatus::Success
+            },
+            // Start the shared cooldown of ability group `parameter`
+            12 => {
+                context.start_group_cooldown(node.parameter as u32, node.extra_parameter(0));
+                NodeStatus::Success
+            },
+            // Fire a projectile at `parameter` speed toward the target, leading
+            // it when extra[0] is non-zero. Fails when there is no direction
+            // to fire in.
+            13 => {
+                let velocity = context.launch_velocity(node.parameter, node.extra_parameter(0) != 0.0);
+                if velocity.length() == 0.0 {
+                    return NodeStatus::Failure;
+                }
+                let position = Vector3::new(context.entity_x as f32, context.entity_y as f32, context.entity_z as f32);
+                context.events.push(BehaviorEvent {
+                    kind: BehaviorEventKind::ProjectileSpawn,
+                    position,
+                    velocity,
+                    value: node.parameter,
+                });
+                context.set_value("action", 13.0); // Fire projectile action
+                context.set_value("action_parameter", node.parameter);
+                NodeStatus::Success
+            },
+            // Charge at `parameter` speed until the target is within extra[0],
+            // then hit for extra[1] damage and end the charge. Running while
+            // closing in; fails when the target is already in range without a
+            // charge under way, so the hit lands once per charge.
+            14 => {
+                let (dx, dy, dz) = context.direction_to_target();
+                let speed = node.parameter;
+                let active_key = format!("charge_{}_active", node_id);
+                if context.get_distance_to_target() > node.extra_parameter(0) {
+                    context.set_value(&active_key, 1.0);
+                    context.set_move(dx * speed, dy * speed, dz * speed);
+                    context.set_value("action", 14.0); // Charge action
+                    context.set_value("action_parameter", speed);
+                    return NodeStatus::Running;
+                }
+                if context.get_value(&active_key) != 1.0 {
+                    return NodeStatus::Failure;
+                }
+
+                let damage = node.extra_parameter(1);
+                context.set_value(&active_key, 0.0);
+                context.events.push(BehaviorEvent {
+                    kind: BehaviorEventKind::ChargeHit,
+                    position: Vector3::new(context.target_x as f32, context.target_y as f32, context.target_z as f32),
+                    velocity: Vector3::new((dx * speed) as f32, (dy * speed) as f32, (dz * speed) as f32),
+                    value: damage,
+                });
+                context.set_value("action", 1.0); // Attack action
+                context.set_value("action_parameter", damage);
+                context.output.attack_damage = Some(damage);
+                NodeStatus::Success
+            },
+            // Timed attack: wind up for extra[0] seconds, hit for `parameter`
+            // damage during the extra[1] second active window, then recover
+            // for extra[2] seconds. Running except on the tick of the hit;
+            // `attack_{id}_phase` is 0 winding up, 1 active and 2 recovering.
+            15 => {
+                let (windup, active, recovery) =
+                    (node.extra_parameter(0), node.extra_parameter(1), node.extra_parameter(2));
+                let (start_key, hit_key, phase_key) = (
+                    format!("attack_{}_start", node_id),
+                    format!("attack_{}_hit", node_id),
+                    format!("attack_{}_phase", node_id),
+                );
+                let now = context.elapsed_time;
+                let start = match context.values.get(&start_key) {
+                    Some(&start) if now - start < windup + active + recovery => start,
+                    // No attack under way, or the last one has fully recovered
+                    _ => {
+                        context.set_value(&start_key, now);
+                        context.set_value(&hit_key, 0.0);
+                        now
+                    },
+                };
+                let elapsed = now - start;
+
+                let phase = if elapsed < windup {
+                    0.0
+                } else if elapsed < windup + active {
+                    1.0
+                } else {
+                    2.0
+                };
+                context.set_value(&phase_key, phase);
+                context.set_value("action", 15.0); // Timed attack action
+                context.set_value("action_parameter", node.parameter);
+
+                if phase != 1.0 || context.get_value(&hit_key) != 0.0 {
+                    return NodeStatus::Running;
+                }
+
+                let damage = node.parameter;
+                context.set_value(&hit_key, 1.0);
+                context.events.push(BehaviorEvent {
+                    kind: BehaviorEventKind::AttackHit,
+                    position: Vector3::new(context.target_x as f32, context.target_y as f32, context.target_z as f32),
+                    velocity: Vector3::new(0.0, 0.0, 0.0),
+                    value: damage,
+                });
+                context.set_value("action", 1.0); // Attack action
+                context.set_value("action_parameter", damage);
+                context.output.attack_damage = Some(damage);
+                NodeStatus::Success
+            },
+            // Default
+            _ => NodeStatus::Failure,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SUCCESS: i32 = 1;
+
+    const FAILURE: i32 = 0;
+
+    const RUNNING: i32 = 2;
+
+    fn context_at_distance(distance: f64) -> BehaviorContext {
+        let mut context = BehaviorContext::new();
+        context.set_target_position(distance, 0.0, 0.0);
+        context
+    }
+
+    #[test]
+    fn commit_replays_committed_action_when_child_switches() {
+        let mut tree = BehaviorTree::new();
+        let commit = tree.create_commit_node(3);
+        let attack = tree.create_action_node(1, 2.0);
+        let chase = tree.create_action_node(0, 5.0);
+        let selector = tree.create_selector_with_children(&[attack, chase]);
+        tree.add_child(commit, selector);
+        tree.set_root(commit);
+
+        let mut context = context_at_distance(1.0);
+        assert_eq!(tree.evaluate(&mut context), SUCCESS);
+        assert_eq!(context.get_value("action"), 1.0);
+
+        // Out of range the child would chase, but the attack is committed
+        context.set_target_position(10.0, 0.0, 0.0);
+        assert_eq!(tree.evaluate(&mut context), SUCCESS);
+        assert_eq!(context.get_value("action"), 1.0);
+        assert_eq!(context.output().attack_damage(), Some(2.0));
+    }
+
+    #[test]
+    fn commit_ignores_stale_action_when_child_chooses_nothing() {
+        let mut tree = BehaviorTree::new();
+        let commit = tree.create_commit_node(3);
+        let healthy = tree.create_condition_node(1, 1.0);
+        tree.add_child(commit, healthy);
+        tree.set_root(commit);
+
+        let mut context = BehaviorContext::new();
+        context.set_value("action", 2.0);
+        assert_eq!(tree.evaluate(&mut context), SUCCESS);
+        assert!(!context.values.contains_key(&format!("commit_{}_action", commit)));
+        assert_eq!(context.get_value("action"), 2.0);
+        assert_eq!(tree.evaluate_iterative(&mut context), SUCCESS);
+        assert!(!context.values.contains_key(&format!("commit_{}_action", commit)));
+    }
+
+    #[test]
+    fn commit_does_not_commit_or_replay_a_failed_child() {
+        let mut tree = BehaviorTree::new();
+        let commit = tree.create_commit_node(3);
+        let chase = tree.create_action_node(0, 5.0);
+        let low_health = tree.create_condition_node(1, 0.1);
+        let sequence = tree.create_sequence_with_children(&[chase, low_health]);
+        tree.add_child(commit, sequence);
+        tree.set_root(commit);
+
+        let mut context = BehaviorContext::new();
+        assert_eq!(tree.evaluate(&mut context), FAILURE);
+        assert!(!context.values.contains_key(&format!("commit_{}_action", commit)));
+    }
+
+    #[test]
+    fn charge_tracks_activity_per_node() {
+        let mut tree = BehaviorTree::new();
+        let long_charge = tree.create_charge_node(12.0, 12.0, 40.0);
+        let short_charge = tree.create_charge_node(12.0, 3.0, 40.0);
+        let parallel = tree.create_parallel_node(2);
+        tree.add_child(parallel, long_charge);
+        tree.add_child(parallel, short_charge);
+        tree.set_root(parallel);
+
+        // The long charge starts in range without charging, so it never hits
+        let mut context = context_at_distance(10.0);
+        tree.evaluate(&mut context);
+        assert_eq!(tree.last_status(&context, long_charge), Some(NodeStatus::Failure));
+        assert!(!context.values.contains_key(&format!("charge_{}_active", long_charge)));
+        assert_eq!(context.get_value(&format!("charge_{}_active", short_charge)), 1.0);
+        assert_eq!(tree.last_status(&context, short_charge), Some(NodeStatus::Running));
+        assert!(context.take_events().is_empty());
+
+        tree.reset_runtime_state(&mut context);
+        assert!(!context.values.contains_key(&format!("charge_{}_active", short_charge)));
+    }
+
+    #[test]
+    fn charge_hits_once_on_arrival_then_resets() {
+        let mut tree = BehaviorTree::new();
+        let charge = tree.create_charge_node(12.0, 3.0, 40.0);
+        tree.set_root(charge);
+
+        let mut context = context_at_distance(10.0);
+        assert_eq!(tree.evaluate(&mut context), RUNNING);
+        assert_eq!(context.output().move_vector().map(|m| m.x), Some(12.0));
+
+        context.set_target_position(2.0, 0.0, 0.0);
+        assert_eq!(tree.evaluate(&mut context), SUCCESS);
+        let hits = context.take_events();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].kind(), BehaviorEventKind::ChargeHit);
+        assert_eq!(hits[0].value(), 40.0);
+        assert_eq!(context.get_value(&format!("charge_{}_active", charge)), 0.0);
+
+        // Still in range on the next tick, but no charge is under way
+        assert_eq!(tree.evaluate(&mut context), FAILURE);
+        assert!(context.take_events().is_empty());
+        assert_eq!(context.output().attack_damage(), None);
+    }
+
+    fn attack_hits(context: &mut BehaviorContext) -> usize {
+        context.take_events().iter().filter(|event| event.kind() == BehaviorEventKind::AttackHit).count()
+    }
+
+    #[test]
+    fn timed_attack_hits_once_inside_active_window() {
+        let mut tree = BehaviorTree::new();
+        let attack = tree.create_timed_attack_node(25.0, 0.5, 0.2, 1.0);
+        tree.set_root(attack);
+
+        let mut context = BehaviorContext::new();
+        assert_eq!(tree.evaluate(&mut context), RUNNING);
+        assert_eq!(context.get_value(&format!("attack_{}_phase", attack)), 0.0);
+
+        context.tick(0.6);
+        assert_eq!(tree.evaluate(&mut context), SUCCESS);
+        assert_eq!(context.output().attack_damage(), Some(25.0));
+        assert_eq!(attack_hits(&mut context), 1);
+
+        context.tick(0.05);
+        assert_eq!(tree.evaluate(&mut context), RUNNING);
+        context.tick(0.5);
+        assert_eq!(tree.evaluate(&mut context), RUNNING);
+        assert_eq!(context.get_value(&format!("attack_{}_phase", attack)), 2.0);
+        assert_eq!(attack_hits(&mut context), 0);
+    }
+
+    #[test]
+    fn timed_attack_misses_when_active_window_is_skipped() {
+        let mut tree = BehaviorTree::new();
+        let attack = tree.create_timed_attack_node(25.0, 0.5, 0.2, 1.0);
+        tree.set_root(attack);
+
+        let mut context = BehaviorContext::new();
+        tree.evaluate(&mut context);
+        context.tick(0.8);
+        assert_eq!(tree.evaluate(&mut context), RUNNING);
+        assert_eq!(attack_hits(&mut context), 0);
+        assert!(!context.values.contains_key("attack_start"));
+
+        tree.reset_runtime_state(&mut context);
+        assert!(!context.values.contains_key(&format!("attack_{}_start", attack)));
+    }
+
+    #[test]
+    fn reset_runtime_state_clears_every_runtime_key() {
+        let mut tree = BehaviorTree::new();
+        let cooldown = tree.create_action_node(5, 1.0);
+        let group = tree.create_group_cooldown_node(2, 3.0);
+        let charge = tree.create_charge_node(12.0, 3.0, 40.0);
+        let timed = tree.create_timed_attack_node(10.0, 1.0, 0.5, 0.5);
+        let commit = tree.create_commit_node(2);
+        let repeater = tree.create_repeater_node(3);
+        let chase = tree.create_action_node(0, 4.0);
+        tree.add_child(repeater, chase);
+        tree.add_child(commit, repeater);
+        let parallel = tree.create_parallel_node(6);
+        for child in [cooldown, group, charge, timed, commit] {
+            tree.add_child(parallel, child);
+        }
+        let heard = tree.create_condition_node(11, 0.0);
+        tree.add_child(parallel, heard);
+        tree.set_root(parallel);
+
+        let mut context = context_at_distance(10.0);
+        context.set_value("aggression", 0.5);
+        tree.evaluate(&mut context);
+        tree.evaluate_iterative(&mut context);
+        assert!(context.values.len() > 1);
+
+        tree.reset_runtime_state(&mut context);
+        let remaining: Vec<_> = context.values.keys().cloned().collect();
+        assert_eq!(remaining, vec!["aggression".to_string()]);
+        assert_eq!(context.get_distance_to_target(), 10.0);
+        assert_eq!(context.get_health_percentage(), 1.0);
+    }
+
+    #[test]
+    fn attack_token_returns_when_sequence_fails_before_release() {
+        let coordinator = AttackCoordinator::new(1);
+        let mut tree = BehaviorTree::new();
+        let token = tree.create_condition_node(5, 0.0);
+        let attack = tree.create_action_node(1, 2.0);
+        let release = tree.create_action_node(6, 0.0);
+        let sequence = tree.create_sequence_with_children(&[token, attack, release]);
+        tree.set_root(sequence);
+
+        // Out of range: the attack fails after the token was acquired
+        let mut context = context_at_distance(10.0);
+        context.set_attack_coordinator(&coordinator);
+        assert_eq!(tree.evaluate(&mut context), FAILURE);
+        assert!(!context.has_attack_token());
+        assert_eq!(coordinator.tokens_in_use(), 0);
+
+        let mut other = context_at_distance(10.0);
+        other.set_attack_coordinator(&coordinator);
+        assert_eq!(tree.evaluate_iterative(&mut other), FAILURE);
+        assert_eq!(coordinator.tokens_in_use(), 0);
+    }
+
+    #[test]
+    fn attack_token_is_kept_while_attacking() {
+        let coordinator = AttackCoordinator::new(1);
+        let mut tree = BehaviorTree::new();
+        let token = tree.create_condition_node(5, 0.0);
+        let attack = tree.create_timed_attack_node(10.0, 1.0, 0.5, 0.5);
+        let sequence = tree.create_sequence_with_children(&[token, attack]);
+        tree.set_root(sequence);
+
+        let mut context = BehaviorContext::new();
+        context.set_attack_coordinator(&coordinator);
+        assert_eq!(tree.evaluate(&mut context), RUNNING);
+        assert!(context.has_attack_token());
+
+        let mut rival = BehaviorContext::new();
+        rival.set_attack_coordinator(&coordinator);
+        assert_eq!(tree.evaluate(&mut rival), FAILURE);
+        assert_eq!(coordinator.tokens_in_use(), 1);
+    }
+
+    #[test]
+    fn random_chance_differs_between_seeds() {
+        let mut tree = BehaviorTree::new();
+        let chance = tree.create_condition_node(12, 0.5);
+        tree.set_root(chance);
+
+        let rolls = |seed: u64| {
+            let mut context = BehaviorContext::with_seed(seed);
+            (0..32).map(|_| tree.evaluate(&mut context)).collect::<Vec<_>>()
+        };
+        assert_eq!(rolls(7), rolls(7));
+        assert_ne!(rolls(7), rolls(8));
+        assert!(rolls(7).contains(&SUCCESS) && rolls(7).contains(&FAILURE));
+    }
+
+    #[test]
+    fn attack_range_covers_every_attack_action() {
+        for (attack_type, guard) in [(13, 20.0), (15, 4.0)] {
+            let mut tree = BehaviorTree::new();
+            let in_range = tree.create_condition_node(0, guard);
+            let attack = tree.create_action_node(attack_type, 1.0);
+            let sequence = tree.create_sequence_with_children(&[in_range, attack]);
+            tree.set_root(sequence);
+            assert_eq!(tree.attack_range(), guard);
+        }
+
+        let mut tree = BehaviorTree::new();
+        let charge = tree.create_charge_node(12.0, 3.0, 40.0);
+        tree.set_root(charge);
+        assert_eq!(tree.attack_range(), 3.0);
+    }
+
+    #[test]
+    fn attack_range_ignores_detached_branches() {
+        let mut tree = BehaviorTree::new();
+        let in_range = tree.create_condition_node(0, 2.0);
+        let attack = tree.create_action_node(1, 10.0);
+        let sequence = tree.create_sequence_with_children(&[in_range, attack]);
+        let far = tree.create_condition_node(0, 50.0);
+        let detached_attack = tree.create_action_node(1, 10.0);
+        let detached = tree.create_sequence_with_children(&[far, detached_attack]);
+        tree.set_root(sequence);
+
+        assert_eq!(tree.attack_range(), 2.0);
+        let reachable = tree.reachable_ids();
+        assert!([far, detached_attack, detached].iter().all(|id| !reachable.contains(id)));
+    }
+
+    #[test]
+    fn context_json_round_trips_state() {
+        let mut context = BehaviorContext::with_seed(5);
+        context.set_target_position(3.0, 0.0, 4.0);
+        context.set_entity_health(40.0, 80.0);
+        context.set_value("aggression", 0.75);
+        context.tick(2.5);
+
+        let restored = BehaviorContext::parse_json(&context.to_json()).unwrap();
+        assert_eq!(restored.get_distance_to_target(), 5.0);
+        assert_eq!(restored.get_health_percentage(), 0.5);
+        assert_eq!(restored.get_value("aggression"), 0.75);
+        assert_eq!(restored.get_elapsed_time(), 2.5);
+        assert_eq!(restored.rng.state(), context.rng.state());
+    }
+
+    #[test]
+    fn context_parse_json_reports_malformed_input() {
+        assert!(BehaviorContext::parse_json("{not json").is_err());
+        let defaults = BehaviorContext::parse_json("{}").unwrap();
+        assert_eq!(defaults.get_health_percentage(), 1.0);
+    }
+
+    #[test]
+    fn tree_json_round_trips_and_allocates_fresh_ids() {
+        let mut tree = BehaviorTree::new();
+        let in_range = tree.create_condition_node(0, 2.0);
+        let attack = tree.create_action_node(1, 10.0);
+        let sequence = tree.create_sequence_with_children(&[in_range, attack]);
+        tree.set_root(sequence);
+        tree.set_node_data(attack, "weight", 3.0);
+
+        let json = tree.to_json().replace(&format!("\"next_id\":{}", sequence + 1), "\"next_id\":0");
+        assert!(json.contains("\"next_id\":0"));
+        let mut loaded = BehaviorTree::parse_json(&json).ok().unwrap();
+        assert_eq!(loaded.node_data(attack, "weight"), 3.0);
+        let mut context = context_at_distance(1.0);
+        assert_eq!(loaded.evaluate(&mut context), SUCCESS);
+
+        let added = loaded.create_action_node(3, 0.0);
+        assert!(!tree.node_ids().contains(&(added as u32)));
+        assert_eq!(loaded.node_type(in_range), NodeType::Condition as i32);
+    }
+
+    #[test]
+    fn tree_parse_json_rejects_dangling_references() {
+        let mut tree = BehaviorTree::new();
+        let sequence = tree.create_sequence_node();
+        tree.set_root(sequence);
+        let json = tree.to_json();
+
+        let dangling_child = json.replace("\"children\":[]", "\"children\":[7]");
+        assert!(BehaviorTree::parse_json(&dangling_child).err().unwrap().contains("missing child 7"));
+        let dangling_root = json.replace(&format!("\"root_id\":{}", sequence), "\"root_id\":9");
+        assert!(BehaviorTree::parse_json(&dangling_root).err().unwrap().contains("missing node 9"));
+        assert!(BehaviorTree::parse_json(&BehaviorTree::new().to_json()).is_ok());
+    }
+
+    #[test]
+    fn stats_count_reachable_nodes_depth_and_leaves() {
+        let mut tree = BehaviorTree::new();
+        let in_range = tree.create_condition_node(0, 2.0);
+        let attack = tree.create_action_node(1, 10.0);
+        let attack_sequence = tree.create_sequence_with_children(&[in_range, attack]);
+        let chase = tree.create_action_node(0, 5.0);
+        let root = tree.create_selector_with_children(&[attack_sequence, chase]);
+        // Shared child is counted once
+        tree.add_child(root, chase);
+        tree.create_action_node(3, 0.0);
+        tree.set_root(root);
+
+        let stats = tree.stats();
+        assert_eq!(stats.node_count, 5);
+        assert_eq!(stats.max_depth, 3);
+        assert_eq!(stats.leaf_count, 3);
+        assert_eq!(stats.count_of(NodeType::Action), 2);
+        assert_eq!(stats.count_of(NodeType::Condition), 1);
+        assert_eq!(stats.count_of(NodeType::Selector), 1);
+    }
+
+    #[test]
+    fn rate_limit_allows_successes_per_sliding_window() {
+        let mut tree = BehaviorTree::new();
+        let limit = tree.create_rate_limit_node(2, 1.0);
+        let ability = tree.create_action_node(4, 3.0);
+        tree.add_child(limit, ability);
+        tree.set_root(limit);
+
+        let mut context = BehaviorContext::new();
+        assert_eq!(tree.evaluate(&mut context), SUCCESS);
+        context.tick(0.4);
+        assert_eq!(tree.evaluate(&mut context), SUCCESS);
+        context.tick(0.4);
+        assert_eq!(tree.evaluate(&mut context), FAILURE);
+        assert_eq!(context.output().ability_id(), None);
+
+        // The first success leaves the window after a full second
+        context.tick(0.3);
+        assert_eq!(context.get_elapsed_time(), 1.1);
+        assert_eq!(tree.evaluate_iterative(&mut context), SUCCESS);
+        assert_eq!(tree.evaluate_iterative(&mut context), FAILURE);
+    }
+
+    #[test]
+    fn iterative_evaluator_matches_recursive_on_boss_tree() {
+        use crate::ai::enemy_types::{EnemyFactory, EnemyType};
+        use crate::rng::Rng;
+
+        let tree = EnemyFactory::new().create_behavior_tree(EnemyType::Boss);
+        let mut rng = Rng::new(7);
+        for seed in 0..200 {
+            let distance = rng.range_f32(0.0, 20.0) as f64;
+            let health = rng.range_f32(1.0, 100.0) as f64;
+            let build = || {
+                let mut context = context_at_distance(distance);
+                context.set_random_seed(seed);
+                context.set_entity_health(health, 100.0);
+                context
+            };
+            let mut recursive = build();
+            let mut iterative = build();
+            for _ in 0..5 {
+                let expected = tree.evaluate(&mut recursive);
+                assert_eq!(tree.evaluate_iterative(&mut iterative), expected);
+                assert_eq!(iterative.get_value("action"), recursive.get_value("action"));
+                recursive.tick(0.5);
+                iterative.tick(0.5);
+            }
+        }
+    }
+
+    #[test]
+    fn falloff_attack_scales_damage_with_distance() {
+        let mut tree = BehaviorTree::new();
+        let attack = tree.create_falloff_attack_node(20.0, 5.0, 10.0);
+        tree.set_root(attack);
+
+        for (distance, expected) in [(0.0, 20.0), (5.0, 12.5), (10.0, 5.0), (25.0, 5.0)] {
+            let mut context = context_at_distance(distance);
+            assert_eq!(tree.evaluate(&mut context), SUCCESS);
+            assert_eq!(context.get_value("action"), 1.0);
+            assert_eq!(context.output().attack_damage(), Some(expected));
+        }
+    }
+
+    #[test]
+    fn squad_morale_condition_tracks_reported_deaths() {
+        let mut squad = SquadState::new(4);
+        let mut member = BehaviorContext::new();
+        member.set_squad_state(&squad);
+
+        let mut tree = BehaviorTree::new();
+        let broken = tree.create_condition_node(6, 0.5);
+        let routed = tree.create_condition_node(6, 0.25);
+
+        tree.set_root(broken);
+        assert_eq!(tree.evaluate(&mut member), FAILURE);
+
+        // Deaths reported on any clone are seen by every member
+        squad.report_death();
+        squad.report_death();
+        assert_eq!(tree.evaluate(&mut member), SUCCESS);
+        tree.set_root(routed);
+        assert_eq!(tree.evaluate(&mut member), FAILURE);
+    }
+
+    #[test]
+    fn compare_condition_applies_each_operator() {
+        let cases = [
+            (CompareOp::Less, [false, false, true]),
+            (CompareOp::LessEqual, [false, true, true]),
+            (CompareOp::Equal, [false, true, false]),
+            (CompareOp::GreaterEqual, [true, true, false]),
+            (CompareOp::Greater, [true, false, false]),
+        ];
+        for (op, expected) in cases {
+            let mut tree = BehaviorTree::new();
+            let compare = tree.create_compare_condition_node("ammo", op, "max_ammo");
+            tree.set_root(compare);
+            for (ammo, succeeds) in [12.0, 10.0, 8.0].into_iter().zip(expected) {
+                let mut context = BehaviorContext::new();
+                context.set_value("ammo", ammo);
+                context.set_value("max_ammo", 10.0);
+                let status = if succeeds { SUCCESS } else { FAILURE };
+                assert_eq!(tree.evaluate(&mut context), status, "{:?} with ammo {}", op, ammo);
+            }
         }
     }
 
-    fn evaluate_repeater(&self, node: &Node, context: &mut BehaviorContext, node_id: usize) -> NodeStatus {
-        if node.children.is_empty() {
-            return NodeStatus::Failure;
+    #[test]
+    fn should_think_throttles_to_the_interval() {
+        let mut context = BehaviorContext::new();
+        context.set_think_interval(0.1);
+        let thinks: Vec<usize> = (1..=60).filter(|_| context.should_think(0.016)).collect();
+        assert!(thinks.len() >= 9 && thinks.len() <= 10);
+        for gap in thinks.windows(2).map(|pair| pair[1] - pair[0]) {
+            assert!(gap == 6 || gap == 7);
         }
-        
-        let count_key = format!("repeater_{}_count", node_id);
-        let mut count = context.get_value(&count_key) as usize;
-        
-        if count >= node.repeat_times {
-            context.set_value(&count_key, 0.0);
-            return NodeStatus::Success;
+
+        // A long frame thinks once rather than catching up
+        assert!(context.should_think(1.0));
+        assert!(!context.should_think(0.016));
+    }
+
+    fn move_vector(context: &BehaviorContext) -> (f32, f32, f32) {
+        let movement = context.output().move_vector().unwrap();
+        (movement.x, movement.y, movement.z)
+    }
+
+    #[test]
+    fn strafe_closes_backs_off_or_circles_by_range_band() {
+        let mut tree = BehaviorTree::new();
+        let strafe = tree.create_strafe_node(5.0, 10.0, 2.0);
+        tree.set_root(strafe);
+
+        let mut far = context_at_distance(20.0);
+        tree.evaluate(&mut far);
+        assert_eq!(move_vector(&far), (2.0, 0.0, 0.0));
+
+        let mut near = context_at_distance(2.0);
+        tree.evaluate(&mut near);
+        assert_eq!(move_vector(&near), (-2.0, 0.0, 0.0));
+
+        // Inside the band the motion is perpendicular to the target direction
+        let mut band = context_at_distance(7.0);
+        tree.evaluate(&mut band);
+        let (x, y, z) = move_vector(&band);
+        assert_eq!((x, y), (0.0, 0.0));
+        assert_eq!(z.abs(), 2.0);
+        assert_eq!(band.get_value("action"), 8.0);
+    }
+
+    #[test]
+    fn leash_condition_and_return_home_action() {
+        let mut tree = BehaviorTree::new();
+        let strayed = tree.create_condition_node(8, 10.0);
+        let go_home = tree.create_action_node(9, 3.0);
+        let leash = tree.create_sequence_with_children(&[strayed, go_home]);
+        tree.set_root(leash);
+
+        let mut context = BehaviorContext::new();
+        context.set_home_position(0.0, 0.0, 0.0);
+        context.set_entity_position(0.0, 0.0, 8.0);
+        assert_eq!(tree.evaluate(&mut context), FAILURE);
+
+        context.set_entity_position(0.0, 0.0, 12.0);
+        assert_eq!(tree.evaluate(&mut context), SUCCESS);
+        assert_eq!(context.get_value("action"), 9.0);
+        assert_eq!(move_vector(&context), (0.0, 0.0, -3.0));
+    }
+
+    #[test]
+    fn arrival_is_inclusive_and_hysteresis_holds_until_leaving() {
+        let mut tree = BehaviorTree::new();
+        let arrived = tree.create_condition_node(9, 2.0);
+        tree.set_root(arrived);
+        assert_eq!(tree.evaluate(&mut context_at_distance(1.99)), SUCCESS);
+        assert_eq!(tree.evaluate(&mut context_at_distance(2.0)), SUCCESS);
+        assert_eq!(tree.evaluate(&mut context_at_distance(2.01)), FAILURE);
+
+        let mut tree = BehaviorTree::new();
+        let settled = tree.create_arrival_hysteresis_node(2.0, 3.0);
+        tree.set_root(settled);
+        let mut context = context_at_distance(2.5);
+        assert_eq!(tree.evaluate(&mut context), FAILURE);
+        context.set_target_position(1.9, 0.0, 0.0);
+        assert_eq!(tree.evaluate(&mut context), SUCCESS);
+        // Drifting past the arrive radius stays arrived until the leave radius
+        context.set_target_position(2.5, 0.0, 0.0);
+        assert_eq!(tree.evaluate(&mut context), SUCCESS);
+        context.set_target_position(3.1, 0.0, 0.0);
+        assert_eq!(tree.evaluate(&mut context), FAILURE);
+        context.set_target_position(2.5, 0.0, 0.0);
+        assert_eq!(tree.evaluate(&mut context), FAILURE);
+    }
+
+    #[test]
+    fn output_reports_typed_fields_and_resets_each_evaluation() {
+        let mut tree = BehaviorTree::new();
+        let attack_range = tree.create_condition_node(0, 2.0);
+        let attack = tree.create_action_node(1, 15.0);
+        let attack_sequence = tree.create_sequence_with_children(&[attack_range, attack]);
+        let chase = tree.create_action_node(0, 4.0);
+        let root = tree.create_selector_with_children(&[attack_sequence, chase]);
+        tree.set_root(root);
+
+        let mut context = context_at_distance(10.0);
+        tree.evaluate(&mut context);
+        let output = context.output();
+        assert_eq!(move_vector(&context), (4.0, 0.0, 0.0));
+        assert_eq!(output.attack_damage(), None);
+        assert_eq!(output.ability_id(), None);
+
+        context.set_target_position(1.0, 0.0, 0.0);
+        tree.evaluate(&mut context);
+        let output = context.output();
+        assert!(output.move_vector().is_none());
+        assert_eq!(output.attack_damage(), Some(15.0));
+    }
+
+    #[test]
+    fn heard_noise_sets_investigate_target_until_it_decays() {
+        let mut stimulus = StimulusMap::new(2.0);
+        stimulus.add_noise(&Vector3::new(5.0, 0.0, 0.0), 10.0, 0.0);
+        stimulus.add_noise(&Vector3::new(-5.0, 0.0, 0.0), 4.0, 0.0);
+        stimulus.add_noise(&Vector3::new(100.0, 0.0, 0.0), 50.0, 0.0);
+
+        let mut tree = BehaviorTree::new();
+        let heard = tree.create_condition_node(11, 3.0);
+        tree.set_root(heard);
+
+        let mut context = BehaviorContext::new();
+        context.set_stimulus_map(&stimulus);
+        context.set_hearing_range(20.0);
+        assert_eq!(tree.evaluate(&mut context), SUCCESS);
+        assert_eq!(context.get_value("investigate_x"), 5.0);
+
+        // Loudness fades linearly over the lifetime
+        context.tick(1.9);
+        assert_eq!(tree.evaluate(&mut context), FAILURE);
+        stimulus.prune(2.0);
+        assert_eq!(stimulus.noise_count(), 0);
+    }
+
+    #[test]
+    fn displayed_health_eases_down_without_overshoot() {
+        let mut context = BehaviorContext::new();
+        context.set_entity_health(100.0, 100.0);
+        context.tick_displayed_health(1.0, 1000.0);
+        context.set_entity_health(40.0, 100.0);
+
+        let mut shown = Vec::new();
+        for _ in 0..4 {
+            context.tick_displayed_health(0.5, 50.0);
+            shown.push(context.displayed_health());
         }
-        
-        match self.evaluate_node(node.children[0], context) {
-            NodeStatus::Failure => {
-                context.set_value(&count_key, 0.0);
-                NodeStatus::Failure
-            },
-            NodeStatus::Success => {
-                count += 1;
-                context.set_value(&count_key, count as f64);
-                if count >= node.repeat_times {
-                    context.set_value(&count_key, 0.0);
-                    NodeStatus::Success
-                } else {
-                    NodeStatus::Running
-                }
-            },
-            NodeStatus::Running => NodeStatus::Running,
+        assert_eq!(shown, vec![75.0, 50.0, 40.0, 40.0]);
+    }
+
+    fn chase_with_flee(interrupting: bool) -> (BehaviorTree, BehaviorContext) {
+        let mut tree = BehaviorTree::new();
+        let low_health = tree.create_condition_node(1, 0.3);
+        let flee = tree.create_action_node(2, 8.0);
+        let flee_sequence = tree.create_sequence_with_children(&[low_health, flee]);
+        tree.set_interrupting(flee_sequence, interrupting);
+        let chase = tree.create_charge_node(1.0, 1.0, 10.0);
+        let root = tree.create_memory_selector_node();
+        tree.add_child(root, flee_sequence);
+        tree.add_child(root, chase);
+        tree.set_root(root);
+
+        let mut context = context_at_distance(10.0);
+        assert_eq!(tree.evaluate(&mut context), RUNNING);
+        assert_eq!(context.get_value("action"), 14.0);
+        context.set_entity_health(10.0, 100.0);
+        (tree, context)
+    }
+
+    #[test]
+    fn interrupting_branch_aborts_running_chase() {
+        let (tree, mut context) = chase_with_flee(true);
+        assert_eq!(tree.evaluate(&mut context), SUCCESS);
+        assert_eq!(context.get_value("action"), 2.0);
+
+        // Without the flag the memory selector resumes the running chase
+        let (tree, mut context) = chase_with_flee(false);
+        assert_eq!(tree.evaluate(&mut context), RUNNING);
+        assert_eq!(context.get_value("action"), 14.0);
+    }
+
+    #[test]
+    fn last_status_reports_cached_node_results() {
+        let mut tree = BehaviorTree::new();
+        let in_range = tree.create_condition_node(0, 2.0);
+        let attack = tree.create_action_node(1, 10.0);
+        let attack_sequence = tree.create_sequence_with_children(&[in_range, attack]);
+        let chase = tree.create_action_node(0, 5.0);
+        let root = tree.create_selector_with_children(&[attack_sequence, chase]);
+        tree.set_root(root);
+
+        let mut context = context_at_distance(1.0);
+        assert_eq!(tree.last_status(&context, attack_sequence), None);
+        tree.evaluate(&mut context);
+        assert_eq!(tree.last_status(&context, attack_sequence), Some(NodeStatus::Success));
+
+        context.set_target_position(10.0, 0.0, 0.0);
+        tree.evaluate(&mut context);
+        assert_eq!(tree.last_status(&context, attack_sequence), Some(NodeStatus::Failure));
+        assert_eq!(tree.last_status(&context, chase), Some(NodeStatus::Success));
+    }
+
+    #[test]
+    fn range_band_condition_rejects_too_close_and_too_far() {
+        let mut tree = BehaviorTree::new();
+        let band = tree.create_range_band_condition_node(2.0, 4.0);
+        tree.set_root(band);
+        assert_eq!(tree.evaluate(&mut context_at_distance(3.0)), SUCCESS);
+        assert_eq!(tree.evaluate(&mut context_at_distance(2.0)), SUCCESS);
+        assert_eq!(tree.evaluate(&mut context_at_distance(1.0)), FAILURE);
+        assert_eq!(tree.evaluate(&mut context_at_distance(5.0)), FAILURE);
+    }
+
+    #[test]
+    fn dodge_sidesteps_near_miss_projectiles_only() {
+        let mut tree = BehaviorTree::new();
+        let incoming = tree.create_condition_node(14, 1.5);
+        let dodge = tree.create_action_node(10, 4.0);
+        let dodge_sequence = tree.create_sequence_with_children(&[incoming, dodge]);
+        tree.set_root(dodge_sequence);
+
+        let mut context = BehaviorContext::new();
+        let velocity = Vector3::new(20.0, 0.0, 0.0);
+        context.set_incoming_projectile(&Vector3::new(-10.0, 0.0, 0.5), &velocity);
+        assert_eq!(tree.evaluate(&mut context), SUCCESS);
+        // Perpendicular to the shot, away from the side it passes on
+        let (x, y, z) = move_vector(&context);
+        assert_eq!(x * velocity.x + y * velocity.y + z * velocity.z, 0.0);
+        assert_eq!((x, y, z), (0.0, 0.0, -4.0));
+
+        let mut context = BehaviorContext::new();
+        context.set_incoming_projectile(&Vector3::new(-10.0, 0.0, 0.5), &Vector3::new(-20.0, 0.0, 0.0));
+        assert_eq!(tree.evaluate(&mut context), FAILURE);
+        assert!(context.output().move_vector().is_none());
+    }
+
+    #[test]
+    fn regen_waits_for_the_delay_after_damage() {
+        let mut tree = BehaviorTree::new();
+        let regen = tree.create_regen_node(5.0, 2.0);
+        tree.set_root(regen);
+
+        let mut context = BehaviorContext::new();
+        context.set_entity_health(50.0, 100.0);
+        context.tick(10.0);
+        context.note_damage_taken();
+        assert!(!context.can_regen(2.0));
+        assert_eq!(tree.evaluate(&mut context), FAILURE);
+
+        context.tick(1.5);
+        assert_eq!(tree.evaluate(&mut context), FAILURE);
+        context.tick(0.5);
+        assert!(context.can_regen(2.0));
+        assert_eq!(tree.evaluate(&mut context), SUCCESS);
+        assert_eq!(context.get_health_percentage(), 0.55);
+    }
+
+    #[test]
+    fn evaluate_all_children_runs_later_side_effects_with_the_same_status() {
+        let build = |evaluate_all: bool| {
+            let mut tree = BehaviorTree::new();
+            let low_health = tree.create_condition_node(1, 0.1);
+            let chase = tree.create_action_node(0, 5.0);
+            let sequence = tree.create_sequence_with_children(&[low_health, chase]);
+            tree.set_evaluate_all_children(sequence, evaluate_all);
+            tree.set_root(sequence);
+            tree
+        };
+
+        let short_circuit = build(false);
+        let mut context = context_at_distance(10.0);
+        assert_eq!(short_circuit.evaluate(&mut context), FAILURE);
+        assert!(context.output().move_vector().is_none());
+
+        let evaluate_all = build(true);
+        for iterative in [false, true] {
+            let mut context = context_at_distance(10.0);
+            let status = if iterative {
+                evaluate_all.evaluate_iterative(&mut context)
+            } else {
+                evaluate_all.evaluate(&mut context)
+            };
+            assert_eq!(status, FAILURE);
+            assert_eq!(move_vector(&context), (5.0, 0.0, 0.0));
         }
     }
 
-    fn evaluate_parallel(&self, node: &Node, context: &mut BehaviorContext) -> NodeStatus {
-        let mut success_count = 0;
-        let mut failure_count = 0;
-        let mut running_count = 0;
-        
-        for &child_id in &node.children {
-            match self.evaluate_node(child_id, context) {
-                NodeStatus::Success => success_count += 1,
-                NodeStatus::Failure => failure_count += 1,
-                NodeStatus::Running => running_count += 1,
-            }
+    fn group_ability(group: u32, ability: f64) -> BehaviorTree {
+        let mut tree = BehaviorTree::new();
+        let ready = tree.create_condition_node(15, group as f64);
+        let special = tree.create_action_node(4, ability);
+        let start_group = tree.create_group_cooldown_node(group, 3.0);
+        let sequence = tree.create_sequence_with_children(&[ready, special, start_group]);
+        tree.set_root(sequence);
+        tree
+    }
+
+    #[test]
+    fn group_cooldown_blocks_every_ability_in_the_group() {
+        let ability_a = group_ability(1, 3.0);
+        let ability_b = group_ability(1, 4.0);
+        let other_group = group_ability(2, 5.0);
+
+        let mut context = BehaviorContext::new();
+        assert_eq!(ability_a.evaluate(&mut context), SUCCESS);
+        assert_eq!(context.output().ability_id(), Some(3));
+        assert_eq!(ability_b.evaluate(&mut context), FAILURE);
+        assert_eq!(other_group.evaluate(&mut context), SUCCESS);
+
+        context.tick(2.5);
+        assert_eq!(ability_b.evaluate(&mut context), FAILURE);
+        context.tick(0.5);
+        assert_eq!(ability_b.evaluate(&mut context), SUCCESS);
+        assert_eq!(context.output().ability_id(), Some(4));
+    }
+
+    #[test]
+    fn change_recording_logs_value_writes_only_when_enabled() {
+        use crate::ai::enemy_types::{EnemyFactory, EnemyType};
+        let grunt = EnemyFactory::new().create_behavior_tree(EnemyType::Grunt);
+
+        let mut context = context_at_distance(1.0);
+        grunt.evaluate(&mut context);
+        assert!(context.take_change_log().is_empty());
+
+        context.set_change_recording(true);
+        context.set_target_position(10.0, 0.0, 0.0);
+        grunt.evaluate(&mut context);
+        let log = context.take_change_log();
+        let action = log.iter().find(|record| record.key() == "action").unwrap();
+        assert_eq!(action.old_value(), Some(1.0));
+        assert_eq!(action.new_value(), Some(0.0));
+        assert!(context.take_change_log().is_empty());
+
+        context.set_change_recording(false);
+        grunt.evaluate(&mut context);
+        assert!(context.take_change_log().is_empty());
+    }
+
+    #[test]
+    fn timeout_forces_failure_after_the_tick_budget_then_resets() {
+        let mut tree = BehaviorTree::new();
+        let timeout = tree.create_timeout_node(3);
+        let chase = tree.create_charge_node(1.0, 1.0, 10.0);
+        tree.add_child(timeout, chase);
+        tree.set_root(timeout);
+
+        let mut context = context_at_distance(10.0);
+        let statuses: Vec<i32> = (0..5).map(|_| tree.evaluate(&mut context)).collect();
+        assert_eq!(statuses, vec![RUNNING, RUNNING, RUNNING, FAILURE, RUNNING]);
+
+        // A child that finishes inside the budget restarts the count
+        context.set_target_position(0.5, 0.0, 0.0);
+        assert_eq!(tree.evaluate(&mut context), SUCCESS);
+        context.set_target_position(10.0, 0.0, 0.0);
+        let statuses: Vec<i32> = (0..4).map(|_| tree.evaluate_iterative(&mut context)).collect();
+        assert_eq!(statuses, vec![RUNNING, RUNNING, RUNNING, FAILURE]);
+    }
+
+    #[test]
+    fn race_ticks_every_child_and_succeeds_on_any_success() {
+        let mut tree = BehaviorTree::new();
+        let race = tree.create_race_node();
+        let charge = tree.create_charge_node(1.0, 1.0, 10.0);
+        let in_range = tree.create_condition_node(0, 5.0);
+        let set_cooldown = tree.create_action_node(5, 7.0);
+        for child in [charge, in_range, set_cooldown] {
+            tree.add_child(race, child);
         }
-        
-        if success_count >= node.success_threshold {
-            NodeStatus::Success
-        } else if failure_count > node.children.len() - node.success_threshold {
-            NodeStatus::Failure
-        } else {
-            NodeStatus::Running
+        tree.set_root(race);
+
+        // The charge keeps running, but the later range check wins the race,
+        // and the last child still runs on the same tick
+        let mut context = context_at_distance(3.0);
+        assert_eq!(tree.evaluate(&mut context), SUCCESS);
+        assert_eq!(tree.last_status(&context, charge), Some(NodeStatus::Running));
+        assert_eq!(context.get_value("cooldown_7"), 7.0);
+
+        let mut tree = BehaviorTree::new();
+        let race = tree.create_race_node();
+        let far = tree.create_condition_node(0, 1.0);
+        let low_health = tree.create_condition_node(1, 0.1);
+        tree.add_child(race, far);
+        tree.add_child(race, low_health);
+        tree.set_root(race);
+        assert_eq!(tree.evaluate(&mut context_at_distance(3.0)), FAILURE);
+    }
+
+    #[test]
+    fn outline_nests_the_grunt_tree_and_stops_at_cycles() {
+        use crate::ai::enemy_types::{EnemyFactory, EnemyType};
+        let grunt = EnemyFactory::new().create_behavior_tree(EnemyType::Grunt);
+        let outline = grunt.to_outline();
+        let lines: Vec<&str> = outline.lines().collect();
+        assert_eq!(lines.len(), 8);
+        assert!(lines[0].starts_with("Selector #"));
+        assert!(lines[1].starts_with("  Sequence #"));
+        assert!(lines[2].starts_with("    Condition #") && lines[2].ends_with("condition=1 param=0.3"));
+        assert!(lines[3].starts_with("    Action #") && lines[3].contains("action=2"));
+        assert!(lines[4].starts_with("  Sequence #"));
+        assert!(lines[6].starts_with("    Action #") && lines[6].contains("action=1 param=10"));
+        assert!(lines[7].starts_with("  Action #") && lines[7].ends_with("action=0 param=5"));
+
+        let mut tree = BehaviorTree::new();
+        let sequence = tree.create_sequence_node();
+        let selector = tree.create_selector_node();
+        tree.add_child(sequence, selector);
+        tree.add_child(selector, sequence);
+        tree.set_root(sequence);
+        let outline = tree.to_outline();
+        assert_eq!(outline.lines().count(), 3);
+        assert!(outline.contains(&format!("    Sequence #{} (repeat)", sequence)));
+    }
+
+    #[test]
+    fn node_data_holds_several_keys_through_json() {
+        let mut tree = BehaviorTree::new();
+        let custom = tree.create_action_node(3, 1.0);
+        tree.set_root(custom);
+        tree.set_node_data(custom, "spread", 0.25);
+        tree.set_node_data(custom, "burst", 3.0);
+        tree.set_node_data(custom, "burst", 4.0);
+        assert_eq!(tree.node_data(custom, "spread"), 0.25);
+        assert_eq!(tree.node_data(custom, "burst"), 4.0);
+        assert_eq!(tree.node_data(custom, "missing"), 0.0);
+
+        let loaded = BehaviorTree::parse_json(&tree.to_json()).ok().unwrap();
+        assert_eq!(loaded.node_data(custom, "spread"), 0.25);
+        assert_eq!(loaded.node_data(custom, "burst"), 4.0);
+    }
+
+    #[test]
+    fn suppression_triggers_cover_and_decays_away() {
+        let mut tree = BehaviorTree::new();
+        let suppressed = tree.create_condition_node(16, 5.0);
+        tree.set_root(suppressed);
+
+        let mut context = BehaviorContext::new();
+        context.set_suppression_decay(2.0);
+        context.add_suppression(3.0);
+        assert_eq!(tree.evaluate(&mut context), FAILURE);
+        context.add_suppression(4.0);
+        assert_eq!(tree.evaluate(&mut context), SUCCESS);
+
+        context.tick(0.5);
+        assert_eq!(context.suppression(), 6.0);
+        assert_eq!(tree.evaluate(&mut context), SUCCESS);
+        context.tick(1.0);
+        assert_eq!(tree.evaluate(&mut context), FAILURE);
+        context.tick(10.0);
+        assert_eq!(context.suppression(), 0.0);
+    }
+
+    #[test]
+    fn default_action_idles_rootless_trees_until_a_root_is_set() {
+        let mut tree = BehaviorTree::new();
+        tree.set_default_action(3, 1.5);
+        let mut context = BehaviorContext::new();
+        assert_eq!(tree.evaluate(&mut context), FAILURE);
+        assert_eq!(context.get_value("action"), 3.0);
+        assert_eq!(context.get_value("action_parameter"), 1.5);
+
+        let chase = tree.create_action_node(0, 5.0);
+        tree.set_root(chase);
+        let mut context = context_at_distance(10.0);
+        assert_eq!(tree.evaluate(&mut context), SUCCESS);
+        assert_eq!(context.get_value("action"), 0.0);
+        assert_eq!(move_vector(&context), (5.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn projectile_action_queues_spawn_aimed_at_target_or_lead_point() {
+        let mut tree = BehaviorTree::new();
+        let direct = tree.create_projectile_node(20.0, false);
+        tree.set_root(direct);
+        let mut context = context_at_distance(10.0);
+        context.set_target_velocity(0.0, 0.0, 5.0);
+        assert_eq!(tree.evaluate(&mut context), SUCCESS);
+        let events = context.take_events();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].kind(), BehaviorEventKind::ProjectileSpawn);
+        let velocity = events[0].velocity();
+        assert_eq!((velocity.x, velocity.y, velocity.z), (20.0, 0.0, 0.0));
+
+        // Leading: the shot and the target meet at the same point
+        let mut tree = BehaviorTree::new();
+        let lead = tree.create_projectile_node(20.0, true);
+        tree.set_root(lead);
+        let mut context = context_at_distance(10.0);
+        context.set_target_velocity(0.0, 0.0, 5.0);
+        tree.evaluate(&mut context);
+        let velocity = context.take_events()[0].velocity();
+        assert!((velocity.length() - 20.0).abs() < 1e-4);
+        let t = 10.0 / velocity.x;
+        assert!((velocity.z * t - 5.0 * t).abs() < 1e-4);
+    }
+
+    #[test]
+    fn node_ids_enumerate_the_grunt_tree_with_valid_types() {
+        use crate::ai::enemy_types::{EnemyFactory, EnemyType};
+        let grunt = EnemyFactory::new().create_behavior_tree(EnemyType::Grunt);
+        let ids = grunt.node_ids();
+        assert_eq!(ids, (0..8).collect::<Vec<u32>>());
+        for id in ids {
+            let node_type = grunt.node_type(id as usize);
+            assert!((NodeType::Sequence as i32..=NodeType::Commit as i32).contains(&node_type));
         }
+        assert_eq!(grunt.node_type(7), NodeType::Selector as i32);
+        assert_eq!(grunt.node_type(0), NodeType::Condition as i32);
+        assert_eq!(grunt.node_type(99), -1);
     }
 
-    fn evaluate_condition(&self, node: &Node, context: &mut BehaviorContext) -> NodeStatus {
-        match node.condition_type {
-            // Is target in range?
-            0 => {
-                let distance = context.get_distance_to_target();
-                if distance <= node.parameter {
-                    NodeStatus::Success
-                } else {
-                    NodeStatus::Failure
-                }
-            },
-            // Is health below threshold?
-            1 => {
-                let health_percentage = context.get_health_percentage();
-                if health_percentage <= node.parameter {
-                    NodeStatus::Success
-                } else {
-                    NodeStatus::Failure
-                }
-            },
-            // Is entity type match?
-            2 => {
-                if context.entity_type == node.parameter as u32 {
-                    NodeStatus::Success
-                } else {
-                    NodeStatus::Failure
-                }
-            },
-            // Is target visible?
-            3 => {
-                // In a real implementation, we would check line of sight
-                // For now, just use a value from the context
-                if context.get_value("target_visible") > 0.5 {
-                    NodeStatus::Success
-                } else {
-                    NodeStatus::Failure
-                }
-            },
-            // Has cooldown expired?
-            4 => {
-                let cooldown_key = format!("cooldown_{}", node.parameter as u32);
-                if context.get_value(&cooldown_key) <= 0.0 {
-                    NodeStatus::Success
-                } else {
-                    NodeStatus::Failure
-                }
-            },
-            // Default
-            _ => NodeStatus::Failure,
+    #[test]
+    fn evaluate_subtree_runs_grunt_branches_in_isolation() {
+        use crate::ai::enemy_types::{EnemyFactory, EnemyType};
+        let grunt = EnemyFactory::new().create_behavior_tree(EnemyType::Grunt);
+        let (flee_sequence, attack_sequence) = (2, 5);
+        assert_eq!(grunt.node_type(flee_sequence), NodeType::Sequence as i32);
+        assert_eq!(grunt.node_type(attack_sequence), NodeType::Sequence as i32);
+
+        // Healthy and in range: the full tree would attack, the flee branch alone fails
+        let mut context = context_at_distance(1.0);
+        assert_eq!(grunt.evaluate_subtree(flee_sequence, &mut context), FAILURE);
+        assert_eq!(grunt.evaluate_subtree(attack_sequence, &mut context), SUCCESS);
+        assert_eq!(context.get_value("action"), 1.0);
+
+        // Badly hurt: the full tree would flee, the attack branch alone still attacks
+        let mut context = context_at_distance(1.0);
+        context.set_entity_health(10.0, 100.0);
+        assert_eq!(grunt.evaluate_subtree(attack_sequence, &mut context), SUCCESS);
+        assert_eq!(context.get_value("action"), 1.0);
+        assert_eq!(grunt.evaluate_subtree(flee_sequence, &mut context), SUCCESS);
+        assert_eq!(context.get_value("action"), 2.0);
+    }
+
+    #[test]
+    fn combined_selector_reaches_both_source_trees() {
+        let mut flee_tree = BehaviorTree::new();
+        let low_health = flee_tree.create_condition_node(1, 0.3);
+        let flee = flee_tree.create_action_node(2, 8.0);
+        let flee_root = flee_tree.create_sequence_with_children(&[low_health, flee]);
+        flee_tree.set_root(flee_root);
+
+        let mut chase_tree = BehaviorTree::new();
+        let chase = chase_tree.create_action_node(0, 5.0);
+        chase_tree.set_root(chase);
+
+        let combined = BehaviorTree::combine(&flee_tree, &chase_tree, 1);
+        assert_eq!(combined.node_ids().len(), 5);
+        assert_eq!(combined.reachable_ids().len(), 5);
+
+        let mut healthy = context_at_distance(10.0);
+        assert_eq!(combined.evaluate(&mut healthy), SUCCESS);
+        assert_eq!(healthy.get_value("action"), 0.0);
+
+        let mut hurt = context_at_distance(10.0);
+        hurt.set_entity_health(10.0, 100.0);
+        assert_eq!(combined.evaluate(&mut hurt), SUCCESS);
+        assert_eq!(hurt.get_value("action"), 2.0);
+
+        // Sequence mode needs both: a healthy entity fails the flee half
+        let sequence = BehaviorTree::combine(&flee_tree, &chase_tree, 0);
+        assert_eq!(sequence.evaluate(&mut context_at_distance(10.0)), FAILURE);
+    }
+
+    #[test]
+    fn unreachable_path_fails_the_chase_branch() {
+        let mut tree = BehaviorTree::new();
+        let reachable = tree.create_condition_node(17, 0.0);
+        let chase = tree.create_action_node(0, 5.0);
+        let chase_sequence = tree.create_sequence_with_children(&[reachable, chase]);
+        let idle = tree.create_action_node(3, 1.0);
+        let root = tree.create_selector_with_children(&[chase_sequence, idle]);
+        tree.set_root(root);
+
+        let mut context = context_at_distance(10.0);
+        context.set_path_reachable(false);
+        tree.evaluate(&mut context);
+        assert_eq!(tree.last_status(&context, chase_sequence), Some(NodeStatus::Failure));
+        assert_eq!(context.get_value("action"), 3.0);
+
+        context.set_path_reachable(true);
+        tree.evaluate(&mut context);
+        assert_eq!(context.get_value("action"), 0.0);
+    }
+
+    #[test]
+    fn boss_emits_one_phase_change_when_crossing_half_health() {
+        use crate::ai::enemy_types::{EnemyFactory, EnemyType};
+        let boss = EnemyFactory::new().create_behavior_tree(EnemyType::Boss);
+        let mut context = context_at_distance(20.0);
+        let phase_changes = |context: &mut BehaviorContext, health: f64| {
+            context.set_entity_health(health, 100.0);
+            boss.evaluate(context);
+            context
+                .take_events()
+                .into_iter()
+                .filter(|event| event.kind() == BehaviorEventKind::PhaseChange)
+                .map(|event| event.value())
+                .collect::<Vec<f64>>()
+        };
+
+        assert!(phase_changes(&mut context, 90.0).is_empty());
+        assert!(phase_changes(&mut context, 60.0).is_empty());
+        assert_eq!(phase_changes(&mut context, 45.0), vec![2.0]);
+        assert!(phase_changes(&mut context, 30.0).is_empty());
+        // Healing back out of the phase announces the earlier one
+        assert_eq!(phase_changes(&mut context, 70.0), vec![1.0]);
+    }
+
+    #[test]
+    fn negated_condition_matches_an_inverter() {
+        let mut negated = BehaviorTree::new();
+        let not_low = negated.create_condition_node_negated(1, 0.3);
+        negated.set_root(not_low);
+
+        let mut inverted = BehaviorTree::new();
+        let inverter = inverted.create_inverter_node();
+        let low = inverted.create_condition_node(1, 0.3);
+        inverted.add_child(inverter, low);
+        inverted.set_root(inverter);
+
+        for (health, expected) in [(80.0, SUCCESS), (30.0, FAILURE), (10.0, FAILURE)] {
+            let mut context = BehaviorContext::new();
+            context.set_entity_health(health, 100.0);
+            assert_eq!(negated.evaluate(&mut context), expected);
+            assert_eq!(inverted.evaluate(&mut context), expected);
         }
+
+        // The setter toggles the same flag back off
+        negated.set_negated(not_low, false);
+        let mut context = BehaviorContext::new();
+        context.set_entity_health(10.0, 100.0);
+        assert_eq!(negated.evaluate(&mut context), SUCCESS);
     }
 
-    fn evaluate_action(&self, node: &Node, context: &mut BehaviorContext) -> NodeStatus {
-        match node.action_type {
-            // Move towards target
-            0 => {
-                // In a real implementation, we would actually move the entity
-                // For now, just return success
-                context.set_value("action", 0.0); // Move action
-                context.set_value("action_parameter", node.parameter);
-                NodeStatus::Success
-            },
-            // Attack target
-            1 => {
-                // In a real implementation, we would actually attack
-                // For now, just return success if in range
-                let distance = context.get_distance_to_target();
-                if distance <= node.parameter {
-                    context.set_value("action", 1.0); // Attack action
-                    context.set_value("action_parameter", node.parameter);
-                    NodeStatus::Success
-                } else {
-                    NodeStatus::Failure
-                }
-            },
-            // Flee from target
-            2 => {
-                // In a real implementation, we would actually flee
-                // For now, just return success
-                context.set_value("action", 2.0); // Flee action
-                context.set_value("action_parameter", node.parameter);
-                NodeStatus::Success
-            },
-            // Wait for a duration
-            3 => {
-                // In a real implementation, we would actually wait
-                // For now, just return success
-                context.set_value("action", 3.0); // Wait action
-                context.set_value("action_parameter", node.parameter);
-                NodeStatus::Success
-            },
-            // Special ability
-            4 => {
-                // In a real implementation, we would use a special ability
-                // For now, just return success
-                context.set_value("action", 4.0); // Special ability action
-                context.set_value("action_parameter", node.parameter);
-                NodeStatus::Success
-            },
-            // Set cooldown
-            5 => {
-                let cooldown_key = format!("cooldown_{}", node.parameter as u32);
-                context.set_value(&cooldown_key, node.parameter);
-                NodeStatus::Success
-            },
-            // Default
-            _ => NodeStatus::Failure,
+    #[test]
+    fn commit_state_survives_a_json_round_trip() {
+        let mut tree = BehaviorTree::new();
+        let commit = tree.create_commit_node(3);
+        let attack = tree.create_action_node(1, 2.0);
+        let chase = tree.create_action_node(0, 5.0);
+        let selector = tree.create_selector_with_children(&[attack, chase]);
+        tree.add_child(commit, selector);
+        tree.set_root(commit);
+
+        // An attack has no move vector or ability, so those stay unset
+        let mut context = context_at_distance(1.0);
+        tree.evaluate(&mut context);
+        assert!(!context.values.contains_key(&format!("commit_{}_move_x", commit)));
+        assert!(!context.values.contains_key(&format!("commit_{}_prior", commit)));
+
+        let mut restored = BehaviorContext::parse_json(&context.to_json()).unwrap();
+        restored.set_target_position(10.0, 0.0, 0.0);
+        assert_eq!(tree.evaluate(&mut restored), SUCCESS);
+        assert_eq!(restored.get_value("action"), 1.0);
+        assert_eq!(restored.output().attack_damage(), Some(2.0));
+        assert!(restored.output().move_vector().is_none());
+    }
+
+    #[test]
+    fn commit_records_its_action_writes_in_the_change_log() {
+        let mut tree = BehaviorTree::new();
+        let commit = tree.create_commit_node(3);
+        let healthy = tree.create_condition_node(1, 1.0);
+        tree.add_child(commit, healthy);
+        tree.set_root(commit);
+
+        let mut context = BehaviorContext::new();
+        context.set_value("action", 2.0);
+        context.set_change_recording(true);
+        tree.evaluate(&mut context);
+        let actions: Vec<_> = context.take_change_log().into_iter()
+            .filter(|record| record.key() == "action")
+            .map(|record| (record.old_value(), record.new_value()))
+            .collect();
+        assert_eq!(actions, vec![(Some(2.0), None), (None, Some(2.0))]);
+    }
+
+    #[test]
+    fn reset_runtime_state_keeps_user_keys_sharing_runtime_prefixes() {
+        let mut tree = BehaviorTree::new();
+        let chase = tree.create_action_node(0, 4.0);
+        tree.set_root(chase);
+
+        let mut context = context_at_distance(10.0);
+        let user_keys = ["attack_power", "actionable", "move_speed", "phase_count", "node_label", "cooldown_scale"];
+        for key in user_keys {
+            context.set_value(key, 1.0);
         }
+        context.set_value("cooldown_3", 2.0);
+        tree.evaluate(&mut context);
+
+        tree.reset_runtime_state(&mut context);
+        let mut remaining: Vec<_> = context.values.keys().cloned().collect();
+        remaining.sort();
+        let mut expected: Vec<_> = user_keys.iter().map(|key| key.to_string()).collect();
+        expected.sort();
+        assert_eq!(remaining, expected);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn change_recording_logs_cooldowns_counting_down() {
+        let mut context = BehaviorContext::new();
+        context.set_value("cooldown_1", 1.0);
+        context.set_value("cooldown_2", 0.0);
+        context.set_change_recording(true);
+        context.tick(0.25);
+
+        let log = context.take_change_log();
+        assert_eq!(log.len(), 1);
+        assert_eq!(log[0].key(), "cooldown_1");
+        assert_eq!((log[0].old_value(), log[0].new_value()), (Some(1.0), Some(0.75)));
+    }
+}
@@ -0,0 +1,71 @@
+use crate::math::Vector3;
+use crate::physics::Aabb;
+
+// Score bonus for a cover point whose line of sight to the threat is blocked
+const BLOCKED_SIGHT_BONUS: f64 = 100.0;
+// Score lost per unit of distance the entity must travel to reach the cover
+const DISTANCE_PENALTY: f64 = 1.0;
+
+// Score a cover point: breaking line of sight to the threat is rewarded,
+// distance from the entity is penalized. Higher is better.
+pub fn score_cover(cover: &Vector3, entity: &Vector3, threat: &Vector3, occluders: &[Aabb]) -> f64 {
+    let blocked = occluders
+        .iter()
+        .any(|occluder| occluder.intersects_segment(cover, threat));
+
+    let mut score = -(cover.distance(entity) as f64) * DISTANCE_PENALTY;
+    if blocked {
+        score += BLOCKED_SIGHT_BONUS;
+    }
+    score
+}
+
+// Pick the highest scoring cover point from an xyz-interleaved buffer of
+// `count` candidates. Returns -1 when there are no candidates.
+pub fn best_cover_index(
+    candidates: &[f32],
+    count: usize,
+    entity: &Vector3,
+    threat: &Vector3,
+    occluders: &[Aabb],
+) -> i32 {
+    let count = count.min(candidates.len() / 3);
+    let mut best_index = -1;
+    let mut best_score = f64::NEG_INFINITY;
+
+    for i in 0..count {
+        let cover = Vector3::new(candidates[i * 3], candidates[i * 3 + 1], candidates[i * 3 + 2]);
+        let score = score_cover(&cover, entity, threat, occluders);
+        if score > best_score {
+            best_score = score;
+            best_index = i as i32;
+        }
+    }
+
+    best_index
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cover_behind_an_occluder_beats_closer_open_ground() {
+        let wall = Aabb::new(&Vector3::new(4.0, 0.0, -1.0), &Vector3::new(5.0, 2.0, 1.0));
+        let threat = Vector3::new(10.0, 1.0, 0.0);
+        let entity = Vector3::new(0.0, 1.0, 0.0);
+        let hidden = Vector3::new(3.0, 1.0, 0.0);
+        let open = Vector3::new(0.0, 1.0, -2.5);
+
+        assert!(wall.intersects_segment(&hidden, &threat));
+        assert!(!wall.intersects_segment(&open, &threat));
+        assert!(score_cover(&hidden, &entity, &threat, &[wall]) > score_cover(&open, &entity, &threat, &[wall]));
+        assert_eq!(score_cover(&open, &entity, &threat, &[wall]), -2.5);
+
+        let candidates = [open.x, open.y, open.z, hidden.x, hidden.y, hidden.z];
+        assert_eq!(best_cover_index(&candidates, 2, &entity, &threat, &[wall]), 1);
+        // Without occluders the nearest point wins
+        assert_eq!(best_cover_index(&candidates, 2, &entity, &threat, &[]), 0);
+        assert_eq!(best_cover_index(&candidates, 0, &entity, &threat, &[wall]), -1);
+    }
+}
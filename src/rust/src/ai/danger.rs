@@ -0,0 +1,117 @@
+use wasm_bindgen::prelude::*;
+use std::collections::HashMap;
+use crate::math::Vector3;
+
+// Coarse XZ grid of accumulated danger (the player's line of fire, recent
+// hits) that AI positioning can steer away from. Cells are stored sparsely
+// and every cell decays exponentially at `decay_rate` per second.
+#[wasm_bindgen]
+pub struct DangerMap {
+    cell_size: f32,
+    decay_rate: f64,
+    cells: HashMap<(i32, i32), f64>,
+}
+
+// Share of a deposit that spills into each of the eight neighbouring cells
+const NEIGHBOUR_SHARE: f64 = 0.5;
+
+// Cells whose danger has decayed below this are dropped
+const MIN_DANGER: f64 = 1e-4;
+
+#[wasm_bindgen]
+impl DangerMap {
+    #[wasm_bindgen(constructor)]
+    pub fn new(cell_size: f32, decay_rate: f64) -> DangerMap {
+        DangerMap {
+            cell_size: cell_size.max(0.01),
+            decay_rate: decay_rate.max(0.0),
+            cells: HashMap::new(),
+        }
+    }
+
+    // Deposit `amount` in the cell containing `position`, and half of it in
+    // the surrounding cells so nearby positions are also discouraged
+    pub fn add_danger(&mut self, position: &Vector3, amount: f64) {
+        let (cx, cz) = self.cell_of(position);
+        for dz in -1..=1 {
+            for dx in -1..=1 {
+                let share = if dx == 0 && dz == 0 { 1.0 } else { NEIGHBOUR_SHARE };
+                *self.cells.entry((cx + dx, cz + dz)).or_insert(0.0) += amount * share;
+            }
+        }
+    }
+
+    // Deposit `amount` in every cell crossed by the segment from -> to, e.g.
+    // along the player's aim. Cells are sampled at half-cell steps.
+    pub fn add_line_danger(&mut self, from: &Vector3, to: &Vector3, amount: f64) {
+        let length = from.distance(to);
+        let steps = ((length / (self.cell_size * 0.5)).ceil() as usize).max(1);
+        let mut previous = None;
+        for step in 0..=steps {
+            let t = step as f32 / steps as f32;
+            let point = from.add(&to.subtract(from).multiply(t));
+            let cell = self.cell_of(&point);
+            if previous != Some(cell) {
+                *self.cells.entry(cell).or_insert(0.0) += amount;
+                previous = Some(cell);
+            }
+        }
+    }
+
+    // Danger of the cell containing `position`
+    pub fn danger_at(&self, position: &Vector3) -> f64 {
+        self.cells.get(&self.cell_of(position)).copied().unwrap_or(0.0)
+    }
+
+    // Decay every cell by `delta_time` seconds
+    pub fn decay(&mut self, delta_time: f64) {
+        let factor = (-self.decay_rate * delta_time.max(0.0)).exp();
+        self.cells.retain(|_, danger| {
+            *danger *= factor;
+            *danger > MIN_DANGER
+        });
+    }
+
+    pub fn clear(&mut self) {
+        self.cells.clear();
+    }
+
+    pub fn cell_count(&self) -> usize {
+        self.cells.len()
+    }
+}
+
+impl DangerMap {
+    fn cell_of(&self, position: &Vector3) -> (i32, i32) {
+        (
+            (position.x / self.cell_size).floor() as i32,
+            (position.z / self.cell_size).floor() as i32,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn danger_spreads_nearby_decays_and_leaves_distant_cells_clear() {
+        let mut map = DangerMap::new(2.0, std::f64::consts::LN_2);
+        let hit = Vector3::new(1.0, 0.0, 1.0);
+        map.add_danger(&hit, 10.0);
+        assert_eq!(map.danger_at(&hit), 10.0);
+        assert_eq!(map.danger_at(&Vector3::new(3.0, 5.0, 1.0)), 5.0);
+        assert_eq!(map.danger_at(&Vector3::new(21.0, 0.0, 21.0)), 0.0);
+
+        // A decay rate of ln 2 halves danger every second
+        map.decay(1.0);
+        assert!((map.danger_at(&hit) - 5.0).abs() < 1e-9);
+        map.decay(30.0);
+        assert_eq!(map.cell_count(), 0);
+
+        map.add_line_danger(&Vector3::new(0.5, 0.0, 0.5), &Vector3::new(7.5, 0.0, 0.5), 1.0);
+        assert_eq!(map.cell_count(), 4);
+        assert_eq!(map.danger_at(&Vector3::new(4.0, 0.0, 0.0)), 1.0);
+        assert_eq!(map.danger_at(&Vector3::new(4.0, 0.0, 3.0)), 0.0);
+    }
+}
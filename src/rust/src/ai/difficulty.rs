@@ -0,0 +1,71 @@
+use wasm_bindgen::prelude::*;
+
+// Interpolation shape for a difficulty ramp
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CurveShape {
+    Linear,
+    // Smoothstep: gentle start and finish, steepest mid-ramp
+    EaseInOut,
+}
+
+// Difficulty that ramps from `base` to `max` over `ramp_duration` seconds of
+// match time. Tree parameters can be scaled by `value_at` when spawning.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug)]
+pub struct DifficultyCurve {
+    base: f64,
+    max: f64,
+    ramp_duration: f64,
+    shape: CurveShape,
+}
+
+#[wasm_bindgen]
+impl DifficultyCurve {
+    #[wasm_bindgen(constructor)]
+    pub fn new(base: f64, max: f64, ramp_duration: f64) -> DifficultyCurve {
+        DifficultyCurve {
+            base,
+            max,
+            ramp_duration,
+            shape: CurveShape::Linear,
+        }
+    }
+
+    pub fn set_shape(&mut self, shape: CurveShape) {
+        self.shape = shape;
+    }
+
+    pub fn value_at(&self, elapsed_seconds: f64) -> f64 {
+        if self.ramp_duration <= 0.0 {
+            return self.max;
+        }
+
+        let t = (elapsed_seconds / self.ramp_duration).clamp(0.0, 1.0);
+        let t = match self.shape {
+            CurveShape::Linear => t,
+            CurveShape::EaseInOut => t * t * (3.0 - 2.0 * t),
+        };
+        self.base + (self.max - self.base) * t
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn difficulty_curve_ramps_from_base_to_max() {
+        let mut curve = DifficultyCurve::new(1.0, 3.0, 100.0);
+        assert_eq!(curve.value_at(0.0), 1.0);
+        assert_eq!(curve.value_at(25.0), 1.5);
+        assert_eq!(curve.value_at(100.0), 3.0);
+        assert_eq!(curve.value_at(500.0), 3.0);
+
+        // Ease starts slower, meets linear at the midpoint and clamps the same way
+        curve.set_shape(CurveShape::EaseInOut);
+        assert!(curve.value_at(25.0) < 1.5);
+        assert_eq!(curve.value_at(50.0), 2.0);
+        assert_eq!(curve.value_at(500.0), 3.0);
+    }
+}
@@ -1,4 +1,5 @@
 use wasm_bindgen::prelude::*;
+use std::cell::OnceCell;
 use super::behavior_tree::BehaviorTree;
 
 // Enemy types
@@ -15,13 +16,23 @@ pub enum EnemyType {
 // Factory for creating behavior trees for different enemy types
 #[wasm_bindgen]
 pub struct EnemyFactory {
+    // Engagement range of every type, indexed by EnemyType, built on first use
+    engagement_ranges: OnceCell<[f64; 5]>,
+}
+
+impl Default for EnemyFactory {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 #[wasm_bindgen]
 impl EnemyFactory {
     #[wasm_bindgen(constructor)]
     pub fn new() -> EnemyFactory {
-        EnemyFactory {}
+        EnemyFactory {
+            engagement_ranges: OnceCell::new(),
+        }
     }
     
     // Create a behavior tree for a specific enemy type
@@ -35,37 +46,37 @@ impl EnemyFactory {
         }
     }
     
+    // Farthest distance at which this enemy type attacks, read from the range
+    // conditions guarding the attacks in its tree. Level tools use it to keep
+    // spawns within reach of the player.
+    pub fn engagement_range(&self, enemy_type: EnemyType) -> f64 {
+        let ranges = self.engagement_ranges.get_or_init(|| {
+            [EnemyType::Grunt, EnemyType::Sniper, EnemyType::Tank, EnemyType::Scout, EnemyType::Boss]
+                .map(|enemy_type| self.create_behavior_tree(enemy_type).attack_range())
+        });
+        ranges[enemy_type as usize]
+    }
+    
     // Create a behavior tree for a Grunt enemy
     // Grunts are basic enemies that chase the player and attack when in range
     fn create_grunt_behavior_tree(&self) -> BehaviorTree {
         let mut tree = BehaviorTree::new();
         
-        // Create the root selector node
-        let root = tree.create_selector_node();
-        
         // Create a sequence for when health is low (flee)
-        let low_health_sequence = tree.create_sequence_node();
         let is_low_health = tree.create_condition_node(1, 0.3); // Health below 30%
         let flee_action = tree.create_action_node(2, 10.0); // Flee with speed 10
-        
-        tree.add_child(low_health_sequence, is_low_health);
-        tree.add_child(low_health_sequence, flee_action);
+        let low_health_sequence = tree.create_sequence_with_children(&[is_low_health, flee_action]);
         
         // Create a sequence for attacking
-        let attack_sequence = tree.create_sequence_node();
         let is_in_attack_range = tree.create_condition_node(0, 2.0); // Target within 2 units
         let attack_action = tree.create_action_node(1, 10.0); // Attack with 10 damage
-        
-        tree.add_child(attack_sequence, is_in_attack_range);
-        tree.add_child(attack_sequence, attack_action);
+        let attack_sequence = tree.create_sequence_with_children(&[is_in_attack_range, attack_action]);
         
         // Create a chase action
         let chase_action = tree.create_action_node(0, 5.0); // Move with speed 5
         
-        // Add all to root
-        tree.add_child(root, low_health_sequence);
-        tree.add_child(root, attack_sequence);
-        tree.add_child(root, chase_action);
+        // Create the root selector node
+        let root = tree.create_selector_with_children(&[low_health_sequence, attack_sequence, chase_action]);
         
         tree.set_root(root);
         tree
@@ -76,45 +87,38 @@ impl EnemyFactory {
     fn create_sniper_behavior_tree(&self) -> BehaviorTree {
         let mut tree = BehaviorTree::new();
         
-        // Create the root selector node
-        let root = tree.create_selector_node();
-        
         // Create a sequence for when health is low (flee)
-        let low_health_sequence = tree.create_sequence_node();
         let is_low_health = tree.create_condition_node(1, 0.4); // Health below 40%
         let flee_action = tree.create_action_node(2, 8.0); // Flee with speed 8
-        
-        tree.add_child(low_health_sequence, is_low_health);
-        tree.add_child(low_health_sequence, flee_action);
+        let low_health_sequence = tree.create_sequence_with_children(&[is_low_health, flee_action]);
         
         // Create a sequence for when target is too close (back away)
-        let too_close_sequence = tree.create_sequence_node();
         let is_too_close = tree.create_condition_node(0, 10.0); // Target within 10 units
         let back_away_action = tree.create_action_node(2, 6.0); // Flee with speed 6
-        
-        tree.add_child(too_close_sequence, is_too_close);
-        tree.add_child(too_close_sequence, back_away_action);
+        let too_close_sequence = tree.create_sequence_with_children(&[is_too_close, back_away_action]);
         
         // Create a sequence for attacking
-        let attack_sequence = tree.create_sequence_node();
         let is_in_attack_range = tree.create_condition_node(0, 30.0); // Target within 30 units
         let is_cooldown_ready = tree.create_condition_node(4, 1.0); // Cooldown 1 is ready
         let attack_action = tree.create_action_node(1, 25.0); // Attack with 25 damage
         let set_cooldown = tree.create_action_node(5, 3.0); // Set cooldown 1 to 3 seconds
-        
-        tree.add_child(attack_sequence, is_in_attack_range);
-        tree.add_child(attack_sequence, is_cooldown_ready);
-        tree.add_child(attack_sequence, attack_action);
-        tree.add_child(attack_sequence, set_cooldown);
+        let attack_sequence = tree.create_sequence_with_children(&[
+            is_in_attack_range,
+            is_cooldown_ready,
+            attack_action,
+            set_cooldown,
+        ]);
         
         // Create a find position action
         let find_position_action = tree.create_action_node(0, 3.0); // Move with speed 3
         
-        // Add all to root
-        tree.add_child(root, low_health_sequence);
-        tree.add_child(root, too_close_sequence);
-        tree.add_child(root, attack_sequence);
-        tree.add_child(root, find_position_action);
+        // Create the root selector node
+        let root = tree.create_selector_with_children(&[
+            low_health_sequence,
+            too_close_sequence,
+            attack_sequence,
+            find_position_action,
+        ]);
         
         tree.set_root(root);
         tree
@@ -125,36 +129,28 @@ impl EnemyFactory {
     fn create_tank_behavior_tree(&self) -> BehaviorTree {
         let mut tree = BehaviorTree::new();
         
-        // Create the root selector node
-        let root = tree.create_selector_node();
-        
         // Create a sequence for special ability (charge)
-        let special_sequence = tree.create_sequence_node();
         let is_cooldown_ready = tree.create_condition_node(4, 2.0); // Cooldown 2 is ready
         let is_in_charge_range = tree.create_condition_node(0, 15.0); // Target within 15 units
-        let charge_action = tree.create_action_node(4, 1.0); // Special ability 1 (charge)
-        let set_cooldown = tree.create_action_node(5, 10.0); // Set cooldown 2 to 10 seconds
-        
-        tree.add_child(special_sequence, is_cooldown_ready);
-        tree.add_child(special_sequence, is_in_charge_range);
-        tree.add_child(special_sequence, charge_action);
-        tree.add_child(special_sequence, set_cooldown);
+        let charge_action = tree.create_charge_node(12.0, 3.0, 40.0); // Charge at speed 12, hit for 40 within 3 units
+        let set_cooldown = tree.create_cooldown_node(2, 10.0); // Set cooldown 2 to 10 seconds
+        let special_sequence = tree.create_sequence_with_children(&[
+            is_cooldown_ready,
+            is_in_charge_range,
+            charge_action,
+            set_cooldown,
+        ]);
         
         // Create a sequence for attacking
-        let attack_sequence = tree.create_sequence_node();
         let is_in_attack_range = tree.create_condition_node(0, 3.0); // Target within 3 units
         let attack_action = tree.create_action_node(1, 20.0); // Attack with 20 damage
-        
-        tree.add_child(attack_sequence, is_in_attack_range);
-        tree.add_child(attack_sequence, attack_action);
+        let attack_sequence = tree.create_sequence_with_children(&[is_in_attack_range, attack_action]);
         
         // Create a chase action
         let chase_action = tree.create_action_node(0, 3.0); // Move with speed 3
         
-        // Add all to root
-        tree.add_child(root, special_sequence);
-        tree.add_child(root, attack_sequence);
-        tree.add_child(root, chase_action);
+        // Create the root selector node
+        let root = tree.create_selector_with_children(&[special_sequence, attack_sequence, chase_action]);
         
         tree.set_root(root);
         tree
@@ -165,38 +161,35 @@ impl EnemyFactory {
     fn create_scout_behavior_tree(&self) -> BehaviorTree {
         let mut tree = BehaviorTree::new();
         
-        // Create the root selector node
-        let root = tree.create_selector_node();
+        // Create a sequence for sidestepping incoming fire
+        let is_projectile_incoming = tree.create_condition_node(14, 1.5); // Projectile passes within 1.5 units
+        let dodge_action = tree.create_action_node(10, 4.0); // Sidestep 4 units
+        let dodge_sequence = tree.create_sequence_with_children(&[is_projectile_incoming, dodge_action]);
         
         // Create a sequence for hit and run
-        let hit_run_sequence = tree.create_sequence_node();
         let is_in_attack_range = tree.create_condition_node(0, 5.0); // Target within 5 units
         let is_cooldown_ready = tree.create_condition_node(4, 3.0); // Cooldown 3 is ready
         let attack_action = tree.create_action_node(1, 8.0); // Attack with 8 damage
         let set_cooldown = tree.create_action_node(5, 2.0); // Set cooldown 3 to 2 seconds
         let flee_action = tree.create_action_node(2, 12.0); // Flee with speed 12
-        
-        tree.add_child(hit_run_sequence, is_in_attack_range);
-        tree.add_child(hit_run_sequence, is_cooldown_ready);
-        tree.add_child(hit_run_sequence, attack_action);
-        tree.add_child(hit_run_sequence, set_cooldown);
-        tree.add_child(hit_run_sequence, flee_action);
+        let hit_run_sequence = tree.create_sequence_with_children(&[
+            is_in_attack_range,
+            is_cooldown_ready,
+            attack_action,
+            set_cooldown,
+            flee_action,
+        ]);
         
         // Create a sequence for circling the player
-        let circle_sequence = tree.create_sequence_node();
         let is_in_circle_range = tree.create_condition_node(0, 10.0); // Target within 10 units
         let circle_action = tree.create_action_node(4, 2.0); // Special ability 2 (circle)
-        
-        tree.add_child(circle_sequence, is_in_circle_range);
-        tree.add_child(circle_sequence, circle_action);
+        let circle_sequence = tree.create_sequence_with_children(&[is_in_circle_range, circle_action]);
         
         // Create an approach action
         let approach_action = tree.create_action_node(0, 8.0); // Move with speed 8
         
-        // Add all to root
-        tree.add_child(root, hit_run_sequence);
-        tree.add_child(root, circle_sequence);
-        tree.add_child(root, approach_action);
+        // Create the root selector node
+        let root = tree.create_selector_with_children(&[dodge_sequence, hit_run_sequence, circle_sequence, approach_action]);
         
         tree.set_root(root);
         tree
@@ -207,91 +200,143 @@ impl EnemyFactory {
     fn create_boss_behavior_tree(&self) -> BehaviorTree {
         let mut tree = BehaviorTree::new();
         
-        // Create the root selector node
-        let root = tree.create_selector_node();
-        
         // Create a sequence for phase 2 (health < 50%)
-        let phase2_sequence = tree.create_sequence_node();
-        let is_phase2 = tree.create_condition_node(1, 0.5); // Health below 50%
-        
-        // Phase 2 selector
-        let phase2_selector = tree.create_selector_node();
+        let is_phase2 = tree.create_phase_condition_node(0.5, 2); // Health below 50%, announces phase 2
         
+        // Special attacks 1 and 2 share cooldown group 1 so they never fire back-to-back
         // Special attack 1
-        let special1_sequence = tree.create_sequence_node();
+        let is_group1_ready_for_special1 = tree.create_condition_node(15, 1.0); // Group 1 is ready
         let is_cooldown1_ready = tree.create_condition_node(4, 4.0); // Cooldown 4 is ready
         let special1_action = tree.create_action_node(4, 3.0); // Special ability 3
         let set_cooldown1 = tree.create_action_node(5, 8.0); // Set cooldown 4 to 8 seconds
-        
-        tree.add_child(special1_sequence, is_cooldown1_ready);
-        tree.add_child(special1_sequence, special1_action);
-        tree.add_child(special1_sequence, set_cooldown1);
+        let start_group1_after_special1 = tree.create_group_cooldown_node(1, 3.0); // Block group 1 for 3 seconds
+        let special1_sequence = tree.create_sequence_with_children(&[
+            is_group1_ready_for_special1,
+            is_cooldown1_ready,
+            special1_action,
+            set_cooldown1,
+            start_group1_after_special1,
+        ]);
         
         // Special attack 2
-        let special2_sequence = tree.create_sequence_node();
+        let is_group1_ready_for_special2 = tree.create_condition_node(15, 1.0); // Group 1 is ready
         let is_cooldown2_ready = tree.create_condition_node(4, 5.0); // Cooldown 5 is ready
         let special2_action = tree.create_action_node(4, 4.0); // Special ability 4
         let set_cooldown2 = tree.create_action_node(5, 12.0); // Set cooldown 5 to 12 seconds
-        
-        tree.add_child(special2_sequence, is_cooldown2_ready);
-        tree.add_child(special2_sequence, special2_action);
-        tree.add_child(special2_sequence, set_cooldown2);
+        let start_group1_after_special2 = tree.create_group_cooldown_node(1, 3.0); // Block group 1 for 3 seconds
+        let special2_sequence = tree.create_sequence_with_children(&[
+            is_group1_ready_for_special2,
+            is_cooldown2_ready,
+            special2_action,
+            set_cooldown2,
+            start_group1_after_special2,
+        ]);
         
         // Normal attack
-        let attack_sequence = tree.create_sequence_node();
         let is_in_attack_range = tree.create_condition_node(0, 4.0); // Target within 4 units
         let attack_action = tree.create_action_node(1, 30.0); // Attack with 30 damage
-        
-        tree.add_child(attack_sequence, is_in_attack_range);
-        tree.add_child(attack_sequence, attack_action);
+        let attack_sequence = tree.create_sequence_with_children(&[is_in_attack_range, attack_action]);
         
         // Chase action
         let chase_action = tree.create_action_node(0, 6.0); // Move with speed 6
         
-        // Add all to phase 2 selector
-        tree.add_child(phase2_selector, special1_sequence);
-        tree.add_child(phase2_selector, special2_sequence);
-        tree.add_child(phase2_selector, attack_sequence);
-        tree.add_child(phase2_selector, chase_action);
-        
-        // Add phase 2 selector to phase 2 sequence
-        tree.add_child(phase2_sequence, is_phase2);
-        tree.add_child(phase2_sequence, phase2_selector);
-        
-        // Create a sequence for phase 1 (health >= 50%)
-        let phase1_selector = tree.create_selector_node();
+        // Phase 2 selector
+        let phase2_selector = tree.create_selector_with_children(&[
+            special1_sequence,
+            special2_sequence,
+            attack_sequence,
+            chase_action,
+        ]);
+        let phase2_sequence = tree.create_sequence_with_children(&[is_phase2, phase2_selector]);
         
         // Special attack
-        let phase1_special_sequence = tree.create_sequence_node();
         let is_phase1_cooldown_ready = tree.create_condition_node(4, 6.0); // Cooldown 6 is ready
         let phase1_special_action = tree.create_action_node(4, 5.0); // Special ability 5
         let phase1_set_cooldown = tree.create_action_node(5, 15.0); // Set cooldown 6 to 15 seconds
-        
-        tree.add_child(phase1_special_sequence, is_phase1_cooldown_ready);
-        tree.add_child(phase1_special_sequence, phase1_special_action);
-        tree.add_child(phase1_special_sequence, phase1_set_cooldown);
+        let phase1_special_sequence = tree.create_sequence_with_children(&[
+            is_phase1_cooldown_ready,
+            phase1_special_action,
+            phase1_set_cooldown,
+        ]);
         
         // Normal attack
-        let phase1_attack_sequence = tree.create_sequence_node();
         let phase1_is_in_attack_range = tree.create_condition_node(0, 5.0); // Target within 5 units
         let phase1_attack_action = tree.create_action_node(1, 20.0); // Attack with 20 damage
-        
-        tree.add_child(phase1_attack_sequence, phase1_is_in_attack_range);
-        tree.add_child(phase1_attack_sequence, phase1_attack_action);
+        let phase1_attack_sequence = tree.create_sequence_with_children(&[phase1_is_in_attack_range, phase1_attack_action]);
         
         // Chase action
         let phase1_chase_action = tree.create_action_node(0, 4.0); // Move with speed 4
         
-        // Add all to phase 1 selector
-        tree.add_child(phase1_selector, phase1_special_sequence);
-        tree.add_child(phase1_selector, phase1_attack_sequence);
-        tree.add_child(phase1_selector, phase1_chase_action);
+        // Create a selector for phase 1 (health >= 50%)
+        let phase1_selector = tree.create_selector_with_children(&[
+            phase1_special_sequence,
+            phase1_attack_sequence,
+            phase1_chase_action,
+        ]);
         
-        // Add all to root
-        tree.add_child(root, phase2_sequence);
-        tree.add_child(root, phase1_selector);
+        // Create the root selector node
+        let root = tree.create_selector_with_children(&[phase2_sequence, phase1_selector]);
         
         tree.set_root(root);
         tree
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::behavior_tree::BehaviorContext;
+
+    fn grunt_action(distance: f64, health: f64) -> f64 {
+        let tree = EnemyFactory::new().create_behavior_tree(EnemyType::Grunt);
+        let mut context = BehaviorContext::new();
+        context.set_target_position(distance, 0.0, 0.0);
+        context.set_entity_health(health, 100.0);
+        assert_eq!(tree.evaluate(&mut context), 1);
+        context.get_value("action")
+    }
+
+    #[test]
+    fn grunt_tree_built_from_composites_picks_branches_in_order() {
+        // Selector children run in the order they were passed in
+        assert_eq!(grunt_action(1.0, 100.0), 1.0);
+        assert_eq!(grunt_action(10.0, 100.0), 0.0);
+        assert_eq!(grunt_action(1.0, 10.0), 2.0);
+    }
+
+    #[test]
+    fn tank_charges_once_then_waits_on_its_cooldown() {
+        let tree = EnemyFactory::new().create_behavior_tree(EnemyType::Tank);
+        let mut context = BehaviorContext::new();
+
+        // Already in melee range: a plain attack rather than a charge hit
+        context.set_target_position(2.0, 0.0, 0.0);
+        tree.evaluate(&mut context);
+        assert_eq!(context.output().attack_damage(), Some(20.0));
+        assert!(context.take_events().is_empty());
+
+        context.set_target_position(10.0, 0.0, 0.0);
+        assert_eq!(tree.evaluate(&mut context), 2);
+        context.set_target_position(2.0, 0.0, 0.0);
+        tree.evaluate(&mut context);
+        assert_eq!(context.output().attack_damage(), Some(40.0));
+        assert_eq!(context.take_events().len(), 1);
+        assert_eq!(context.get_value("cooldown_2"), 10.0);
+
+        // The special stays gated while cooldown 2 runs
+        context.set_target_position(10.0, 0.0, 0.0);
+        tree.evaluate(&mut context);
+        assert_eq!(context.get_value("action"), 0.0);
+    }
+
+    #[test]
+    fn engagement_range_follows_each_tree_attack_range() {
+        let factory = EnemyFactory::new();
+        assert_eq!(factory.engagement_range(EnemyType::Grunt), 2.0);
+        assert_eq!(factory.engagement_range(EnemyType::Sniper), 30.0);
+        for enemy_type in [EnemyType::Grunt, EnemyType::Sniper, EnemyType::Tank, EnemyType::Scout, EnemyType::Boss] {
+            let tree_range = factory.create_behavior_tree(enemy_type).attack_range();
+            assert_eq!(factory.engagement_range(enemy_type), tree_range);
+        }
+    }
+}
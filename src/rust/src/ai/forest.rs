@@ -0,0 +1,200 @@
+use wasm_bindgen::prelude::*;
+use std::cell::RefCell;
+use std::rc::Rc;
+use super::behavior_tree::{BehaviorContext, BehaviorOutput, BehaviorTree};
+use super::squad::SquadState;
+use super::stimulus::StimulusMap;
+use crate::math::Vector3;
+use crate::physics::Aabb;
+
+struct WorldData {
+    occluders: Vec<Aabb>,
+    stimulus: Option<StimulusMap>,
+    squad: Option<SquadState>,
+}
+
+// Read-only world data shared by every entity in a forest. Clones share the
+// same data, so attaching it to a context copies only a handle.
+#[wasm_bindgen]
+#[derive(Clone)]
+pub struct WorldContext {
+    data: Rc<RefCell<WorldData>>,
+}
+
+impl Default for WorldContext {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[wasm_bindgen]
+impl WorldContext {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> WorldContext {
+        WorldContext {
+            data: Rc::new(RefCell::new(WorldData {
+                occluders: Vec::new(),
+                stimulus: None,
+                squad: None,
+            })),
+        }
+    }
+
+    // Static geometry that blocks line of sight
+    pub fn add_occluder(&mut self, occluder: &Aabb) {
+        self.data.borrow_mut().occluders.push(*occluder);
+    }
+
+    pub fn clear_occluders(&mut self) {
+        self.data.borrow_mut().occluders.clear();
+    }
+
+    pub fn occluder_count(&self) -> usize {
+        self.data.borrow().occluders.len()
+    }
+
+    // Noise map heard by entities without one of their own
+    pub fn set_stimulus_map(&mut self, stimulus: &StimulusMap) {
+        self.data.borrow_mut().stimulus = Some(stimulus.clone());
+    }
+
+    // Squad used by entities without one of their own
+    pub fn set_squad_state(&mut self, squad: &SquadState) {
+        self.data.borrow_mut().squad = Some(squad.clone());
+    }
+}
+
+impl WorldContext {
+    // Does any occluder cut the segment between the two points?
+    pub(crate) fn line_blocked(&self, from: &Vector3, to: &Vector3) -> bool {
+        self.data
+            .borrow()
+            .occluders
+            .iter()
+            .any(|occluder| occluder.intersects_segment(from, to))
+    }
+
+    pub(crate) fn stimulus(&self) -> Option<StimulusMap> {
+        self.data.borrow().stimulus.clone()
+    }
+
+    pub(crate) fn squad(&self) -> Option<SquadState> {
+        self.data.borrow().squad.clone()
+    }
+}
+
+struct ForestEntity {
+    tree_index: usize,
+    context: BehaviorContext,
+}
+
+// A set of behavior trees and the entities running them, evaluated together
+// against one shared WorldContext. Entities may share a tree; each keeps its
+// own BehaviorContext.
+#[wasm_bindgen]
+#[derive(Default)]
+pub struct BehaviorForest {
+    trees: Vec<BehaviorTree>,
+    entities: Vec<ForestEntity>,
+}
+
+#[wasm_bindgen]
+impl BehaviorForest {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> BehaviorForest {
+        BehaviorForest {
+            trees: Vec::new(),
+            entities: Vec::new(),
+        }
+    }
+
+    // Returns the tree's index for add_entity
+    pub fn add_tree(&mut self, tree: BehaviorTree) -> usize {
+        self.trees.push(tree);
+        self.trees.len() - 1
+    }
+
+    // Returns the entity's index, or -1 if the tree index is unknown
+    pub fn add_entity(&mut self, tree_index: usize, context: BehaviorContext) -> i32 {
+        if tree_index >= self.trees.len() {
+            return -1;
+        }
+        self.entities.push(ForestEntity { tree_index, context });
+        (self.entities.len() - 1) as i32
+    }
+
+    pub fn entity_count(&self) -> usize {
+        self.entities.len()
+    }
+
+    pub fn set_entity_position(&mut self, entity: usize, x: f64, y: f64, z: f64) {
+        if let Some(context) = self.context_mut(entity) {
+            context.set_entity_position(x, y, z);
+        }
+    }
+
+    pub fn set_target_position(&mut self, entity: usize, x: f64, y: f64, z: f64) {
+        if let Some(context) = self.context_mut(entity) {
+            context.set_target_position(x, y, z);
+        }
+    }
+
+    pub fn get_value(&self, entity: usize, key: &str) -> f64 {
+        self.context(entity).map_or(0.0, |context| context.get_value(key))
+    }
+
+    pub fn output(&self, entity: usize) -> BehaviorOutput {
+        self.context(entity).map(|context| context.output()).unwrap_or_default()
+    }
+
+    // Evaluate every entity's tree with `world` attached to its context.
+    // Returns one status code per entity in insertion order.
+    pub fn evaluate_all(&mut self, world: &WorldContext) -> Vec<i32> {
+        let trees = &self.trees;
+        self.entities
+            .iter_mut()
+            .map(|entity| {
+                entity.context.set_world_context(world);
+                trees[entity.tree_index].evaluate(&mut entity.context)
+            })
+            .collect()
+    }
+}
+
+impl BehaviorForest {
+    pub fn context(&self, entity: usize) -> Option<&BehaviorContext> {
+        self.entities.get(entity).map(|entity| &entity.context)
+    }
+
+    pub fn context_mut(&mut self, entity: usize) -> Option<&mut BehaviorContext> {
+        self.entities.get_mut(entity).map(|entity| &mut entity.context)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shared_occluder_blocks_sight_for_every_entity() {
+        let mut tree = BehaviorTree::new();
+        let visible = tree.create_condition_node(3, 0.0);
+        tree.set_root(visible);
+
+        let mut forest = BehaviorForest::new();
+        let tree_index = forest.add_tree(tree);
+        for z in [-1.0, 0.0, 1.0] {
+            let entity = forest.add_entity(tree_index, BehaviorContext::new()) as usize;
+            forest.set_entity_position(entity, 0.0, 0.0, z);
+            forest.set_target_position(entity, 10.0, 0.0, z);
+        }
+
+        let mut world = WorldContext::new();
+        assert_eq!(forest.evaluate_all(&world), vec![1, 1, 1]);
+
+        // One wall between every entity and its target
+        world.add_occluder(&Aabb::new(&Vector3::new(4.0, -2.0, -2.0), &Vector3::new(5.0, 2.0, 2.0)));
+        assert_eq!(forest.evaluate_all(&world), vec![0, 0, 0]);
+        assert_eq!(forest.add_entity(7, BehaviorContext::new()), -1);
+    }
+}
@@ -0,0 +1,98 @@
+use wasm_bindgen::prelude::*;
+use std::f32::consts::PI;
+use crate::math::Vector3;
+
+// Formation kinds accepted by formation_positions
+const FORMATION_CIRCLE: u32 = 0;
+const FORMATION_GRID: u32 = 1;
+const FORMATION_LINE: u32 = 2;
+const FORMATION_WEDGE: u32 = 3;
+
+// Spawn positions on the XZ plane at the center's height, xyz-interleaved.
+// Kinds: 0 circle, 1 grid, 2 line (along X), 3 wedge (trailing toward -Z).
+// Neighbouring slots are `spacing` apart; unknown kinds return no positions.
+#[wasm_bindgen]
+pub fn formation_positions(kind: u32, count: usize, center: &Vector3, spacing: f32) -> Vec<f32> {
+    let mut positions = Vec::with_capacity(count * 3);
+    let mut push = |x: f32, z: f32| {
+        positions.push(center.x + x);
+        positions.push(center.y);
+        positions.push(center.z + z);
+    };
+
+    match kind {
+        FORMATION_CIRCLE => {
+            // Radius chosen so the chord between neighbours equals `spacing`
+            let radius = if count > 1 { spacing / (2.0 * (PI / count as f32).sin()) } else { 0.0 };
+            for i in 0..count {
+                let angle = 2.0 * PI * i as f32 / count as f32;
+                push(angle.sin() * radius, angle.cos() * radius);
+            }
+        },
+        FORMATION_GRID => {
+            let columns = (count as f32).sqrt().ceil().max(1.0) as usize;
+            let rows = count.div_ceil(columns);
+            let half_width = (columns - 1) as f32 * spacing * 0.5;
+            let half_depth = rows.saturating_sub(1) as f32 * spacing * 0.5;
+            for i in 0..count {
+                let column = (i % columns) as f32;
+                let row = (i / columns) as f32;
+                push(column * spacing - half_width, half_depth - row * spacing);
+            }
+        },
+        FORMATION_LINE => {
+            let half_width = count.saturating_sub(1) as f32 * spacing * 0.5;
+            for i in 0..count {
+                push(i as f32 * spacing - half_width, 0.0);
+            }
+        },
+        FORMATION_WEDGE => {
+            // Leader at the center, then pairs alternating right and left
+            for i in 0..count {
+                let rank = i.div_ceil(2) as f32;
+                let side = if i % 2 == 1 { 1.0 } else { -1.0 };
+                push(side * rank * spacing, -rank * spacing);
+            }
+        },
+        _ => {},
+    }
+    positions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn points(positions: &[f32]) -> Vec<(f32, f32, f32)> {
+        positions.chunks(3).map(|p| (p[0], p[1], p[2])).collect()
+    }
+
+    #[test]
+    fn circle_places_points_equidistant_and_evenly_spaced() {
+        let center = Vector3::new(10.0, 2.0, -5.0);
+        let circle = points(&formation_positions(0, 6, &center, 3.0));
+        assert_eq!(circle.len(), 6);
+        // Six points a chord of 3 apart sit on a radius-3 circle
+        for (i, &(x, y, z)) in circle.iter().enumerate() {
+            assert_eq!(y, 2.0);
+            assert!((Vector3::new(x, y, z).distance(&center) - 3.0).abs() < 1e-4);
+            let (nx, ny, nz) = circle[(i + 1) % circle.len()];
+            assert!((Vector3::new(x, y, z).distance(&Vector3::new(nx, ny, nz)) - 3.0).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn grid_and_line_form_rows_and_columns() {
+        let center = Vector3::new(0.0, 0.0, 0.0);
+        let grid = points(&formation_positions(1, 6, &center, 2.0));
+        // Six slots fill three columns and two rows around the center
+        assert_eq!(grid, vec![
+            (-2.0, 0.0, 1.0), (0.0, 0.0, 1.0), (2.0, 0.0, 1.0),
+            (-2.0, 0.0, -1.0), (0.0, 0.0, -1.0), (2.0, 0.0, -1.0),
+        ]);
+
+        let line = points(&formation_positions(2, 3, &center, 2.0));
+        assert_eq!(line, vec![(-2.0, 0.0, 0.0), (0.0, 0.0, 0.0), (2.0, 0.0, 0.0)]);
+        assert!(formation_positions(9, 3, &center, 2.0).is_empty());
+    }
+}
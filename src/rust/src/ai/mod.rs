@@ -1,5 +1,29 @@
+pub mod attack_coordinator;
 pub mod behavior_tree;
+pub mod cover;
+pub mod danger;
+pub mod difficulty;
 pub mod enemy_types;
+pub mod forest;
+pub mod formation;
+pub mod registry;
+pub mod scheduler;
+pub mod spacing;
+pub mod squad;
+pub mod stimulus;
+pub mod target_selector;
 
-pub use behavior_tree::{BehaviorTree, BehaviorContext, NodeType, NodeStatus};
-pub use enemy_types::{EnemyType, EnemyFactory};
\ No newline at end of file
+pub use attack_coordinator::AttackCoordinator;
+pub use behavior_tree::{BehaviorTree, BehaviorContext, BehaviorEvent, BehaviorEventKind, BehaviorOutput, ChangeRecord, CompareOp, NodeType, NodeStatus, TreeStats};
+pub use cover::{score_cover, best_cover_index};
+pub use danger::DangerMap;
+pub use difficulty::{CurveShape, DifficultyCurve};
+pub use enemy_types::{EnemyType, EnemyFactory};
+pub use forest::{BehaviorForest, WorldContext};
+pub use formation::formation_positions;
+pub use registry::TreeRegistry;
+pub use scheduler::AiScheduler;
+pub use spacing::spacing_adjustment;
+pub use squad::SquadState;
+pub use stimulus::StimulusMap;
+pub use target_selector::TargetSelector;
\ No newline at end of file
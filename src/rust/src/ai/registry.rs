@@ -0,0 +1,110 @@
+use wasm_bindgen::prelude::*;
+use std::collections::HashMap;
+use super::behavior_tree::BehaviorTree;
+
+// Named behavior tree templates registered at runtime as JSON (the format of
+// `BehaviorTree::to_json`), so enemy definitions can live in data rather
+// than in factory code. Every instantiation is an independent tree.
+#[wasm_bindgen]
+#[derive(Default)]
+pub struct TreeRegistry {
+    templates: HashMap<String, String>,
+}
+
+#[wasm_bindgen]
+impl TreeRegistry {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> TreeRegistry {
+        TreeRegistry {
+            templates: HashMap::new(),
+        }
+    }
+
+    // Add or replace the template `name`. Fails without registering when the
+    // JSON is not a valid tree, including one with dangling node references.
+    pub fn register(&mut self, name: &str, json: &str) -> Result<(), JsValue> {
+        self.register_template(name, json).map_err(|err| JsValue::from_str(&err))
+    }
+
+    // Build a fresh tree from the template `name`
+    pub fn instantiate(&self, name: &str) -> Result<BehaviorTree, JsValue> {
+        self.instantiate_template(name).map_err(|err| JsValue::from_str(&err))
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.templates.contains_key(name)
+    }
+
+    pub fn remove(&mut self, name: &str) -> bool {
+        self.templates.remove(name).is_some()
+    }
+
+    // Registered template names in alphabetical order
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.templates.keys().cloned().collect();
+        names.sort();
+        names
+    }
+}
+
+impl TreeRegistry {
+    fn register_template(&mut self, name: &str, json: &str) -> Result<(), String> {
+        BehaviorTree::parse_json(json)?;
+        self.templates.insert(name.to_string(), json.to_string());
+        Ok(())
+    }
+
+    fn instantiate_template(&self, name: &str) -> Result<BehaviorTree, String> {
+        let json = self
+            .templates
+            .get(name)
+            .ok_or_else(|| format!("unknown tree template: {}", name))?;
+        BehaviorTree::parse_json(json)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::behavior_tree::BehaviorContext;
+
+    fn grunt_json() -> String {
+        let mut tree = BehaviorTree::new();
+        let in_range = tree.create_condition_node(0, 2.0);
+        let attack = tree.create_action_node(1, 10.0);
+        let sequence = tree.create_sequence_with_children(&[in_range, attack]);
+        tree.set_root(sequence);
+        tree.to_json()
+    }
+
+    #[test]
+    fn instances_are_independent_trees() {
+        let mut registry = TreeRegistry::new();
+        registry.register_template("grunt", &grunt_json()).unwrap();
+        registry.register_template("alpha", &grunt_json()).unwrap();
+        assert_eq!(registry.names(), vec!["alpha".to_string(), "grunt".to_string()]);
+
+        let mut first = registry.instantiate_template("grunt").ok().unwrap();
+        let second = registry.instantiate_template("grunt").ok().unwrap();
+        first.create_action_node(3, 0.0);
+        assert_eq!(first.node_ids().len(), second.node_ids().len() + 1);
+
+        let mut context = BehaviorContext::new();
+        context.set_target_position(1.0, 0.0, 0.0);
+        assert_eq!(second.evaluate(&mut context), 1);
+    }
+
+    #[test]
+    fn invalid_templates_are_rejected() {
+        let mut registry = TreeRegistry::new();
+        assert!(registry.register_template("broken", "{").is_err());
+        let dangling = grunt_json().replace("\"root_id\":2", "\"root_id\":40");
+        assert!(registry.register_template("dangling", &dangling).is_err());
+        assert!(!registry.contains("broken") && !registry.contains("dangling"));
+        assert!(registry.instantiate_template("missing").is_err());
+
+        registry.register_template("grunt", &grunt_json()).unwrap();
+        assert!(registry.remove("grunt"));
+        assert!(!registry.remove("grunt"));
+    }
+}
@@ -0,0 +1,69 @@
+use wasm_bindgen::prelude::*;
+
+// Spreads behavior tree evaluation across frames by handing out at most
+// `budget` entity indices per frame in round-robin order. With `total`
+// entities each one is evaluated every ceil(total / budget) frames.
+#[wasm_bindgen]
+pub struct AiScheduler {
+    budget: u32,
+    cursor: u32,
+}
+
+#[wasm_bindgen]
+impl AiScheduler {
+    #[wasm_bindgen(constructor)]
+    pub fn new(budget: u32) -> AiScheduler {
+        AiScheduler {
+            budget: budget.max(1),
+            cursor: 0,
+        }
+    }
+
+    // Maximum entities evaluated per frame (at least 1)
+    pub fn set_budget(&mut self, budget: u32) {
+        self.budget = budget.max(1);
+    }
+
+    // Indices to evaluate this frame, continuing where the previous frame
+    // stopped. `total` may change between frames as entities spawn and die.
+    pub fn next_batch(&mut self, total: u32) -> Vec<u32> {
+        if total == 0 {
+            self.cursor = 0;
+            return Vec::new();
+        }
+
+        let start = self.cursor % total;
+        let count = self.budget.min(total);
+        self.cursor = (start + count) % total;
+        (0..count).map(|offset| (start + offset) % total).collect()
+    }
+
+    pub fn reset(&mut self) {
+        self.cursor = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_robin_covers_every_entity_within_budget() {
+        let mut scheduler = AiScheduler::new(3);
+        let total = 10;
+        let mut seen = vec![0; total as usize];
+        // ceil(10 / 3) frames reach everyone
+        for _ in 0..4 {
+            let batch = scheduler.next_batch(total);
+            assert!(batch.len() <= 3);
+            for index in batch {
+                seen[index as usize] += 1;
+            }
+        }
+        assert!(seen.iter().all(|&count| count >= 1));
+
+        scheduler.set_budget(50);
+        assert_eq!(scheduler.next_batch(total).len(), 10);
+        assert!(scheduler.next_batch(0).is_empty());
+    }
+}
@@ -0,0 +1,56 @@
+use wasm_bindgen::prelude::*;
+use crate::math::Vector3;
+
+// AI-level push keeping agent `index` at least `desired_spacing` from its
+// neighbours in an xyz-interleaved buffer of `count` positions. Points away
+// from the average of the too-close neighbours with half the missing spacing,
+// so a crowded pair splits the correction evenly. Add it to the move vector.
+#[wasm_bindgen]
+pub fn spacing_adjustment(index: usize, positions: &[f32], count: usize, desired_spacing: f32) -> Vector3 {
+    let count = count.min(positions.len() / 3);
+    let zero = Vector3::new(0.0, 0.0, 0.0);
+    if index >= count {
+        return zero;
+    }
+
+    let at = |i: usize| Vector3::new(positions[i * 3], positions[i * 3 + 1], positions[i * 3 + 2]);
+    let agent = at(index);
+    let mut neighbour_sum = zero;
+    let mut neighbour_count = 0;
+    for i in (0..count).filter(|&i| i != index) {
+        let other = at(i);
+        if agent.distance(&other) < desired_spacing {
+            neighbour_sum = neighbour_sum.add(&other);
+            neighbour_count += 1;
+        }
+    }
+    if neighbour_count == 0 {
+        return zero;
+    }
+
+    let average = neighbour_sum.multiply(1.0 / neighbour_count as f32);
+    let away = agent.subtract(&average);
+    let distance = away.length();
+    if distance <= 0.0 {
+        // Stacked exactly on the crowd centre: no direction to push in
+        return zero;
+    }
+    away.multiply((desired_spacing - distance).max(0.0) * 0.5 / distance)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn isolated_agents_stay_and_crowded_pairs_split_evenly() {
+        let positions = [0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 50.0, 0.0, 0.0];
+        let isolated = spacing_adjustment(2, &positions, 3, 3.0);
+        assert_eq!((isolated.x, isolated.y, isolated.z), (0.0, 0.0, 0.0));
+
+        let first = spacing_adjustment(0, &positions, 3, 3.0);
+        let second = spacing_adjustment(1, &positions, 3, 3.0);
+        assert_eq!((first.x, first.y, first.z), (-1.0, 0.0, 0.0));
+        assert_eq!((second.x, second.y, second.z), (1.0, 0.0, 0.0));
+    }
+}
@@ -0,0 +1,51 @@
+use wasm_bindgen::prelude::*;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+struct SquadCounts {
+    initial_count: u32,
+    alive_count: u32,
+}
+
+// Shared squad headcount used for morale checks.
+// Clones share the same counts, so every member's context sees each death.
+#[wasm_bindgen]
+#[derive(Clone)]
+pub struct SquadState {
+    counts: Rc<RefCell<SquadCounts>>,
+}
+
+#[wasm_bindgen]
+impl SquadState {
+    #[wasm_bindgen(constructor)]
+    pub fn new(initial_count: u32) -> SquadState {
+        SquadState {
+            counts: Rc::new(RefCell::new(SquadCounts {
+                initial_count,
+                alive_count: initial_count,
+            })),
+        }
+    }
+
+    pub fn report_death(&mut self) {
+        let mut counts = self.counts.borrow_mut();
+        counts.alive_count = counts.alive_count.saturating_sub(1);
+    }
+
+    pub fn initial_count(&self) -> u32 {
+        self.counts.borrow().initial_count
+    }
+
+    pub fn alive_count(&self) -> u32 {
+        self.counts.borrow().alive_count
+    }
+
+    // Fraction of the squad still alive; an empty squad counts as wiped out
+    pub fn alive_fraction(&self) -> f64 {
+        let counts = self.counts.borrow();
+        if counts.initial_count == 0 {
+            return 0.0;
+        }
+        counts.alive_count as f64 / counts.initial_count as f64
+    }
+}
@@ -0,0 +1,81 @@
+use wasm_bindgen::prelude::*;
+use std::cell::RefCell;
+use std::rc::Rc;
+use crate::math::Vector3;
+
+struct Noise {
+    position: Vector3,
+    loudness: f64,
+    timestamp: f64,
+}
+
+struct StimulusState {
+    lifetime: f64,
+    noises: Vec<Noise>,
+}
+
+// Shared record of recent noises (gunfire, footsteps) that enemies can hear.
+// A noise's loudness fades linearly to zero over `lifetime` seconds.
+// Timestamps use the same clock as `BehaviorContext::tick`.
+#[wasm_bindgen]
+#[derive(Clone)]
+pub struct StimulusMap {
+    state: Rc<RefCell<StimulusState>>,
+}
+
+#[wasm_bindgen]
+impl StimulusMap {
+    #[wasm_bindgen(constructor)]
+    pub fn new(lifetime: f64) -> StimulusMap {
+        StimulusMap {
+            state: Rc::new(RefCell::new(StimulusState {
+                lifetime,
+                noises: Vec::new(),
+            })),
+        }
+    }
+
+    pub fn add_noise(&mut self, position: &Vector3, loudness: f64, timestamp: f64) {
+        self.state.borrow_mut().noises.push(Noise {
+            position: *position,
+            loudness,
+            timestamp,
+        });
+    }
+
+    // Forget noises that have fully decayed by `now`
+    pub fn prune(&mut self, now: f64) {
+        let mut state = self.state.borrow_mut();
+        let lifetime = state.lifetime;
+        state.noises.retain(|noise| now - noise.timestamp < lifetime);
+    }
+
+    pub fn noise_count(&self) -> usize {
+        self.state.borrow().noises.len()
+    }
+}
+
+impl StimulusMap {
+    // Loudest decayed noise within `hearing_range` of `listener` at time `now`,
+    // as (position, effective loudness)
+    pub(crate) fn loudest_audible(&self, listener: &Vector3, hearing_range: f64, now: f64) -> Option<(Vector3, f64)> {
+        let state = self.state.borrow();
+        let mut loudest: Option<(Vector3, f64)> = None;
+
+        for noise in &state.noises {
+            let age = now - noise.timestamp;
+            if age < 0.0 || age >= state.lifetime {
+                continue;
+            }
+            if noise.position.distance(listener) as f64 > hearing_range {
+                continue;
+            }
+            let loudness = noise.loudness * (1.0 - age / state.lifetime);
+            if loudest.is_none_or(|(_, best)| loudness > best) {
+                loudest = Some((noise.position, loudness));
+            }
+        }
+
+        loudest
+    }
+}
@@ -0,0 +1,98 @@
+use wasm_bindgen::prelude::*;
+
+// Picks a target from per-candidate scores with hysteresis so near-equal
+// candidates do not cause flip-flopping. A challenger must beat the current
+// target by `margin`, and no switch happens until the current target has
+// been held for `min_dwell_ticks` calls to `choose`.
+#[wasm_bindgen]
+pub struct TargetSelector {
+    margin: f64,
+    min_dwell_ticks: u32,
+    current: Option<usize>,
+    ticks_held: u32,
+}
+
+#[wasm_bindgen]
+impl TargetSelector {
+    #[wasm_bindgen(constructor)]
+    pub fn new(margin: f64, min_dwell_ticks: u32) -> TargetSelector {
+        TargetSelector {
+            margin: margin.max(0.0),
+            min_dwell_ticks,
+            current: None,
+            ticks_held: 0,
+        }
+    }
+
+    // Index of the target to engage this tick, or -1 when there are no
+    // candidates. Higher scores are better; NaN scores are never chosen.
+    pub fn choose(&mut self, scores: &[f64]) -> i64 {
+        let best = scores
+            .iter()
+            .enumerate()
+            .filter(|(_, score)| !score.is_nan())
+            .max_by(|a, b| a.1.total_cmp(b.1))
+            .map(|(index, _)| index);
+
+        let current = self.current.filter(|&index| scores.get(index).is_some_and(|score| !score.is_nan()));
+        let chosen = match (current, best) {
+            (Some(current), Some(best)) if best != current => {
+                let dwell_met = self.ticks_held >= self.min_dwell_ticks;
+                if dwell_met && scores[best] >= scores[current] + self.margin {
+                    Some(best)
+                } else {
+                    Some(current)
+                }
+            },
+            (Some(current), _) => Some(current),
+            (None, best) => best,
+        };
+
+        if chosen == self.current {
+            self.ticks_held = self.ticks_held.saturating_add(1);
+        } else {
+            self.current = chosen;
+            self.ticks_held = 1;
+        }
+        chosen.map_or(-1, |index| index as i64)
+    }
+
+    // Currently held target index, or -1
+    pub fn current(&self) -> i64 {
+        self.current.map_or(-1, |index| index as i64)
+    }
+
+    // Forget the current target, e.g. when it dies
+    pub fn reset(&mut self) {
+        self.current = None;
+        self.ticks_held = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn switches_only_for_a_clearly_better_target() {
+        let mut selector = TargetSelector::new(1.0, 0);
+        assert_eq!(selector.choose(&[5.0, 4.0]), 0);
+        assert_eq!(selector.choose(&[5.0, 5.5]), 0);
+        assert_eq!(selector.choose(&[5.0, 6.5]), 1);
+        // With no candidates left there is nothing to hold on to
+        assert_eq!(selector.choose(&[]), -1);
+    }
+
+    #[test]
+    fn dwell_time_blocks_immediate_reswitching() {
+        let mut selector = TargetSelector::new(0.5, 3);
+        assert_eq!(selector.choose(&[5.0, 1.0]), 0);
+        assert_eq!(selector.choose(&[5.0, 1.0]), 0);
+        assert_eq!(selector.choose(&[5.0, 1.0]), 0);
+        assert_eq!(selector.choose(&[1.0, 9.0]), 1);
+        // Target 0 is clearly better again, but 1 was only just picked
+        assert_eq!(selector.choose(&[9.0, 1.0]), 1);
+        assert_eq!(selector.choose(&[9.0, 1.0]), 1);
+        assert_eq!(selector.choose(&[9.0, 1.0]), 0);
+    }
+}
@@ -3,11 +3,28 @@ use wasm_bindgen::prelude::*;
 mod math;
 mod physics;
 mod ai;
+mod memory;
+mod nav;
+mod rng;
+mod spatial;
+mod steering;
+mod transform;
 
 // Re-export modules
-pub use math::Vector3;
-pub use physics::PhysicsSystem;
-pub use ai::{BehaviorTree, BehaviorContext, NodeType, NodeStatus, EnemyType, EnemyFactory};
+pub use math::{barycentric, centroid, interpolate_barycentric, lerp_angle, turn_towards_yaw, yaw_to_target, DVector3, Quaternion, Vector2, Vector3};
+pub use physics::{k_nearest_indices, projectile_landing, scatter_directions, Aabb, Capsule, CollisionState, ContactKind, ContactPhase, ContactResolver, PhysicsSystem, UpAxis};
+pub use nav::{visible_cells, FlowField, Grid};
+pub use rng::Rng;
+pub use spatial::SpatialHash;
+pub use steering::{blend, blend_flat, wander, wander_with_rng};
+pub use transform::TransformBuffer;
+pub use memory::{memory_stats, MemoryStats};
+pub use ai::{AttackCoordinator, BehaviorTree, BehaviorContext, BehaviorEvent, BehaviorEventKind, BehaviorOutput, ChangeRecord, CompareOp, NodeType, NodeStatus, TreeStats, EnemyType, EnemyFactory, SquadState, StimulusMap};
+pub use ai::{CurveShape, DifficultyCurve};
+pub use ai::{BehaviorForest, TreeRegistry, WorldContext};
+pub use ai::{AiScheduler, DangerMap, TargetSelector};
+pub use ai::{score_cover, best_cover_index};
+pub use ai::{formation_positions, spacing_adjustment};
 
 // When the `wee_alloc` feature is enabled, use `wee_alloc` as the global allocator.
 #[cfg(feature = "wee_alloc")]
@@ -9,66 +9,659 @@ pub struct Vector3 {
     pub z: f32,
 }
 
+// Double-precision counterpart of Vector3 for positions in large worlds,
+// where f32 loses sub-centimetre precision far from the origin
 #[wasm_bindgen]
-impl Vector3 {
+#[derive(Clone, Copy, Debug)]
+pub struct DVector3 {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+// Operations shared by Vector3 and DVector3, generated once per scalar type so
+// both keep the same API. `$opposite` is the cross product length under which
+// rotate_towards treats two directions as opposite.
+macro_rules! impl_vector3 {
+    ($name:ident, $scalar:ty, $opposite:expr) => {
+        #[wasm_bindgen]
+        impl $name {
+            #[wasm_bindgen(constructor)]
+            pub fn new(x: $scalar, y: $scalar, z: $scalar) -> $name {
+                $name { x, y, z }
+            }
+
+            pub fn distance(&self, other: &$name) -> $scalar {
+                let dx = self.x - other.x;
+                let dy = self.y - other.y;
+                let dz = self.z - other.z;
+                (dx * dx + dy * dy + dz * dz).sqrt()
+            }
+
+            pub fn normalize(&mut self) {
+                let length = (self.x * self.x + self.y * self.y + self.z * self.z).sqrt();
+                if length > 0.0 {
+                    self.x /= length;
+                    self.y /= length;
+                    self.z /= length;
+                }
+            }
+
+            pub fn dot(&self, other: &$name) -> $scalar {
+                self.x * other.x + self.y * other.y + self.z * other.z
+            }
+
+            pub fn cross(&self, other: &$name) -> $name {
+                $name {
+                    x: self.y * other.z - self.z * other.y,
+                    y: self.z * other.x - self.x * other.z,
+                    z: self.x * other.y - self.y * other.x,
+                }
+            }
+
+            pub fn length(&self) -> $scalar {
+                (self.x * self.x + self.y * self.y + self.z * self.z).sqrt()
+            }
+
+            pub fn add(&self, other: &$name) -> $name {
+                $name {
+                    x: self.x + other.x,
+                    y: self.y + other.y,
+                    z: self.z + other.z,
+                }
+            }
+
+            pub fn subtract(&self, other: &$name) -> $name {
+                $name {
+                    x: self.x - other.x,
+                    y: self.y - other.y,
+                    z: self.z - other.z,
+                }
+            }
+
+            pub fn multiply(&self, scalar: $scalar) -> $name {
+                $name {
+                    x: self.x * scalar,
+                    y: self.y * scalar,
+                    z: self.z * scalar,
+                }
+            }
+
+            pub fn midpoint(&self, other: &$name) -> $name {
+                $name {
+                    x: (self.x + other.x) * 0.5,
+                    y: (self.y + other.y) * 0.5,
+                    z: (self.z + other.z) * 0.5,
+                }
+            }
+
+            // Shortest distance from this point to the segment a-b
+            pub fn distance_to_segment(&self, a: &$name, b: &$name) -> $scalar {
+                let ab = b.subtract(a);
+                let length_squared = ab.dot(&ab);
+                if length_squared <= 0.0 {
+                    return self.distance(a);
+                }
+                let t = (self.subtract(a).dot(&ab) / length_squared).clamp(0.0, 1.0);
+                self.distance(&a.add(&ab.multiply(t)))
+            }
+
+            // False if any component is NaN or infinite
+            pub fn is_finite(&self) -> bool {
+                self.x.is_finite() && self.y.is_finite() && self.z.is_finite()
+            }
+
+            // This vector, or `fallback` when it is not finite, so a single bad input
+            // cannot poison state that is integrated frame after frame
+            pub fn sanitized(&self, fallback: &$name) -> $name {
+                if self.is_finite() { *self } else { *fallback }
+            }
+
+            // Rotate by `yaw` about world Y, then by `pitch` about the yawed X axis.
+            // Yaw turns +Z toward +X and positive pitch tilts +Z toward +Y, matching
+            // `yaw_to_target`. Equivalent to pitching in local space, then yawing.
+            pub fn rotate_yaw_pitch(&self, yaw: $scalar, pitch: $scalar) -> $name {
+                let (sin_pitch, cos_pitch) = pitch.sin_cos();
+                let y = self.y * cos_pitch + self.z * sin_pitch;
+                let z = -self.y * sin_pitch + self.z * cos_pitch;
+
+                let (sin_yaw, cos_yaw) = yaw.sin_cos();
+                $name {
+                    x: self.x * cos_yaw + z * sin_yaw,
+                    y,
+                    z: -self.x * sin_yaw + z * cos_yaw,
+                }
+            }
+
+            // Turn this direction toward `target` along the shortest arc by at most
+            // `max_radians`, reaching `target` when it is within that angle. Both are
+            // normalized first, so the result is a unit vector. Opposite directions
+            // turn about an arbitrary perpendicular axis.
+            pub fn rotate_towards(&self, target: &$name, max_radians: $scalar) -> $name {
+                let mut from = *self;
+                from.normalize();
+                let mut to = *target;
+                to.normalize();
+
+                let angle = from.dot(&to).clamp(-1.0, 1.0).acos();
+                let max_radians = max_radians.max(0.0);
+                if angle <= max_radians {
+                    return to;
+                }
+
+                let mut axis = from.cross(&to);
+                if axis.length() < $opposite {
+                    // from and to are opposite; any perpendicular axis is a shortest arc
+                    axis = from.build_basis()[2];
+                }
+                axis.normalize();
+
+                // Rodrigues' rotation; the axis is perpendicular to `from`
+                let (sin, cos) = max_radians.sin_cos();
+                from.multiply(cos).add(&axis.cross(&from).multiply(sin))
+            }
+
+            // Pack the vector into little-endian bytes: x, y, z at the width of
+            // the scalar type, 12 bytes in all for Vector3 and 24 for DVector3
+            pub fn to_bytes(&self) -> Vec<u8> {
+                let mut bytes = Vec::with_capacity(3 * std::mem::size_of::<$scalar>());
+                bytes.extend_from_slice(&self.x.to_le_bytes());
+                bytes.extend_from_slice(&self.y.to_le_bytes());
+                bytes.extend_from_slice(&self.z.to_le_bytes());
+                bytes
+            }
+
+            // Read a vector packed by `to_bytes`; shorter input yields the zero vector
+            pub fn from_bytes(bytes: &[u8]) -> $name {
+                const WIDTH: usize = std::mem::size_of::<$scalar>();
+                if bytes.len() < 3 * WIDTH {
+                    return $name { x: 0.0, y: 0.0, z: 0.0 };
+                }
+                let read = |index: usize| {
+                    let mut word = [0u8; WIDTH];
+                    word.copy_from_slice(&bytes[index * WIDTH..(index + 1) * WIDTH]);
+                    <$scalar>::from_le_bytes(word)
+                };
+                $name {
+                    x: read(0),
+                    y: read(1),
+                    z: read(2),
+                }
+            }
+
+            // Build an orthonormal frame [forward, right, up] from this direction.
+            // World up (+Y) is the reference axis unless forward is nearly parallel
+            // to it, in which case +X is used instead.
+            pub fn build_basis(&self) -> Vec<$name> {
+                let mut forward = self.sanitized(&$name::new(0.0, 0.0, 1.0));
+                forward.normalize();
+                if forward.length() == 0.0 {
+                    forward = $name::new(0.0, 0.0, 1.0);
+                }
+
+                let reference = if forward.y.abs() > 0.999 {
+                    $name::new(1.0, 0.0, 0.0)
+                } else {
+                    $name::new(0.0, 1.0, 0.0)
+                };
+
+                let mut right = reference.cross(&forward);
+                right.normalize();
+                let up = forward.cross(&right);
+
+                vec![forward, right, up]
+            }
+        }
+    };
+}
+
+impl_vector3!(Vector3, f32, 1e-6);
+impl_vector3!(DVector3, f64, 1e-12);
+
+#[wasm_bindgen]
+impl DVector3 {
+    pub fn from_vector3(vector: &Vector3) -> DVector3 {
+        DVector3::new(vector.x as f64, vector.y as f64, vector.z as f64)
+    }
+
+    // Narrow to f32, e.g. after re-basing around a nearby origin for rendering
+    pub fn to_vector3(&self) -> Vector3 {
+        Vector3::new(self.x as f32, self.y as f32, self.z as f32)
+    }
+}
+
+// Average of `count` points from an xyz-interleaved buffer; the origin for empty input
+#[wasm_bindgen]
+pub fn centroid(points: &[f32], count: usize) -> Vector3 {
+    let count = count.min(points.len() / 3);
+    if count == 0 {
+        return Vector3::new(0.0, 0.0, 0.0);
+    }
+
+    let mut sum = Vector3::new(0.0, 0.0, 0.0);
+    for point in points.chunks_exact(3).take(count) {
+        sum.x += point[0];
+        sum.y += point[1];
+        sum.z += point[2];
+    }
+    sum.multiply(1.0 / count as f32)
+}
+
+// Wrap an angle into [-PI, PI]
+fn wrap_angle(angle: f32) -> f32 {
+    let two_pi = std::f32::consts::TAU;
+    let wrapped = (angle + std::f32::consts::PI).rem_euclid(two_pi) - std::f32::consts::PI;
+    // rem_euclid can land exactly on TAU through rounding
+    if wrapped > std::f32::consts::PI { wrapped - two_pi } else { wrapped }
+}
+
+// Yaw (rotation about Y, radians) that faces `to` from `from` using only the
+// XZ components. Yaw 0 faces +Z and positive yaw turns toward +X.
+#[wasm_bindgen]
+pub fn yaw_to_target(from: &Vector3, to: &Vector3) -> f32 {
+    (to.x - from.x).atan2(to.z - from.z)
+}
+
+// Turn `current` toward `target` by at most `max_delta` radians along the
+// shorter direction, without overshooting. The result is wrapped into [-PI, PI].
+#[wasm_bindgen]
+pub fn turn_towards_yaw(current: f32, target: f32, max_delta: f32) -> f32 {
+    let difference = wrap_angle(target - current);
+    let max_delta = max_delta.abs();
+    if difference.abs() <= max_delta {
+        wrap_angle(target)
+    } else {
+        wrap_angle(current + max_delta.copysign(difference))
+    }
+}
+
+// Interpolate between two angles (radians) along the shorter way around, so
+// 350 degrees to 10 degrees passes through 0 rather than 180. The result is
+// wrapped into [-PI, PI]; `t` is not clamped.
+#[wasm_bindgen]
+pub fn lerp_angle(from: f32, to: f32, t: f32) -> f32 {
+    wrap_angle(from + wrap_angle(to - from) * t)
+}
+
+// Barycentric weights [u, v, w] of `p` (projected onto the triangle plane)
+// relative to a, b, c. Degenerate triangles return full weight on `a`.
+#[wasm_bindgen]
+pub fn barycentric(p: &Vector3, a: &Vector3, b: &Vector3, c: &Vector3) -> Vec<f32> {
+    let v0 = b.subtract(a);
+    let v1 = c.subtract(a);
+    let v2 = p.subtract(a);
+    let d00 = v0.dot(&v0);
+    let d01 = v0.dot(&v1);
+    let d11 = v1.dot(&v1);
+    let d20 = v2.dot(&v0);
+    let d21 = v2.dot(&v1);
+
+    let denominator = d00 * d11 - d01 * d01;
+    if denominator.abs() < 1e-12 {
+        return vec![1.0, 0.0, 0.0];
+    }
+
+    let v = (d11 * d20 - d01 * d21) / denominator;
+    let w = (d00 * d21 - d01 * d20) / denominator;
+    vec![1.0 - v - w, v, w]
+}
+
+// Blend three per-vertex values with barycentric weights (missing weights count as 0)
+#[wasm_bindgen]
+pub fn interpolate_barycentric(weights: &[f32], va: &Vector3, vb: &Vector3, vc: &Vector3) -> Vector3 {
+    let weight = |i: usize| weights.get(i).copied().unwrap_or(0.0);
+    va.multiply(weight(0))
+        .add(&vb.multiply(weight(1)))
+        .add(&vc.multiply(weight(2)))
+}
+
+// Vector2 for grid-space directions
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug)]
+pub struct Vector2 {
+    pub x: f32,
+    pub y: f32,
+}
+
+#[wasm_bindgen]
+impl Vector2 {
     #[wasm_bindgen(constructor)]
-    pub fn new(x: f32, y: f32, z: f32) -> Vector3 {
-        Vector3 { x, y, z }
+    pub fn new(x: f32, y: f32) -> Vector2 {
+        Vector2 { x, y }
     }
 
-    pub fn distance(&self, other: &Vector3) -> f32 {
-        let dx = self.x - other.x;
-        let dy = self.y - other.y;
-        let dz = self.z - other.z;
-        (dx * dx + dy * dy + dz * dz).sqrt()
+    pub fn length(&self) -> f32 {
+        (self.x * self.x + self.y * self.y).sqrt()
     }
 
     pub fn normalize(&mut self) {
-        let length = (self.x * self.x + self.y * self.y + self.z * self.z).sqrt();
+        let length = self.length();
         if length > 0.0 {
             self.x /= length;
             self.y /= length;
-            self.z /= length;
         }
     }
-    
-    pub fn dot(&self, other: &Vector3) -> f32 {
-        self.x * other.x + self.y * other.y + self.z * other.z
+}
+
+// Quaternion for orientations (x, y, z vector part, w scalar part)
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug)]
+pub struct Quaternion {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub w: f32,
+}
+
+#[wasm_bindgen]
+impl Quaternion {
+    #[wasm_bindgen(constructor)]
+    pub fn new(x: f32, y: f32, z: f32, w: f32) -> Quaternion {
+        Quaternion { x, y, z, w }
     }
-    
-    pub fn cross(&self, other: &Vector3) -> Vector3 {
-        Vector3 {
-            x: self.y * other.z - self.z * other.y,
-            y: self.z * other.x - self.x * other.z,
-            z: self.x * other.y - self.y * other.x,
+
+    pub fn identity() -> Quaternion {
+        Quaternion { x: 0.0, y: 0.0, z: 0.0, w: 1.0 }
+    }
+
+    // Rotation of `angle` radians about `axis` (normalized internally)
+    pub fn from_axis_angle(axis: &Vector3, angle: f32) -> Quaternion {
+        let mut axis = *axis;
+        axis.normalize();
+        let half = angle * 0.5;
+        let s = half.sin();
+        Quaternion {
+            x: axis.x * s,
+            y: axis.y * s,
+            z: axis.z * s,
+            w: half.cos(),
         }
     }
-    
+
+    pub fn dot(&self, other: &Quaternion) -> f32 {
+        self.x * other.x + self.y * other.y + self.z * other.z + self.w * other.w
+    }
+
     pub fn length(&self) -> f32 {
-        (self.x * self.x + self.y * self.y + self.z * self.z).sqrt()
-    }
-    
-    pub fn add(&self, other: &Vector3) -> Vector3 {
-        Vector3 {
-            x: self.x + other.x,
-            y: self.y + other.y,
-            z: self.z + other.z,
+        self.dot(self).sqrt()
+    }
+
+    pub fn normalize(&mut self) {
+        let length = self.length();
+        if length > 0.0 {
+            self.x /= length;
+            self.y /= length;
+            self.z /= length;
+            self.w /= length;
+        }
+    }
+
+    pub fn rotate_vector(&self, v: &Vector3) -> Vector3 {
+        // v' = v + 2w(q x v) + 2(q x (q x v))
+        let q = Vector3::new(self.x, self.y, self.z);
+        let t = q.cross(v).multiply(2.0);
+        v.add(&t.multiply(self.w)).add(&q.cross(&t))
+    }
+
+    // Spherical interpolation along the shortest arc; falls back to a
+    // normalized lerp when the orientations are nearly identical
+    pub fn slerp(&self, other: &Quaternion, t: f32) -> Quaternion {
+        let mut end = *other;
+        let mut cos_theta = self.dot(other);
+
+        // q and -q are the same orientation; flip to take the short path
+        if cos_theta < 0.0 {
+            end = Quaternion::new(-end.x, -end.y, -end.z, -end.w);
+            cos_theta = -cos_theta;
+        }
+
+        let (scale_start, scale_end) = if cos_theta > 0.9995 {
+            (1.0 - t, t)
+        } else {
+            let theta = cos_theta.acos();
+            let sin_theta = theta.sin();
+            (
+                ((1.0 - t) * theta).sin() / sin_theta,
+                (t * theta).sin() / sin_theta,
+            )
+        };
+
+        let mut result = Quaternion {
+            x: self.x * scale_start + end.x * scale_end,
+            y: self.y * scale_start + end.y * scale_end,
+            z: self.z * scale_start + end.z * scale_end,
+            w: self.w * scale_start + end.w * scale_end,
+        };
+        result.normalize();
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn dvector3_matches_vector3_operations() {
+        let single = Vector3::new(0.3, -0.2, 0.9);
+        let double = DVector3::from_vector3(&single);
+
+        let rotated = single.rotate_yaw_pitch(0.7, 0.2);
+        let rotated_double = double.rotate_yaw_pitch(0.7, 0.2);
+        assert!((rotated_double.x - rotated.x as f64).abs() < 1e-6);
+        assert!((rotated_double.z - rotated.z as f64).abs() < 1e-6);
+
+        let target = DVector3::new(1.0, 0.0, 0.0);
+        let turned = double.rotate_towards(&target, 0.1);
+        assert!(close(turned.length(), 1.0));
+        let mut start = double;
+        start.normalize();
+        assert!(close(start.dot(&turned).acos(), 0.1));
+
+        let basis = target.build_basis();
+        assert!(close(basis[0].dot(&basis[1]), 0.0));
+        assert!(close(basis[1].dot(&basis[2]), 0.0));
+        assert!(close(basis[2].length(), 1.0));
+    }
+
+    #[test]
+    fn dvector3_keeps_full_precision_through_bytes() {
+        let far = DVector3::new(1.0e7 + 0.001, -2.5, 1.0e-12);
+        let restored = DVector3::from_bytes(&far.to_bytes());
+        assert_eq!((restored.x, restored.y, restored.z), (far.x, far.y, far.z));
+        assert_eq!(DVector3::from_bytes(&[0; 8]).length(), 0.0);
+
+        let fallback = DVector3::new(0.0, 1.0, 0.0);
+        assert_eq!(DVector3::new(f64::NAN, 0.0, 0.0).sanitized(&fallback).y, 1.0);
+        assert!(!DVector3::new(0.0, f64::INFINITY, 0.0).is_finite());
+    }
+
+    #[test]
+    fn vector3_bytes_round_trip_little_endian() {
+        let vector = Vector3::new(1.5, -2.25, 1.0e-3);
+        let bytes = vector.to_bytes();
+        assert_eq!(bytes.len(), 12);
+        assert_eq!(&bytes[0..4], &1.5f32.to_le_bytes());
+        let restored = Vector3::from_bytes(&bytes);
+        assert_eq!((restored.x, restored.y, restored.z), (vector.x, vector.y, vector.z));
+
+        let short = Vector3::from_bytes(&bytes[..11]);
+        assert_eq!((short.x, short.y, short.z), (0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn build_basis_is_orthonormal_even_for_vertical_forward() {
+        for forward in [Vector3::new(0.0, 0.0, 2.0), Vector3::new(1.0, 2.0, 3.0), Vector3::new(0.0, -5.0, 0.0)] {
+            let basis = forward.build_basis();
+            for axis in &basis {
+                assert!((axis.length() - 1.0).abs() < 1e-5);
+            }
+            assert!(basis[0].dot(&basis[1]).abs() < 1e-5);
+            assert!(basis[0].dot(&basis[2]).abs() < 1e-5);
+            assert!(basis[1].dot(&basis[2]).abs() < 1e-5);
+            let mut expected = forward;
+            expected.normalize();
+            assert!(basis[0].distance(&expected) < 1e-5);
         }
+
+        // Looking down +Z: right is +X and up is +Y
+        let basis = Vector3::new(0.0, 0.0, 1.0).build_basis();
+        assert!(basis[1].distance(&Vector3::new(1.0, 0.0, 0.0)) < 1e-6);
+        assert!(basis[2].distance(&Vector3::new(0.0, 1.0, 0.0)) < 1e-6);
     }
-    
-    pub fn subtract(&self, other: &Vector3) -> Vector3 {
-        Vector3 {
-            x: self.x - other.x,
-            y: self.y - other.y,
-            z: self.z - other.z,
+
+    #[test]
+    fn quaternion_slerp_halfway_between_identity_and_quarter_yaw() {
+        let up = Vector3::new(0.0, 1.0, 0.0);
+        let quarter = Quaternion::from_axis_angle(&up, std::f32::consts::FRAC_PI_2);
+        let half = Quaternion::identity().slerp(&quarter, 0.5);
+        let expected = Quaternion::from_axis_angle(&up, std::f32::consts::FRAC_PI_4);
+        assert!((half.dot(&expected).abs() - 1.0).abs() < 1e-5);
+
+        let rotated = half.rotate_vector(&Vector3::new(1.0, 0.0, 0.0));
+        let diagonal = std::f32::consts::FRAC_1_SQRT_2;
+        assert!((rotated.x - diagonal).abs() < 1e-5);
+        assert!((rotated.z + diagonal).abs() < 1e-5);
+    }
+
+    #[test]
+    fn quaternion_slerp_takes_the_shortest_path() {
+        let up = Vector3::new(0.0, 1.0, 0.0);
+        let quarter = Quaternion::from_axis_angle(&up, std::f32::consts::FRAC_PI_2);
+        let negated = Quaternion::new(-quarter.x, -quarter.y, -quarter.z, -quarter.w);
+        // Same rotation with the opposite sign; the long way round would pass 180 degrees
+        let direct = Quaternion::identity().slerp(&quarter, 0.5);
+        let flipped = Quaternion::identity().slerp(&negated, 0.5);
+        assert!((direct.dot(&flipped).abs() - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn midpoint_and_centroid_average_points() {
+        let mid = Vector3::new(0.0, 2.0, -4.0).midpoint(&Vector3::new(2.0, 4.0, 4.0));
+        assert_eq!((mid.x, mid.y, mid.z), (1.0, 3.0, 0.0));
+
+        let square = [0.0, 0.0, 0.0, 4.0, 0.0, 0.0, 4.0, 0.0, 4.0, 0.0, 0.0, 4.0];
+        let center = centroid(&square, 4);
+        assert_eq!((center.x, center.y, center.z), (2.0, 0.0, 2.0));
+
+        let empty = centroid(&[], 0);
+        assert_eq!((empty.x, empty.y, empty.z), (0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn yaw_to_target_faces_each_cardinal_direction() {
+        use std::f32::consts::{FRAC_PI_2, PI};
+        let origin = Vector3::new(1.0, 5.0, 1.0);
+        let cases = [
+            (Vector3::new(1.0, 0.0, 4.0), 0.0),
+            (Vector3::new(4.0, 0.0, 1.0), FRAC_PI_2),
+            (Vector3::new(1.0, 9.0, -4.0), PI),
+            (Vector3::new(-4.0, 0.0, 1.0), -FRAC_PI_2),
+        ];
+        for (target, yaw) in cases {
+            assert!((yaw_to_target(&origin, &target) - yaw).abs() < 1e-5);
         }
     }
-    
-    pub fn multiply(&self, scalar: f32) -> Vector3 {
-        Vector3 {
-            x: self.x * scalar,
-            y: self.y * scalar,
-            z: self.z * scalar,
+
+    #[test]
+    fn turn_towards_yaw_wraps_across_pi_without_overshoot() {
+        use std::f32::consts::PI;
+        // 0.9 PI to -0.9 PI is a 0.2 PI turn through PI, not 1.8 PI back through 0
+        let step = turn_towards_yaw(0.9 * PI, -0.9 * PI, 0.05 * PI);
+        assert!((step - 0.95 * PI).abs() < 1e-5);
+        let step = turn_towards_yaw(0.95 * PI, -0.9 * PI, 0.1 * PI);
+        assert!((step + 0.95 * PI).abs() < 1e-5);
+        assert!((turn_towards_yaw(0.0, 0.1, 1.0) - 0.1).abs() < 1e-5);
+    }
+
+    #[test]
+    fn barycentric_weights_at_centroid_vertices_and_degenerate() {
+        let a = Vector3::new(0.0, 0.0, 0.0);
+        let b = Vector3::new(3.0, 0.0, 0.0);
+        let c = Vector3::new(0.0, 0.0, 3.0);
+        let weights = barycentric(&Vector3::new(1.0, 0.0, 1.0), &a, &b, &c);
+        for weight in &weights {
+            assert!((weight - 1.0 / 3.0).abs() < 1e-5);
         }
+        assert_eq!(barycentric(&a, &a, &b, &c), vec![1.0, 0.0, 0.0]);
+        assert_eq!(barycentric(&b, &a, &b, &c), vec![0.0, 1.0, 0.0]);
+        assert_eq!(barycentric(&c, &a, &b, &c), vec![0.0, 0.0, 1.0]);
+
+        // Collinear corners collapse onto the first vertex
+        let degenerate = barycentric(&b, &a, &b, &Vector3::new(6.0, 0.0, 0.0));
+        assert_eq!(degenerate.iter().sum::<f32>(), 1.0);
+
+        let heights = interpolate_barycentric(&weights, &Vector3::new(0.0, 3.0, 0.0), &Vector3::new(0.0, 6.0, 0.0), &Vector3::new(0.0, 9.0, 0.0));
+        assert!((heights.y - 6.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn rotate_yaw_pitch_applies_yaw_then_pitch() {
+        use std::f32::consts::{FRAC_PI_2, FRAC_PI_4};
+        let forward = Vector3::new(0.0, 0.0, 1.0);
+        let close = |v: Vector3, x: f32, y: f32, z: f32| {
+            (v.x - x).abs() < 1e-5 && (v.y - y).abs() < 1e-5 && (v.z - z).abs() < 1e-5
+        };
+
+        assert!(close(forward.rotate_yaw_pitch(FRAC_PI_2, 0.0), 1.0, 0.0, 0.0));
+        let tilt = std::f32::consts::FRAC_1_SQRT_2;
+        assert!(close(forward.rotate_yaw_pitch(0.0, FRAC_PI_4), 0.0, tilt, tilt));
+        // Pitch follows the yawed frame, so the tilt stays in the facing direction
+        assert!(close(forward.rotate_yaw_pitch(FRAC_PI_2, FRAC_PI_4), tilt, tilt, 0.0));
+    }
+
+    #[test]
+    fn distance_to_segment_clamps_to_the_endpoints() {
+        let a = Vector3::new(0.0, 0.0, 0.0);
+        let b = Vector3::new(10.0, 0.0, 0.0);
+        assert_eq!(Vector3::new(4.0, 3.0, 0.0).distance_to_segment(&a, &b), 3.0);
+        assert_eq!(Vector3::new(13.0, 4.0, 0.0).distance_to_segment(&a, &b), 5.0);
+        assert_eq!(Vector3::new(-3.0, 0.0, 4.0).distance_to_segment(&a, &b), 5.0);
+        assert_eq!(Vector3::new(3.0, 4.0, 0.0).distance_to_segment(&a, &a), 5.0);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn non_finite_vectors_fall_back_when_sanitized() {
+        let fallback = Vector3::new(0.0, 1.0, 0.0);
+        let nan = Vector3::new(1.0, f32::NAN, 0.0);
+        let infinite = Vector3::new(0.0, 0.0, f32::NEG_INFINITY);
+        let finite = Vector3::new(1.0, 2.0, 3.0);
+        assert!(!nan.is_finite());
+        assert!(!infinite.is_finite());
+        assert!(finite.is_finite());
+        assert_eq!(nan.sanitized(&fallback).y, 1.0);
+        assert_eq!(infinite.sanitized(&fallback).z, 0.0);
+        assert_eq!(finite.sanitized(&fallback).z, 3.0);
+    }
+
+    #[test]
+    fn rotate_towards_steps_partially_or_snaps() {
+        let step = Vector3::new(2.0, 0.0, 0.0).rotate_towards(&Vector3::new(0.0, 0.0, 5.0), 0.1);
+        assert!((step.length() - 1.0).abs() < 1e-5);
+        assert!((step.x - 0.1_f32.cos()).abs() < 1e-5);
+        assert!((step.z - 0.1_f32.sin()).abs() < 1e-5);
+
+        let snapped = Vector3::new(1.0, 0.0, 0.0).rotate_towards(&Vector3::new(0.0, 0.0, 5.0), 2.0);
+        assert!(snapped.x.abs() < 1e-6);
+        assert!((snapped.z - 1.0).abs() < 1e-6);
+
+        // Opposite directions still make progress
+        let opposite = Vector3::new(1.0, 0.0, 0.0).rotate_towards(&Vector3::new(-1.0, 0.0, 0.0), 0.1);
+        assert!((opposite.x - 0.1_f32.cos()).abs() < 1e-5);
+    }
+
+    #[test]
+    fn lerp_angle_takes_the_short_way_across_the_wrap() {
+        let (from, to) = (350.0_f32.to_radians(), 10.0_f32.to_radians());
+        let mid = lerp_angle(from, to, 0.5);
+        assert!(mid.abs() < 1e-5);
+        assert!((lerp_angle(from, to, 0.25) - (-5.0_f32).to_radians()).abs() < 1e-5);
+        assert!((lerp_angle(from, to, 0.0) - (-10.0_f32).to_radians()).abs() < 1e-5);
+        assert!((lerp_angle(from, to, 1.0) - to).abs() < 1e-5);
+        assert!((lerp_angle(0.0, 1.0, 0.5) - 0.5).abs() < 1e-6);
+    }
+}
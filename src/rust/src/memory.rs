@@ -0,0 +1,29 @@
+use wasm_bindgen::prelude::*;
+
+// Size of a WebAssembly linear memory page
+const WASM_PAGE_SIZE: usize = 65536;
+
+// Snapshot of the module's linear memory footprint
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug)]
+pub struct MemoryStats {
+    pub page_count: usize,
+    pub linear_memory_bytes: usize,
+}
+
+// Report current linear memory size in bytes. Linear memory only ever grows,
+// so this is the high-water mark: compacting individual structures such as
+// `SpatialHash::compact` frees space for reuse inside it but never lowers it.
+// Always zero on non-wasm targets.
+#[wasm_bindgen]
+pub fn memory_stats() -> MemoryStats {
+    #[cfg(target_arch = "wasm32")]
+    let page_count = core::arch::wasm32::memory_size(0);
+    #[cfg(not(target_arch = "wasm32"))]
+    let page_count = 0;
+
+    MemoryStats {
+        page_count,
+        linear_memory_bytes: page_count * WASM_PAGE_SIZE,
+    }
+}
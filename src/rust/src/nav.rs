@@ -0,0 +1,298 @@
+use wasm_bindgen::prelude::*;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use crate::math::Vector2;
+
+// Path costs in tenths of a cell so diagonal steps stay integral
+const STRAIGHT_COST: u32 = 10;
+const DIAGONAL_COST: u32 = 14;
+const UNREACHABLE: u32 = u32::MAX;
+
+// 8-connected neighbour offsets
+const NEIGHBOURS: [(i32, i32); 8] = [
+    (1, 0), (-1, 0), (0, 1), (0, -1),
+    (1, 1), (1, -1), (-1, 1), (-1, -1),
+];
+
+// Walkability grid for navigation, indexed by (x, y) cell coordinates
+#[wasm_bindgen]
+#[derive(Clone)]
+pub struct Grid {
+    width: u32,
+    height: u32,
+    walkable: Vec<bool>,
+}
+
+#[wasm_bindgen]
+impl Grid {
+    // Create a grid with every cell walkable
+    #[wasm_bindgen(constructor)]
+    pub fn new(width: u32, height: u32) -> Grid {
+        Grid {
+            width,
+            height,
+            walkable: vec![true; (width * height) as usize],
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn set_walkable(&mut self, x: u32, y: u32, walkable: bool) {
+        if let Some(index) = self.index(x as i32, y as i32) {
+            self.walkable[index] = walkable;
+        }
+    }
+
+    // Cells outside the grid are never walkable
+    pub fn is_walkable(&self, x: u32, y: u32) -> bool {
+        self.index(x as i32, y as i32)
+            .map(|index| self.walkable[index])
+            .unwrap_or(false)
+    }
+}
+
+impl Grid {
+    fn index(&self, x: i32, y: i32) -> Option<usize> {
+        if x < 0 || y < 0 || x >= self.width as i32 || y >= self.height as i32 {
+            None
+        } else {
+            Some((y as u32 * self.width + x as u32) as usize)
+        }
+    }
+
+    fn walkable_at(&self, x: i32, y: i32) -> bool {
+        self.index(x, y).map(|index| self.walkable[index]).unwrap_or(false)
+    }
+
+    // Diagonal moves may not cut the corner of a blocked cell
+    fn can_step(&self, x: i32, y: i32, dx: i32, dy: i32) -> bool {
+        if !self.walkable_at(x + dx, y + dy) {
+            return false;
+        }
+        dx == 0 || dy == 0 || (self.walkable_at(x + dx, y) && self.walkable_at(x, y + dy))
+    }
+}
+
+// Shared distance field toward a single goal; agents sample a direction per
+// cell instead of each running their own search
+#[wasm_bindgen]
+pub struct FlowField {
+    width: u32,
+    height: u32,
+    distances: Vec<u32>,
+    directions: Vec<Vector2>,
+}
+
+#[wasm_bindgen]
+impl FlowField {
+    // Run Dijkstra outward from the goal and point every reachable cell at
+    // its cheapest neighbour
+    pub fn build(grid: &Grid, goal_x: u32, goal_y: u32) -> FlowField {
+        let cell_count = (grid.width * grid.height) as usize;
+        let mut distances = vec![UNREACHABLE; cell_count];
+        let mut directions = vec![Vector2::new(0.0, 0.0); cell_count];
+
+        let goal_index = match grid.index(goal_x as i32, goal_y as i32) {
+            Some(index) if grid.walkable[index] => index,
+            _ => {
+                return FlowField {
+                    width: grid.width,
+                    height: grid.height,
+                    distances,
+                    directions,
+                }
+            },
+        };
+
+        distances[goal_index] = 0;
+        let mut open = BinaryHeap::new();
+        open.push(Reverse((0, goal_x as i32, goal_y as i32)));
+
+        while let Some(Reverse((cost, x, y))) = open.pop() {
+            if cost > distances[grid.index(x, y).unwrap()] {
+                continue;
+            }
+            for &(dx, dy) in NEIGHBOURS.iter() {
+                if !grid.can_step(x, y, dx, dy) {
+                    continue;
+                }
+                let step = if dx != 0 && dy != 0 { DIAGONAL_COST } else { STRAIGHT_COST };
+                let next_index = grid.index(x + dx, y + dy).unwrap();
+                let next_cost = cost + step;
+                if next_cost < distances[next_index] {
+                    distances[next_index] = next_cost;
+                    open.push(Reverse((next_cost, x + dx, y + dy)));
+                }
+            }
+        }
+
+        for y in 0..grid.height as i32 {
+            for x in 0..grid.width as i32 {
+                let index = grid.index(x, y).unwrap();
+                if index == goal_index || distances[index] == UNREACHABLE {
+                    continue;
+                }
+                let mut best = (distances[index], 0, 0);
+                for &(dx, dy) in NEIGHBOURS.iter() {
+                    if !grid.can_step(x, y, dx, dy) {
+                        continue;
+                    }
+                    let neighbour_cost = distances[grid.index(x + dx, y + dy).unwrap()];
+                    if neighbour_cost < best.0 {
+                        best = (neighbour_cost, dx, dy);
+                    }
+                }
+                let mut direction = Vector2::new(best.1 as f32, best.2 as f32);
+                direction.normalize();
+                directions[index] = direction;
+            }
+        }
+
+        FlowField {
+            width: grid.width,
+            height: grid.height,
+            distances,
+            directions,
+        }
+    }
+
+    // Unit direction toward the goal; zero at the goal and for blocked,
+    // unreachable or out-of-range cells
+    pub fn direction_at(&self, x: u32, y: u32) -> Vector2 {
+        match self.index(x, y) {
+            Some(index) => self.directions[index],
+            None => Vector2::new(0.0, 0.0),
+        }
+    }
+
+    // Path distance to the goal in cells, or -1 when unreachable
+    pub fn distance_at(&self, x: u32, y: u32) -> f32 {
+        match self.index(x, y) {
+            Some(index) if self.distances[index] != UNREACHABLE => {
+                self.distances[index] as f32 / STRAIGHT_COST as f32
+            },
+            _ => -1.0,
+        }
+    }
+}
+
+impl FlowField {
+    fn index(&self, x: u32, y: u32) -> Option<usize> {
+        if x >= self.width || y >= self.height {
+            None
+        } else {
+            Some((y * self.width + x) as usize)
+        }
+    }
+}
+
+// Indices (y * width + x) of the cells within `max_range` cells (Euclidean)
+// of `from` that have line of sight to it. Each cell is tested with a
+// Bresenham line that must not pass through an unwalkable cell before
+// reaching it, so walls themselves are visible but hide what is behind them.
+// Empty when `from` lies outside the grid.
+#[wasm_bindgen]
+pub fn visible_cells(grid: &Grid, from_x: u32, from_y: u32, max_range: u32) -> Vec<u32> {
+    let (from_x, from_y) = (from_x as i32, from_y as i32);
+    if grid.index(from_x, from_y).is_none() {
+        return Vec::new();
+    }
+
+    let range = max_range as i32;
+    let mut visible = Vec::new();
+    for y in (from_y - range).max(0)..=(from_y + range).min(grid.height as i32 - 1) {
+        for x in (from_x - range).max(0)..=(from_x + range).min(grid.width as i32 - 1) {
+            let (dx, dy) = (x - from_x, y - from_y);
+            if dx * dx + dy * dy > range * range {
+                continue;
+            }
+            if line_of_sight(grid, from_x, from_y, x, y) {
+                visible.push(grid.index(x, y).unwrap() as u32);
+            }
+        }
+    }
+    visible
+}
+
+// Walk the Bresenham line from (x0, y0) to (x1, y1), failing on any blocked
+// cell strictly between the endpoints
+fn line_of_sight(grid: &Grid, x0: i32, y0: i32, x1: i32, y1: i32) -> bool {
+    let (dx, dy) = ((x1 - x0).abs(), -(y1 - y0).abs());
+    let (step_x, step_y) = (if x0 < x1 { 1 } else { -1 }, if y0 < y1 { 1 } else { -1 });
+    let (mut x, mut y, mut error) = (x0, y0, dx + dy);
+
+    loop {
+        if x == x1 && y == y1 {
+            return true;
+        }
+        if (x, y) != (x0, y0) && !grid.walkable_at(x, y) {
+            return false;
+        }
+        let doubled = 2 * error;
+        if doubled >= dy {
+            error += dy;
+            x += step_x;
+        }
+        if doubled <= dx {
+            error += dx;
+            y += step_y;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flow_field_points_neighbours_at_the_goal() {
+        let grid = Grid::new(5, 5);
+        let field = FlowField::build(&grid, 2, 2);
+        let direction = field.direction_at(1, 2);
+        assert_eq!((direction.x, direction.y), (1.0, 0.0));
+        let direction = field.direction_at(2, 3);
+        assert_eq!((direction.x, direction.y), (0.0, -1.0));
+        assert_eq!(field.distance_at(1, 2), 1.0);
+    }
+
+    #[test]
+    fn flow_field_leaves_walled_off_cells_still() {
+        let mut grid = Grid::new(5, 5);
+        for y in 0..5 {
+            grid.set_walkable(3, y, false);
+        }
+        let field = FlowField::build(&grid, 0, 0);
+        for y in 0..5 {
+            let direction = field.direction_at(4, y);
+            assert_eq!((direction.x, direction.y), (0.0, 0.0));
+            assert_eq!(field.distance_at(4, y), -1.0);
+        }
+    }
+
+    #[test]
+    fn visible_cells_cover_open_range_and_stop_at_walls() {
+        let open = Grid::new(9, 9);
+        let visible = visible_cells(&open, 4, 4, 2);
+        // Every cell with dx^2 + dy^2 <= 4
+        assert_eq!(visible.len(), 13);
+        assert!(visible.contains(&(4 * 9 + 6)));
+        assert!(!visible.contains(&(6 * 9 + 6)));
+
+        let mut walled = Grid::new(7, 3);
+        for y in 0..3 {
+            walled.set_walkable(3, y, false);
+        }
+        let visible = visible_cells(&walled, 0, 1, 6);
+        assert!(visible.contains(&(7 + 2)));
+        assert!(visible.contains(&(7 + 3)));
+        assert!(!visible.contains(&(7 + 4)));
+        assert!(!visible.contains(&(7 + 6)));
+        assert!(visible_cells(&walled, 10, 1, 6).is_empty());
+    }
+}
@@ -1,27 +1,517 @@
 use wasm_bindgen::prelude::*;
-use crate::math::Vector3;
+use std::collections::HashSet;
+use crate::math::{DVector3, Vector3};
+use crate::rng::Rng;
+
+// Axis-aligned bounding box used for static level geometry
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug)]
+pub struct Aabb {
+    pub min: Vector3,
+    pub max: Vector3,
+}
+
+#[wasm_bindgen]
+impl Aabb {
+    #[wasm_bindgen(constructor)]
+    pub fn new(min: &Vector3, max: &Vector3) -> Aabb {
+        Aabb {
+            min: *min,
+            max: *max,
+        }
+    }
+
+    // Inclusive point containment test
+    pub fn contains_point(&self, point: &Vector3) -> bool {
+        point.x >= self.min.x && point.x <= self.max.x
+            && point.y >= self.min.y && point.y <= self.max.y
+            && point.z >= self.min.z && point.z <= self.max.z
+    }
+
+    // Sphere overlap test using the closest point on the box to the center
+    pub fn intersects_sphere(&self, center: &Vector3, radius: f32) -> bool {
+        self.closest_point(center).distance(center) <= radius
+    }
+
+    // Slab test for the segment from `start` to `end`
+    pub fn intersects_segment(&self, start: &Vector3, end: &Vector3) -> bool {
+        let origin = [start.x, start.y, start.z];
+        let delta = [end.x - start.x, end.y - start.y, end.z - start.z];
+        let min = [self.min.x, self.min.y, self.min.z];
+        let max = [self.max.x, self.max.y, self.max.z];
+
+        let mut t_enter = 0.0f32;
+        let mut t_exit = 1.0f32;
+        for axis in 0..3 {
+            if delta[axis].abs() < 1e-8 {
+                // Parallel to this slab: must already be inside it
+                if origin[axis] < min[axis] || origin[axis] > max[axis] {
+                    return false;
+                }
+                continue;
+            }
+            let inv = 1.0 / delta[axis];
+            let mut t0 = (min[axis] - origin[axis]) * inv;
+            let mut t1 = (max[axis] - origin[axis]) * inv;
+            if t0 > t1 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+            t_enter = t_enter.max(t0);
+            t_exit = t_exit.min(t1);
+            if t_enter > t_exit {
+                return false;
+            }
+        }
+        true
+    }
+
+    // Total area of the six faces, the cost term for SAH splits
+    pub fn surface_area(&self) -> f32 {
+        let dx = (self.max.x - self.min.x).max(0.0);
+        let dy = (self.max.y - self.min.y).max(0.0);
+        let dz = (self.max.z - self.min.z).max(0.0);
+        2.0 * (dx * dy + dy * dz + dz * dx)
+    }
+
+    pub fn volume(&self) -> f32 {
+        let dx = (self.max.x - self.min.x).max(0.0);
+        let dy = (self.max.y - self.min.y).max(0.0);
+        let dz = (self.max.z - self.min.z).max(0.0);
+        dx * dy * dz
+    }
+
+    // Inclusive test that `other` lies entirely inside this box
+    pub fn contains(&self, other: &Aabb) -> bool {
+        self.contains_point(&other.min) && self.contains_point(&other.max)
+    }
+}
+
+impl Aabb {
+    fn closest_point(&self, point: &Vector3) -> Vector3 {
+        Vector3 {
+            x: point.x.clamp(self.min.x, self.max.x),
+            y: point.y.clamp(self.min.y, self.max.y),
+            z: point.z.clamp(self.min.z, self.max.z),
+        }
+    }
+}
+
+// Capsule-shaped character: a segment from `a` to `b` swept by `radius`
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug)]
+pub struct Capsule {
+    pub a: Vector3,
+    pub b: Vector3,
+    pub radius: f32,
+}
+
+#[wasm_bindgen]
+impl Capsule {
+    #[wasm_bindgen(constructor)]
+    pub fn new(a: &Vector3, b: &Vector3, radius: f32) -> Capsule {
+        Capsule {
+            a: *a,
+            b: *b,
+            radius,
+        }
+    }
+
+    fn translate(&mut self, offset: &Vector3) {
+        self.a = self.a.add(offset);
+        self.b = self.b.add(offset);
+    }
+}
+
+// Closest points between segments p1-q1 and p2-q2
+fn closest_points_on_segments(p1: &Vector3, q1: &Vector3, p2: &Vector3, q2: &Vector3) -> (Vector3, Vector3) {
+    let d1 = q1.subtract(p1);
+    let d2 = q2.subtract(p2);
+    let r = p1.subtract(p2);
+    let a = d1.dot(&d1);
+    let e = d2.dot(&d2);
+    let f = d2.dot(&r);
+    const EPSILON: f32 = 1e-8;
+
+    let (s, t) = if a <= EPSILON && e <= EPSILON {
+        (0.0, 0.0)
+    } else if a <= EPSILON {
+        (0.0, (f / e).clamp(0.0, 1.0))
+    } else {
+        let c = d1.dot(&r);
+        if e <= EPSILON {
+            ((-c / a).clamp(0.0, 1.0), 0.0)
+        } else {
+            let b = d1.dot(&d2);
+            let denominator = a * e - b * b;
+            // Parallel segments: any s works, start from p1
+            let mut s = if denominator > EPSILON { ((b * f - c * e) / denominator).clamp(0.0, 1.0) } else { 0.0 };
+            let mut t = (b * s + f) / e;
+            if t < 0.0 {
+                t = 0.0;
+                s = (-c / a).clamp(0.0, 1.0);
+            } else if t > 1.0 {
+                t = 1.0;
+                s = ((b - c) / a).clamp(0.0, 1.0);
+            }
+            (s, t)
+        }
+    };
+    (p1.add(&d1.multiply(s)), p2.add(&d2.multiply(t)))
+}
+
+// Contact phase of an entity pair relative to the previous frame
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ContactPhase {
+    None,
+    Enter,
+    Stay,
+    Exit,
+}
+
+// Tracks which entity pairs overlapped last frame to derive enter/stay/exit events
+#[wasm_bindgen]
+#[derive(Default)]
+pub struct CollisionState {
+    touching: HashSet<(u32, u32)>,
+}
+
+#[wasm_bindgen]
+impl CollisionState {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> CollisionState {
+        CollisionState {
+            touching: HashSet::new(),
+        }
+    }
+
+    // Record this frame's overlap for a pair (order-independent) and report its phase
+    pub fn update_pair(&mut self, id_a: u32, id_b: u32, overlapping: bool) -> ContactPhase {
+        let key = if id_a <= id_b { (id_a, id_b) } else { (id_b, id_a) };
+        let was_overlapping = self.touching.contains(&key);
+
+        match (was_overlapping, overlapping) {
+            (false, true) => {
+                self.touching.insert(key);
+                ContactPhase::Enter
+            },
+            (true, true) => ContactPhase::Stay,
+            (true, false) => {
+                self.touching.remove(&key);
+                ContactPhase::Exit
+            },
+            (false, false) => ContactPhase::None,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.touching.clear();
+    }
+}
+
+// How two spheres in contact are moving along the contact normal
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ContactKind {
+    Colliding,
+    Resting,
+    Separating,
+}
+
+// Resolves sphere contacts through PhysicsSystem and classifies each one by
+// the normal velocity it had beforehand. Closing or separating speeds within
+// `resting_threshold` count as Resting, which gameplay can use to put
+// entities to sleep.
+#[wasm_bindgen]
+pub struct ContactResolver {
+    resting_threshold: f32,
+    last_kind: ContactKind,
+    last_normal_velocity: f32,
+}
+
+#[wasm_bindgen]
+impl ContactResolver {
+    #[wasm_bindgen(constructor)]
+    pub fn new(resting_threshold: f32) -> ContactResolver {
+        ContactResolver {
+            resting_threshold: resting_threshold.abs(),
+            last_kind: ContactKind::Separating,
+            last_normal_velocity: 0.0,
+        }
+    }
+
+    // Classify the contact, then apply resolve_sphere_collision to it
+    #[allow(clippy::too_many_arguments)]
+    pub fn resolve(
+        &mut self,
+        physics: &PhysicsSystem,
+        position1: &mut Vector3,
+        velocity1: &mut Vector3,
+        mass1: f32,
+        position2: &mut Vector3,
+        velocity2: &mut Vector3,
+        mass2: f32,
+    ) -> ContactKind {
+        self.last_normal_velocity = normal_velocity(position1, velocity1, position2, velocity2);
+        self.last_kind = self.classify_normal_velocity(self.last_normal_velocity);
+        physics.resolve_sphere_collision(position1, velocity1, mass1, position2, velocity2, mass2);
+        self.last_kind
+    }
+
+    // Classify without resolving
+    pub fn classify(&self, position1: &Vector3, velocity1: &Vector3, position2: &Vector3, velocity2: &Vector3) -> ContactKind {
+        self.classify_normal_velocity(normal_velocity(position1, velocity1, position2, velocity2))
+    }
+
+    // Classification of the most recent `resolve`
+    pub fn last_kind(&self) -> ContactKind {
+        self.last_kind
+    }
+
+    // Relative normal velocity of the most recent `resolve`; positive when separating
+    pub fn last_normal_velocity(&self) -> f32 {
+        self.last_normal_velocity
+    }
+}
+
+impl ContactResolver {
+    fn classify_normal_velocity(&self, normal_velocity: f32) -> ContactKind {
+        if normal_velocity.abs() <= self.resting_threshold {
+            ContactKind::Resting
+        } else if normal_velocity > 0.0 {
+            ContactKind::Separating
+        } else {
+            ContactKind::Colliding
+        }
+    }
+}
+
+// Relative velocity of sphere 1 away from sphere 2 along the line between
+// their centers; 0 when the centers coincide
+fn normal_velocity(position1: &Vector3, velocity1: &Vector3, position2: &Vector3, velocity2: &Vector3) -> f32 {
+    let mut normal = position1.subtract(position2);
+    normal.normalize();
+    velocity1.subtract(velocity2).dot(&normal)
+}
+
+// Inverse mass with non-positive mass treated as infinite (immovable)
+fn inverse_mass(mass: f32) -> f32 {
+    if mass > 0.0 {
+        1.0 / mass
+    } else {
+        0.0
+    }
+}
+
+// World axis that points up. Gravity, the ground clamp, ground contacts,
+// speed clamping and the character movement helpers all measure height and
+// horizontal motion against it.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum UpAxis {
+    X,
+    Y,
+    Z,
+}
+
+impl UpAxis {
+    fn unit(self) -> Vector3 {
+        let mut unit = Vector3::new(0.0, 0.0, 0.0);
+        *self.component_mut(&mut unit) = 1.0;
+        unit
+    }
+
+    fn component(self, vector: &Vector3) -> f32 {
+        vector.dot(&self.unit())
+    }
+
+    // `vector` with its up component removed
+    fn horizontal(self, vector: &Vector3) -> Vector3 {
+        let mut horizontal = *vector;
+        *self.component_mut(&mut horizontal) = 0.0;
+        horizontal
+    }
+
+    fn component_mut(self, vector: &mut Vector3) -> &mut f32 {
+        match self {
+            UpAxis::X => &mut vector.x,
+            UpAxis::Y => &mut vector.y,
+            UpAxis::Z => &mut vector.z,
+        }
+    }
+
+    fn component_mut_f64(self, vector: &mut DVector3) -> &mut f64 {
+        match self {
+            UpAxis::X => &mut vector.x,
+            UpAxis::Y => &mut vector.y,
+            UpAxis::Z => &mut vector.z,
+        }
+    }
+}
+
+// Distance within which move_and_slide still reports a surface as touched
+// after resolving out of it, so sliding keeps its wall contact
+const CONTACT_SKIN: f32 = 1e-3;
+
+// Surfaces whose normal points up by less than this (steeper than about 84
+// degrees, or overhanging) block move_and_slide instead of being slid along
+const BLOCKING_NORMAL_UP: f32 = 0.1;
+
+// Steepest walkable slope accepted by set_max_walkable_slope, just short of
+// acos(BLOCKING_NORMAL_UP) so walkable surfaces never reach the blocking
+// regime and lifting a character up one stays finite
+const MAX_WALKABLE_SLOPE_LIMIT: f32 = 1.47;
 
 // Physics system for collision detection
 #[wasm_bindgen]
 pub struct PhysicsSystem {
     gravity: f32,
+    // Axis treated as up by gravity, ground handling and movement
+    up_axis: UpAxis,
+    // Steepest surface (radians from horizontal) move_and_slide walks up
+    max_walkable_slope: f32,
+    // Tallest ledge move_and_slide steps onto instead of treating as a wall
+    step_height: f32,
+    // Fraction of wall-tangent speed move_and_slide_velocity removes per
+    // second while touching a wall; walkable ground is not affected
+    wall_friction: f32,
+    // Largest impulse a single sphere or capsule contact may apply, so
+    // extreme closing speeds cannot launch bodies across the map
+    max_impulse: f32,
+    // Passes solve_contacts makes over every contact. More passes let
+    // corrections propagate through stacks and dense piles at a linear cost
+    // per frame; a single pass is cheapest but lets stacked bodies sink.
+    iterations: u32,
+}
+
+impl Default for PhysicsSystem {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 #[wasm_bindgen]
 impl PhysicsSystem {
     #[wasm_bindgen(constructor)]
     pub fn new() -> PhysicsSystem {
-        PhysicsSystem { gravity: 9.8 }
+        PhysicsSystem {
+            gravity: 9.8,
+            up_axis: UpAxis::Y,
+            max_walkable_slope: std::f32::consts::FRAC_PI_4,
+            step_height: 0.3,
+            wall_friction: 0.0,
+            max_impulse: f32::INFINITY,
+            iterations: 1,
+        }
+    }
+
+    pub fn set_up_axis(&mut self, up_axis: UpAxis) {
+        self.up_axis = up_axis;
+    }
+
+    // Clamped to [0, MAX_WALKABLE_SLOPE_LIMIT]; vertical surfaces are never walkable
+    pub fn set_max_walkable_slope(&mut self, radians: f32) {
+        self.max_walkable_slope = radians.clamp(0.0, MAX_WALKABLE_SLOPE_LIMIT);
+    }
+
+    pub fn set_step_height(&mut self, height: f32) {
+        self.step_height = height;
+    }
+
+    pub fn set_wall_friction(&mut self, friction: f32) {
+        self.wall_friction = friction.max(0.0);
+    }
+
+    // Cap the impulse magnitude of sphere and capsule contacts; a negative
+    // cap means unlimited, which is the default
+    pub fn set_max_impulse(&mut self, max_impulse: f32) {
+        self.max_impulse = if max_impulse < 0.0 { f32::INFINITY } else { max_impulse };
+    }
+
+    // Solver passes per solve_contacts call (at least 1)
+    pub fn set_iterations(&mut self, iterations: u32) {
+        self.iterations = iterations.max(1);
     }
 
     pub fn apply_gravity(&self, position: &mut Vector3, velocity: &mut Vector3, delta_time: f32) {
-        velocity.y -= self.gravity * delta_time;
-        position.y += velocity.y * delta_time;
+        let up_velocity = self.up_axis.component_mut(velocity);
+        *up_velocity -= self.gravity * delta_time;
+        let up_speed = *up_velocity;
+        *self.up_axis.component_mut(position) += up_speed * delta_time;
 
         // Simple ground collision
-        if position.y < 0.0 {
-            position.y = 0.0;
-            velocity.y = 0.0;
+        self.clamp_to_ground(position, velocity);
+    }
+
+    // apply_gravity plus an extra acceleration (thrusters, jetpacks, homing)
+    // added to the velocity before position is integrated
+    pub fn integrate_with_acceleration(
+        &self,
+        position: &mut Vector3,
+        velocity: &mut Vector3,
+        acceleration: &Vector3,
+        delta_time: f32,
+    ) {
+        *velocity = velocity.add(&acceleration.multiply(delta_time));
+        *self.up_axis.component_mut(velocity) -= self.gravity * delta_time;
+        *position = position.add(&velocity.multiply(delta_time));
+
+        self.clamp_to_ground(position, velocity);
+    }
+
+    // A grounded entity moving no faster than `threshold` can skip integration
+    pub fn should_sleep(&self, velocity: &Vector3, grounded: bool, threshold: f32) -> bool {
+        grounded && velocity.length() <= threshold
+    }
+
+    // integrate_with_acceleration (no extra acceleration) over `count`
+    // entities in xyz-interleaved buffers, skipping those flagged asleep in
+    // `sleeping` (non-zero). A sleeping entity whose velocity was pushed above
+    // `sleep_threshold` since the last call, e.g. by a collision impulse, is
+    // woken and integrated. Afterwards every entity resting on the ground at
+    // or below the threshold is flagged asleep.
+    pub fn integrate_batch(
+        &self,
+        positions: &mut [f32],
+        velocities: &mut [f32],
+        sleeping: &mut [u8],
+        count: usize,
+        delta_time: f32,
+        sleep_threshold: f32,
+    ) {
+        let count = count
+            .min(positions.len() / 3)
+            .min(velocities.len() / 3)
+            .min(sleeping.len());
+
+        for i in 0..count {
+            let range = i * 3..i * 3 + 3;
+            let mut velocity = Vector3::new(velocities[i * 3], velocities[i * 3 + 1], velocities[i * 3 + 2]);
+            if sleeping[i] != 0 && velocity.length() <= sleep_threshold {
+                continue;
+            }
+
+            let mut position = Vector3::new(positions[i * 3], positions[i * 3 + 1], positions[i * 3 + 2]);
+            self.integrate_with_acceleration(&mut position, &mut velocity, &Vector3::new(0.0, 0.0, 0.0), delta_time);
+            positions[range.clone()].copy_from_slice(&[position.x, position.y, position.z]);
+            velocities[range].copy_from_slice(&[velocity.x, velocity.y, velocity.z]);
+
+            let grounded = self.up_axis.component(&position) <= 0.0;
+            sleeping[i] = self.should_sleep(&velocity, grounded, sleep_threshold) as u8;
+        }
+    }
+
+    // apply_gravity in double precision for entities far from the origin
+    pub fn apply_gravity_f64(&self, position: &mut DVector3, velocity: &mut DVector3, delta_time: f64) {
+        let up_velocity = self.up_axis.component_mut_f64(velocity);
+        *up_velocity -= self.gravity as f64 * delta_time;
+        let up_speed = *up_velocity;
+        let height = self.up_axis.component_mut_f64(position);
+        *height += up_speed * delta_time;
+
+        if *height < 0.0 {
+            *height = 0.0;
+            *self.up_axis.component_mut_f64(velocity) = 0.0;
         }
     }
 
@@ -55,6 +545,8 @@ impl PhysicsSystem {
             z: position1.z - position2.z,
         };
         direction.normalize();
+        // Coincident or non-finite positions give no usable normal
+        direction = direction.sanitized(&Vector3::new(0.0, 0.0, 0.0));
 
         // Calculate relative velocity
         let relative_velocity = Vector3 {
@@ -73,20 +565,1423 @@ impl PhysicsSystem {
             return;
         }
 
+        // Non-positive mass is immovable; two immovable bodies exchange nothing
+        let inverse_mass1 = inverse_mass(mass1);
+        let inverse_mass2 = inverse_mass(mass2);
+        let inverse_mass_sum = inverse_mass1 + inverse_mass2;
+        if inverse_mass_sum <= 0.0 {
+            return;
+        }
+
         // Calculate restitution (bounciness)
         let restitution = 0.2;
 
         // Calculate impulse scalar
         let impulse_scalar = -(1.0 + restitution) * velocity_along_normal;
-        let impulse_scalar = impulse_scalar / (1.0 / mass1 + 1.0 / mass2);
+        let impulse_scalar = (impulse_scalar / inverse_mass_sum).min(self.max_impulse);
 
         // Apply impulse
-        velocity1.x += impulse_scalar * direction.x / mass1;
-        velocity1.y += impulse_scalar * direction.y / mass1;
-        velocity1.z += impulse_scalar * direction.z / mass1;
+        velocity1.x += impulse_scalar * direction.x * inverse_mass1;
+        velocity1.y += impulse_scalar * direction.y * inverse_mass1;
+        velocity1.z += impulse_scalar * direction.z * inverse_mass1;
 
-        velocity2.x -= impulse_scalar * direction.x / mass2;
-        velocity2.y -= impulse_scalar * direction.y / mass2;
-        velocity2.z -= impulse_scalar * direction.z / mass2;
+        velocity2.x -= impulse_scalar * direction.x * inverse_mass2;
+        velocity2.y -= impulse_scalar * direction.y * inverse_mass2;
+        velocity2.z -= impulse_scalar * direction.z * inverse_mass2;
     }
-}
\ No newline at end of file
+
+    // Clamp horizontal speed and vertical speed (along the up axis)
+    // independently; a negative cap means unlimited
+    pub fn clamp_velocity(&self, velocity: &mut Vector3, max_horizontal: f32, max_vertical: f32) {
+        let up = self.up_axis;
+        if max_horizontal >= 0.0 {
+            let horizontal = up.horizontal(velocity);
+            let horizontal_speed = horizontal.length();
+            if horizontal_speed > max_horizontal {
+                let vertical = up.component(velocity);
+                *velocity = horizontal.multiply(max_horizontal / horizontal_speed);
+                *up.component_mut(velocity) = vertical;
+            }
+        }
+
+        if max_vertical >= 0.0 {
+            let vertical = up.component_mut(velocity);
+            *vertical = vertical.clamp(-max_vertical, max_vertical);
+        }
+    }
+
+    // Push a sphere out of a plane and reflect its velocity.
+    // The plane holds all points p with dot(plane_normal, p) == plane_d and the
+    // normal (assumed normalized) points toward the free side. `position` is
+    // the sphere's center, so include its radius in `plane_d`: a sphere of
+    // radius r resting on the plane d has its center on the plane d + r.
+    pub fn resolve_sphere_plane(
+        &self,
+        position: &mut Vector3,
+        velocity: &mut Vector3,
+        plane_normal: &Vector3,
+        plane_d: f32,
+        restitution: f32,
+    ) {
+        let distance = position.dot(plane_normal) - plane_d;
+        if distance >= 0.0 {
+            return;
+        }
+
+        // Move the sphere back onto the surface
+        let penetration = -distance;
+        position.x += plane_normal.x * penetration;
+        position.y += plane_normal.y * penetration;
+        position.z += plane_normal.z * penetration;
+
+        // Reflect only the velocity heading into the plane
+        let velocity_along_normal = velocity.dot(plane_normal);
+        if velocity_along_normal < 0.0 {
+            let impulse = -(1.0 + restitution) * velocity_along_normal;
+            velocity.x += impulse * plane_normal.x;
+            velocity.y += impulse * plane_normal.y;
+            velocity.z += impulse * plane_normal.z;
+        }
+    }
+
+    // Trigger volumes only report overlap and never resolve. Returns true only on
+    // the frame a sphere of `radius` moves from outside `trigger` to inside it.
+    pub fn check_trigger_enter(&self, prev_pos: &Vector3, new_pos: &Vector3, radius: f32, trigger: &Aabb) -> bool {
+        !trigger.intersects_sphere(prev_pos, radius) && trigger.intersects_sphere(new_pos, radius)
+    }
+
+    // Damage-over-time for an entity inside a zone this frame: dps * delta_time
+    // when inside (the boundary counts as inside), otherwise 0
+    pub fn apply_zone_damage(&self, entity: &Vector3, zone: &Aabb, dps: f32, delta_time: f32) -> f32 {
+        if zone.contains_point(entity) {
+            dps * delta_time
+        } else {
+            0.0
+        }
+    }
+
+    // Debug dump of every overlapping sphere pair from xyz-interleaved positions.
+    // Each contact is 9 floats: [index_a, index_b, point xyz, normal xyz, depth],
+    // with the normal pointing from a to b and the point midway through the overlap.
+    pub fn collect_manifold(&self, positions: &[f32], radii: &[f32], count: usize) -> Vec<f32> {
+        let count = count.min(positions.len() / 3).min(radii.len());
+        let mut records = Vec::new();
+
+        for a in 0..count {
+            let position_a = Vector3::new(positions[a * 3], positions[a * 3 + 1], positions[a * 3 + 2]);
+            for b in (a + 1)..count {
+                let position_b = Vector3::new(positions[b * 3], positions[b * 3 + 1], positions[b * 3 + 2]);
+                let offset = position_b.subtract(&position_a);
+                let distance = offset.length();
+                let depth = radii[a] + radii[b] - distance;
+                if depth <= 0.0 {
+                    continue;
+                }
+
+                // Coincident centers have no defined normal; pick up
+                let normal = if distance > 0.0 {
+                    offset.multiply(1.0 / distance)
+                } else {
+                    self.up_axis.unit()
+                };
+                let point = position_a.add(&normal.multiply(radii[a] - depth * 0.5));
+
+                records.extend_from_slice(&[
+                    a as f32, b as f32,
+                    point.x, point.y, point.z,
+                    normal.x, normal.y, normal.z,
+                    depth,
+                ]);
+            }
+        }
+
+        records
+    }
+
+    // Fraction of the frame [0, 1] at which two linearly moving spheres first
+    // touch, solved in the frame of the second sphere. Returns 0 if they already
+    // overlap and 1.0 when they never touch during the interval.
+    pub fn sweep_sphere_sphere(
+        &self,
+        p1_start: &Vector3,
+        p1_end: &Vector3,
+        r1: f32,
+        p2_start: &Vector3,
+        p2_end: &Vector3,
+        r2: f32,
+    ) -> f32 {
+        let separation = p1_start.subtract(p2_start);
+        let relative_motion = p1_end.subtract(p1_start).subtract(&p2_end.subtract(p2_start));
+        let radius = r1 + r2;
+
+        let c = separation.dot(&separation) - radius * radius;
+        if c <= 0.0 {
+            return 0.0;
+        }
+
+        let a = relative_motion.dot(&relative_motion);
+        if a < 1e-12 {
+            return 1.0;
+        }
+        let b = 2.0 * separation.dot(&relative_motion);
+        let discriminant = b * b - 4.0 * a * c;
+        if discriminant < 0.0 {
+            return 1.0;
+        }
+
+        let t = (-b - discriminant.sqrt()) / (2.0 * a);
+        if (0.0..=1.0).contains(&t) {
+            t
+        } else {
+            1.0
+        }
+    }
+
+    // Impulse resolution plus positional correction for overlapping spheres.
+    // The overlap is split by inverse mass, so the lighter body moves more;
+    // a non-positive mass is treated as immovable.
+    #[allow(clippy::too_many_arguments)]
+    pub fn resolve_sphere_collision_with_correction(
+        &self,
+        position1: &mut Vector3,
+        velocity1: &mut Vector3,
+        radius1: f32,
+        mass1: f32,
+        position2: &mut Vector3,
+        velocity2: &mut Vector3,
+        radius2: f32,
+        mass2: f32,
+    ) {
+        let offset = position1.subtract(position2);
+        let distance = offset.length();
+        let penetration = radius1 + radius2 - distance;
+        if penetration <= 0.0 {
+            return;
+        }
+
+        self.resolve_sphere_collision(position1, velocity1, mass1, position2, velocity2, mass2);
+
+        let inverse_mass1 = inverse_mass(mass1);
+        let inverse_mass2 = inverse_mass(mass2);
+        let inverse_mass_sum = inverse_mass1 + inverse_mass2;
+        if inverse_mass_sum <= 0.0 {
+            return;
+        }
+
+        // Coincident centers have no defined normal; separate vertically
+        let normal = if distance > 0.0 {
+            offset.multiply(1.0 / distance)
+        } else {
+            self.up_axis.unit()
+        };
+        *position1 = position1.add(&normal.multiply(penetration * inverse_mass1 / inverse_mass_sum));
+        *position2 = position2.subtract(&normal.multiply(penetration * inverse_mass2 / inverse_mass_sum));
+    }
+
+    // Resolve every overlapping sphere pair, and spheres sunk below the ground
+    // plane at height ground_level on the up axis, from xyz-interleaved
+    // positions and velocities.
+    // Runs `iterations` passes so corrections propagate through stacks.
+    pub fn solve_contacts(
+        &self,
+        positions: &mut [f32],
+        velocities: &mut [f32],
+        radii: &[f32],
+        masses: &[f32],
+        count: usize,
+        ground_level: f32,
+    ) {
+        let count = count
+            .min(positions.len() / 3)
+            .min(velocities.len() / 3)
+            .min(radii.len())
+            .min(masses.len());
+        let load = |buffer: &[f32], i: usize| Vector3::new(buffer[i * 3], buffer[i * 3 + 1], buffer[i * 3 + 2]);
+        let store = |buffer: &mut [f32], i: usize, value: &Vector3| {
+            buffer[i * 3..i * 3 + 3].copy_from_slice(&[value.x, value.y, value.z]);
+        };
+        let ground_normal = self.up_axis.unit();
+
+        for _ in 0..self.iterations {
+            for a in 0..count {
+                for b in (a + 1)..count {
+                    let (mut position_a, mut velocity_a) = (load(positions, a), load(velocities, a));
+                    let (mut position_b, mut velocity_b) = (load(positions, b), load(velocities, b));
+                    self.resolve_sphere_collision_with_correction(
+                        &mut position_a, &mut velocity_a, radii[a], masses[a],
+                        &mut position_b, &mut velocity_b, radii[b], masses[b],
+                    );
+                    store(positions, a, &position_a);
+                    store(velocities, a, &velocity_a);
+                    store(positions, b, &position_b);
+                    store(velocities, b, &velocity_b);
+                }
+            }
+
+            for (i, &radius) in radii.iter().enumerate().take(count) {
+                let (mut position, mut velocity) = (load(positions, i), load(velocities, i));
+                self.resolve_sphere_plane(&mut position, &mut velocity, &ground_normal, ground_level + radius, 0.0);
+                store(positions, i, &position);
+                store(velocities, i, &velocity);
+            }
+        }
+    }
+
+    // Move an entity to `to` and drop its velocity, e.g. on respawn, so it
+    // does not carry momentum from where it was
+    pub fn teleport(&self, position: &mut Vector3, velocity: &mut Vector3, to: &Vector3) {
+        *position = *to;
+        *velocity = Vector3::new(0.0, 0.0, 0.0);
+    }
+
+    // Move an entity to `to` keeping its velocity, e.g. through a portal
+    pub fn teleport_keep_velocity(&self, position: &mut Vector3, to: &Vector3) {
+        *position = *to;
+    }
+
+    // For each of `ray_count` rays (xyz-interleaved origins and directions,
+    // directions need not be normalized) the index of the nearest sphere it
+    // hits in front of its origin, or -1 on a miss. A ray starting inside a
+    // sphere hits it at distance 0.
+    pub fn raycast_spheres_batch(
+        &self,
+        origins: &[f32],
+        directions: &[f32],
+        ray_count: usize,
+        centers: &[f32],
+        radii: &[f32],
+        sphere_count: usize,
+    ) -> Vec<i32> {
+        let ray_count = ray_count.min(origins.len() / 3).min(directions.len() / 3);
+        let sphere_count = sphere_count.min(centers.len() / 3).min(radii.len());
+        let load = |buffer: &[f32], i: usize| Vector3::new(buffer[i * 3], buffer[i * 3 + 1], buffer[i * 3 + 2]);
+
+        (0..ray_count)
+            .map(|ray| {
+                let origin = load(origins, ray);
+                let mut direction = load(directions, ray);
+                direction.normalize();
+                if direction.length() == 0.0 {
+                    return -1;
+                }
+
+                let mut nearest: Option<(usize, f32)> = None;
+                for (sphere, &radius) in radii.iter().enumerate().take(sphere_count) {
+                    // Solve |origin + direction t - center| == radius for t >= 0
+                    let offset = origin.subtract(&load(centers, sphere));
+                    let b = offset.dot(&direction);
+                    let c = offset.dot(&offset) - radius * radius;
+                    let discriminant = b * b - c;
+                    if discriminant < 0.0 {
+                        continue;
+                    }
+                    // Outside the sphere both roots share a sign, so the
+                    // nearer one being negative means it lies behind the ray
+                    let t = if c <= 0.0 { 0.0 } else { -b - discriminant.sqrt() };
+                    if t < 0.0 {
+                        continue;
+                    }
+                    if nearest.is_none_or(|(_, best)| t < best) {
+                        nearest = Some((sphere, t));
+                    }
+                }
+                nearest.map_or(-1, |(sphere, _)| sphere as i32)
+            })
+            .collect()
+    }
+
+    // move_and_slide with boxes given as a flat buffer of (min xyz, max xyz)
+    // records, for use from JS
+    pub fn move_and_slide_flat(
+        &self,
+        position: &mut Vector3,
+        displacement: &Vector3,
+        radius: f32,
+        planes: &[f32],
+        boxes: &[f32],
+    ) {
+        self.move_and_slide(position, displacement, radius, planes, &boxes_from_flat(boxes));
+    }
+
+    // move_and_slide_velocity with boxes as in move_and_slide_flat
+    pub fn move_and_slide_velocity_flat(
+        &self,
+        position: &mut Vector3,
+        velocity: &mut Vector3,
+        delta_time: f32,
+        radius: f32,
+        planes: &[f32],
+        boxes: &[f32],
+    ) {
+        self.move_and_slide_velocity(position, velocity, delta_time, radius, planes, &boxes_from_flat(boxes));
+    }
+
+    // snap_to_ground with boxes as in move_and_slide_flat
+    pub fn snap_to_ground_flat(
+        &self,
+        position: &mut Vector3,
+        velocity: &Vector3,
+        max_snap_distance: f32,
+        ground_boxes: &[f32],
+    ) -> bool {
+        self.snap_to_ground(position, velocity, max_snap_distance, &boxes_from_flat(ground_boxes))
+    }
+
+    // Carry a rider along with the platform it stands on by applying the
+    // platform's displacement for this frame
+    pub fn apply_platform_motion(&self, rider_position: &mut Vector3, platform_delta: &Vector3, grounded_on_platform: bool) {
+        if grounded_on_platform {
+            *rider_position = rider_position.add(platform_delta);
+        }
+    }
+
+    // Keep an entity inside the level bounds, cancelling any velocity that
+    // still points out through a wall it was clamped against
+    pub fn confine_to_bounds(&self, position: &mut Vector3, velocity: &mut Vector3, bounds: &Aabb) {
+        fn confine_axis(position: &mut f32, velocity: &mut f32, min: f32, max: f32) {
+            if *position < min {
+                *position = min;
+                *velocity = velocity.max(0.0);
+            } else if *position > max {
+                *position = max;
+                *velocity = velocity.min(0.0);
+            }
+        }
+        confine_axis(&mut position.x, &mut velocity.x, bounds.min.x, bounds.max.x);
+        confine_axis(&mut position.y, &mut velocity.y, bounds.min.y, bounds.max.y);
+        confine_axis(&mut position.z, &mut velocity.z, bounds.min.z, bounds.max.z);
+    }
+
+    // Keep an entity inside a spherical dome: past the boundary it is pulled
+    // back onto the surface and its outward velocity is reflected inward
+    pub fn confine_to_sphere(
+        &self,
+        position: &mut Vector3,
+        velocity: &mut Vector3,
+        center: &Vector3,
+        radius: f32,
+        restitution: f32,
+    ) {
+        let offset = position.subtract(center);
+        let distance = offset.length();
+        if distance <= radius || distance == 0.0 {
+            return;
+        }
+
+        let outward = offset.multiply(1.0 / distance);
+        *position = center.add(&outward.multiply(radius.max(0.0)));
+
+        let outward_speed = velocity.dot(&outward);
+        if outward_speed > 0.0 {
+            *velocity = velocity.subtract(&outward.multiply((1.0 + restitution) * outward_speed));
+        }
+    }
+
+    // Separate two overlapping capsules: an impulse along the normal between
+    // their closest points, then positional correction split by inverse mass.
+    // A non-positive mass is treated as immovable.
+    pub fn resolve_capsule_capsule(
+        &self,
+        c1: &mut Capsule,
+        v1: &mut Vector3,
+        m1: f32,
+        c2: &mut Capsule,
+        v2: &mut Vector3,
+        m2: f32,
+    ) {
+        let (closest1, closest2) = closest_points_on_segments(&c1.a, &c1.b, &c2.a, &c2.b);
+        let offset = closest1.subtract(&closest2);
+        let distance = offset.length();
+        let penetration = c1.radius + c2.radius - distance;
+        if penetration <= 0.0 {
+            return;
+        }
+
+        let inverse_mass1 = inverse_mass(m1);
+        let inverse_mass2 = inverse_mass(m2);
+        let inverse_mass_sum = inverse_mass1 + inverse_mass2;
+        if inverse_mass_sum <= 0.0 {
+            return;
+        }
+
+        // Crossing core segments have no defined normal; separate vertically
+        let normal = if distance > 0.0 {
+            offset.multiply(1.0 / distance)
+        } else {
+            self.up_axis.unit()
+        };
+
+        let velocity_along_normal = v1.subtract(v2).dot(&normal);
+        if velocity_along_normal < 0.0 {
+            let restitution = 0.2;
+            let impulse = (-(1.0 + restitution) * velocity_along_normal / inverse_mass_sum).min(self.max_impulse);
+            *v1 = v1.add(&normal.multiply(impulse * inverse_mass1));
+            *v2 = v2.subtract(&normal.multiply(impulse * inverse_mass2));
+        }
+
+        c1.translate(&normal.multiply(penetration * inverse_mass1 / inverse_mass_sum));
+        c2.translate(&normal.multiply(-penetration * inverse_mass2 / inverse_mass_sum));
+    }
+}
+
+impl PhysicsSystem {
+    // Keep the position on or above the ground plane through the origin,
+    // stopping vertical motion on contact
+    fn clamp_to_ground(&self, position: &mut Vector3, velocity: &mut Vector3) {
+        let height = self.up_axis.component_mut(position);
+        if *height < 0.0 {
+            *height = 0.0;
+            *self.up_axis.component_mut(velocity) = 0.0;
+        }
+    }
+
+    // Move a sphere-shaped character (position is its center) by `displacement`
+    // and resolve it against level geometry. `planes` holds (nx, ny, nz, d)
+    // records using the same convention as resolve_sphere_plane. Surfaces no
+    // steeper than max_walkable_slope are walked up, steeper ones are slid
+    // along (pushed out along their normal), and near-vertical or
+    // overhanging ones block: only the horizontal motion into them is undone,
+    // so they cannot be climbed. Boxes whose top is within step_height of the
+    // feet are stepped onto; taller boxes block like walls.
+    pub fn move_and_slide(
+        &self,
+        position: &mut Vector3,
+        displacement: &Vector3,
+        radius: f32,
+        planes: &[f32],
+        boxes: &[Aabb],
+    ) {
+        self.slide(position, displacement, radius, planes, boxes);
+    }
+
+    // move_and_slide driven by a velocity over `delta_time`. Velocity into
+    // any surface touched is removed, and for each wall touched the velocity
+    // tangential to it loses `wall_friction` of its speed per second; the
+    // component along the wall normal is left alone.
+    pub fn move_and_slide_velocity(
+        &self,
+        position: &mut Vector3,
+        velocity: &mut Vector3,
+        delta_time: f32,
+        radius: f32,
+        planes: &[f32],
+        boxes: &[Aabb],
+    ) {
+        let contacts = self.slide(position, &velocity.multiply(delta_time), radius, planes, boxes);
+
+        for (normal, _) in &contacts {
+            let into_surface = velocity.dot(normal);
+            if into_surface < 0.0 {
+                *velocity = velocity.subtract(&normal.multiply(into_surface));
+            }
+        }
+
+        let retained = (1.0 - self.wall_friction * delta_time).max(0.0);
+        for (normal, _) in contacts.iter().filter(|(_, is_wall)| *is_wall) {
+            let along_normal = normal.multiply(velocity.dot(normal));
+            let tangential = velocity.subtract(&along_normal);
+            *velocity = along_normal.add(&tangential.multiply(retained));
+        }
+    }
+
+    // Pull a character whose `position` is at its feet down onto the highest
+    // box top beneath it when that top is no more than `max_snap_distance`
+    // below and it is moving downward, so walking down slopes and steps does
+    // not leave it briefly airborne. Returns whether it snapped.
+    pub fn snap_to_ground(
+        &self,
+        position: &mut Vector3,
+        velocity: &Vector3,
+        max_snap_distance: f32,
+        ground_boxes: &[Aabb],
+    ) -> bool {
+        self.snap_sphere_to_ground(position, velocity, 0.0, max_snap_distance, ground_boxes)
+    }
+
+    // snap_to_ground for a sphere-shaped character positioned by its center,
+    // as in move_and_slide, with its feet `radius` below it
+    pub fn snap_sphere_to_ground(
+        &self,
+        position: &mut Vector3,
+        velocity: &Vector3,
+        radius: f32,
+        max_snap_distance: f32,
+        ground_boxes: &[Aabb],
+    ) -> bool {
+        let up = self.up_axis;
+        if up.component(velocity) >= 0.0 {
+            return false;
+        }
+        let feet = up.component(position) - radius;
+
+        let ground = ground_boxes
+            .iter()
+            .filter(|aabb| up.horizontal(&position.subtract(&aabb.closest_point(position))).length() == 0.0)
+            .map(|aabb| up.component(&aabb.max))
+            .filter(|&top| top <= feet + CONTACT_SKIN && feet - top <= max_snap_distance)
+            .reduce(f32::max);
+
+        match ground {
+            Some(top) => {
+                *up.component_mut(position) = top + radius;
+                true
+            },
+            None => false,
+        }
+    }
+
+    // Shared resolution for move_and_slide; returns the normal of every
+    // surface touched, flagged true for slopes and walls. Blocking walls
+    // report their horizontal normal.
+    fn slide(
+        &self,
+        position: &mut Vector3,
+        displacement: &Vector3,
+        radius: f32,
+        planes: &[f32],
+        boxes: &[Aabb],
+    ) -> Vec<(Vector3, bool)> {
+        let mut contacts = Vec::new();
+        *position = position.add(displacement);
+
+        let up = self.up_axis;
+        let walkable_normal_up = self.max_walkable_slope.cos();
+        for plane in planes.chunks_exact(4) {
+            let normal = Vector3::new(plane[0], plane[1], plane[2]);
+            let penetration = radius - (position.dot(&normal) - plane[3]);
+            if penetration <= -CONTACT_SKIN {
+                continue;
+            }
+            let normal_up = up.component(&normal);
+            if normal_up >= walkable_normal_up {
+                // Walkable: lift straight up so horizontal progress carries up
+                // the slope. The slope limit keeps normal_up well above zero.
+                if penetration > 0.0 {
+                    *up.component_mut(position) += penetration / normal_up;
+                }
+                contacts.push((normal, false));
+            } else if normal_up >= BLOCKING_NORMAL_UP {
+                // Slide: remove only the motion into the surface and keep the rest
+                if penetration > 0.0 {
+                    *position = position.add(&normal.multiply(penetration));
+                }
+                contacts.push((normal, true));
+            } else {
+                // Block: push back out horizontally so the wall cannot be climbed
+                let mut horizontal = up.horizontal(&normal);
+                let horizontal_length = horizontal.length();
+                if horizontal_length == 0.0 {
+                    continue;
+                }
+                horizontal = horizontal.multiply(1.0 / horizontal_length);
+                if penetration > 0.0 {
+                    *position = position.add(&horizontal.multiply(penetration / horizontal_length));
+                }
+                contacts.push((horizontal, true));
+            }
+        }
+
+        for aabb in boxes {
+            let overlapping = aabb.intersects_sphere(position, radius);
+            if !overlapping && !aabb.intersects_sphere(position, radius + CONTACT_SKIN) {
+                continue;
+            }
+            let feet = up.component(position) - radius;
+            let top = up.component(&aabb.max);
+            if overlapping && top - feet <= self.step_height {
+                *up.component_mut(position) = top + radius;
+                contacts.push((up.unit(), false));
+                continue;
+            }
+            // Push out horizontally from the nearest point on the box
+            let offset = up.horizontal(&position.subtract(&aabb.closest_point(position)));
+            let distance = offset.length();
+            if distance > 0.0 && distance < radius + CONTACT_SKIN {
+                if distance < radius {
+                    *position = position.add(&offset.multiply((radius - distance) / distance));
+                }
+                contacts.push((offset.multiply(1.0 / distance), true));
+            }
+        }
+
+        contacts
+    }
+}
+
+fn boxes_from_flat(boxes: &[f32]) -> Vec<Aabb> {
+    boxes
+        .chunks_exact(6)
+        .map(|b| Aabb::new(&Vector3::new(b[0], b[1], b[2]), &Vector3::new(b[3], b[4], b[5])))
+        .collect()
+}
+
+// `count` unit directions spread uniformly over the cone of `half_angle`
+// radians around `cone_forward`, xyz-interleaved. A half angle of π covers
+// the full sphere. The same seed always yields the same directions.
+#[wasm_bindgen]
+pub fn scatter_directions(count: usize, cone_forward: &Vector3, half_angle: f32, seed: u64) -> Vec<f32> {
+    let basis = cone_forward.build_basis();
+    let (forward, right, up) = (basis[0], basis[1], basis[2]);
+    let min_cos = half_angle.clamp(0.0, std::f32::consts::PI).cos();
+    let mut rng = Rng::new(seed);
+
+    let mut directions = Vec::with_capacity(count * 3);
+    for _ in 0..count {
+        // Uniform in cos(theta) gives uniform area on the spherical cap
+        let cos_theta = 1.0 - rng.next_f32() * (1.0 - min_cos);
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let (sin_phi, cos_phi) = (rng.next_f32() * std::f32::consts::TAU).sin_cos();
+        let mut direction = forward
+            .multiply(cos_theta)
+            .add(&right.multiply(sin_theta * cos_phi))
+            .add(&up.multiply(sin_theta * sin_phi));
+        direction.normalize();
+        directions.extend_from_slice(&[direction.x, direction.y, direction.z]);
+    }
+    directions
+}
+
+// Where and when a ballistic projectile first comes down through the ground
+// plane y == ground_level, as [x, z, time]. Empty when it never lands.
+#[wasm_bindgen]
+pub fn projectile_landing(origin: &Vector3, velocity: &Vector3, gravity: &Vector3, ground_level: f32) -> Vec<f32> {
+    // Solve origin.y + velocity.y t + gravity.y t^2 / 2 == ground_level
+    let a = 0.5 * gravity.y;
+    let b = velocity.y;
+    let c = origin.y - ground_level;
+    let roots = if a.abs() < 1e-8 {
+        if b.abs() < 1e-8 { vec![] } else { vec![-c / b] }
+    } else {
+        let discriminant = b * b - 4.0 * a * c;
+        if discriminant < 0.0 {
+            vec![]
+        } else {
+            let root = discriminant.sqrt();
+            vec![(-b - root) / (2.0 * a), (-b + root) / (2.0 * a)]
+        }
+    };
+
+    // Earliest future crossing while moving downward
+    let landing = roots
+        .into_iter()
+        .filter(|&t| t > 0.0 && velocity.y + gravity.y * t < 0.0)
+        .min_by(|a, b| a.total_cmp(b));
+    match landing {
+        Some(t) => vec![
+            origin.x + velocity.x * t + 0.5 * gravity.x * t * t,
+            origin.z + velocity.z * t + 0.5 * gravity.z * t * t,
+            t,
+        ],
+        None => Vec::new(),
+    }
+}
+
+// Indices of the `k` points nearest to `from` in an xyz-interleaved buffer of
+// `count` points, closest first. Keeps a bounded sorted buffer of the best
+// candidates so only k entries are ever ordered; ties keep index order.
+#[wasm_bindgen]
+pub fn k_nearest_indices(from: &Vector3, positions: &[f32], count: usize, k: usize) -> Vec<u32> {
+    let count = count.min(positions.len() / 3);
+    let k = k.min(count);
+    let mut nearest: Vec<(f32, u32)> = Vec::with_capacity(k + 1);
+    if k == 0 {
+        return Vec::new();
+    }
+
+    for (index, point) in positions.chunks_exact(3).take(count).enumerate() {
+        let dx = point[0] - from.x;
+        let dy = point[1] - from.y;
+        let dz = point[2] - from.z;
+        let distance_squared = dx * dx + dy * dy + dz * dz;
+        if nearest.len() == k && distance_squared >= nearest[k - 1].0 {
+            continue;
+        }
+        let slot = nearest.partition_point(|&(distance, _)| distance <= distance_squared);
+        nearest.insert(slot, (distance_squared, index as u32));
+        nearest.truncate(k);
+    }
+    nearest.into_iter().map(|(_, index)| index).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plane(normal: Vector3, d: f32) -> [f32; 4] {
+        let mut normal = normal;
+        normal.normalize();
+        [normal.x, normal.y, normal.z, d]
+    }
+
+    #[test]
+    fn move_and_slide_walks_up_gentle_slopes() {
+        let physics = PhysicsSystem::new();
+        // 30 degree ramp rising toward +X through the origin
+        let ramp = plane(Vector3::new(-0.5, 0.866, 0.0), 0.0);
+        let mut position = Vector3::new(0.0, 0.5, 0.0);
+        physics.move_and_slide(&mut position, &Vector3::new(1.0, 0.0, 0.0), 0.5, &ramp, &[]);
+        assert!((position.x - 1.0).abs() < 1e-5);
+        assert!(position.y > 1.0);
+    }
+
+    #[test]
+    fn move_and_slide_slides_on_steep_slopes_and_is_blocked_by_walls() {
+        let physics = PhysicsSystem::new();
+        // 60 degree slope: pushed back along the normal, so forward progress is lost
+        let steep = plane(Vector3::new(-0.866, 0.5, 0.0), 0.0);
+        let mut position = Vector3::new(0.0, 0.5, 0.0);
+        physics.move_and_slide(&mut position, &Vector3::new(1.0, 0.0, 0.0), 0.5, &steep, &[]);
+        assert!(position.x < 1.0);
+
+        // Nearly vertical wall at x = 2: blocked without being lifted
+        let wall = plane(Vector3::new(-1.0, 0.05, 0.0), -2.0);
+        let mut position = Vector3::new(1.0, 0.5, 0.0);
+        physics.move_and_slide(&mut position, &Vector3::new(1.0, 0.0, 1.0), 0.5, &wall, &[]);
+        assert!(position.x < 1.6);
+        assert_eq!(position.y, 0.5);
+        assert_eq!(position.z, 1.0);
+    }
+
+    #[test]
+    fn move_and_slide_stays_finite_with_vertical_slope_limit() {
+        let mut physics = PhysicsSystem::new();
+        physics.set_max_walkable_slope(std::f32::consts::FRAC_PI_2 + 0.2);
+        let wall = plane(Vector3::new(-1.0, 0.0, 0.0), -1.0);
+        let mut position = Vector3::new(0.8, 0.5, 0.0);
+        physics.move_and_slide(&mut position, &Vector3::new(0.5, 0.0, 0.0), 0.5, &wall, &[]);
+        assert!(position.is_finite());
+        assert!((position.x - 0.5).abs() < 1e-5);
+    }
+
+    #[test]
+    fn move_and_slide_steps_onto_low_boxes_only() {
+        let physics = PhysicsSystem::new();
+        let step = [0.8, 0.0, -1.0, 2.0, 0.2, 1.0];
+        let mut position = Vector3::new(0.0, 0.5, 0.0);
+        physics.move_and_slide_flat(&mut position, &Vector3::new(0.5, 0.0, 0.0), 0.5, &[], &step);
+        assert!((position.y - 0.7).abs() < 1e-5);
+
+        let ledge = [0.8, 0.0, -1.0, 2.0, 1.0, 1.0];
+        let mut position = Vector3::new(0.0, 0.5, 0.0);
+        physics.move_and_slide_flat(&mut position, &Vector3::new(0.5, 0.0, 0.0), 0.5, &[], &ledge);
+        assert_eq!(position.y, 0.5);
+        assert!((position.x - 0.3).abs() < 1e-5);
+    }
+
+    #[test]
+    fn wall_friction_only_slows_motion_along_the_wall() {
+        let mut physics = PhysicsSystem::new();
+        physics.set_wall_friction(2.0);
+        let wall = [-1.0, 0.0, 0.0, -1.0];
+
+        // Pressing into the wall: into-wall speed removed, sliding speed damped
+        let mut position = Vector3::new(0.5, 0.5, 0.0);
+        let mut velocity = Vector3::new(1.0, 0.0, 4.0);
+        physics.move_and_slide_velocity_flat(&mut position, &mut velocity, 0.25, 0.5, &wall, &[]);
+        assert!(velocity.x.abs() < 1e-5);
+        assert!((velocity.z - 2.0).abs() < 1e-5);
+
+        // Pushing off the wall keeps the full speed away from it
+        let mut position = Vector3::new(0.5, 0.5, 0.0);
+        let mut velocity = Vector3::new(-0.002, 0.0, 4.0);
+        physics.move_and_slide_velocity(&mut position, &mut velocity, 0.25, 0.5, &wall, &[]);
+        assert_eq!(velocity.x, -0.002);
+        assert!((velocity.z - 2.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn snap_to_ground_pulls_feet_down_onto_a_step() {
+        let physics = PhysicsSystem::new();
+        let floor = [-5.0, -1.0, -5.0, 5.0, 0.0, 5.0];
+        let step_down = [0.0, -1.0, -1.0, 2.0, -0.2, 1.0];
+        let falling = Vector3::new(0.0, -1.0, 0.0);
+
+        // Feet 0.1 above the floor: snapped onto it
+        let mut position = Vector3::new(-2.0, 0.1, 0.0);
+        assert!(physics.snap_to_ground_flat(&mut position, &falling, 0.3, &floor));
+        assert!(position.y.abs() < 1e-6);
+
+        // Stepping off onto a lower box keeps the character grounded
+        let mut position = Vector3::new(1.0, 0.0, 0.0);
+        assert!(physics.snap_to_ground_flat(&mut position, &falling, 0.3, &step_down));
+        assert!((position.y + 0.2).abs() < 1e-6);
+
+        // Out of reach, moving up or not moving down, it stays airborne
+        let mut position = Vector3::new(-2.0, 1.0, 0.0);
+        assert!(!physics.snap_to_ground_flat(&mut position, &falling, 0.3, &floor));
+        assert!(!physics.snap_to_ground_flat(&mut position, &Vector3::new(0.0, 1.0, 0.0), 5.0, &floor));
+        assert!(!physics.snap_to_ground_flat(&mut position, &Vector3::new(1.0, 0.0, 0.0), 5.0, &floor));
+        assert_eq!(position.y, 1.0);
+    }
+
+    #[test]
+    fn snap_sphere_to_ground_uses_the_sphere_center_like_move_and_slide() {
+        let physics = PhysicsSystem::new();
+        let floor = [Aabb::new(&Vector3::new(-5.0, -1.0, -5.0), &Vector3::new(5.0, 0.0, 5.0))];
+        let falling = Vector3::new(0.0, -1.0, 0.0);
+
+        // Feet 0.1 above the floor: snapped so the feet rest on it
+        let mut position = Vector3::new(-2.0, 0.6, 0.0);
+        assert!(physics.snap_sphere_to_ground(&mut position, &falling, 0.5, 0.3, &floor));
+        assert!((position.y - 0.5).abs() < 1e-6);
+
+        // A box resolved by move_and_slide counts as ground afterwards
+        let boxes = [Aabb::new(&Vector3::new(0.8, 0.0, -1.0), &Vector3::new(2.0, 0.2, 1.0))];
+        let mut position = Vector3::new(0.0, 0.5, 0.0);
+        physics.move_and_slide(&mut position, &Vector3::new(1.0, 0.0, 0.0), 0.5, &[], &boxes);
+        let stepped = position;
+        assert!(physics.snap_sphere_to_ground(&mut position, &falling, 0.5, 0.1, &boxes));
+        assert!((position.y - stepped.y).abs() < 1e-6);
+    }
+
+    #[test]
+    fn z_up_axis_drives_ground_contacts_and_movement() {
+        let mut physics = PhysicsSystem::new();
+        physics.set_up_axis(UpAxis::Z);
+
+        // Ground contact pushes out along +Z
+        let mut positions = [0.0, 0.0, -0.2];
+        let mut velocities = [0.0, 0.0, -1.0];
+        physics.solve_contacts(&mut positions, &mut velocities, &[0.5], &[1.0], 1, 0.0);
+        assert!((positions[2] - 0.5).abs() < 1e-5);
+        assert_eq!(positions[1], 0.0);
+
+        // Step onto a low box: lifted along Z
+        let step = [Aabb::new(&Vector3::new(0.8, -1.0, 0.0), &Vector3::new(2.0, 1.0, 0.2))];
+        let mut position = Vector3::new(0.0, 0.0, 0.5);
+        physics.move_and_slide(&mut position, &Vector3::new(0.5, 0.0, 0.0), 0.5, &[], &step);
+        assert!((position.z - 0.7).abs() < 1e-5);
+
+        // Walkable ramp rising toward +X lifts along Z
+        let ramp = [-0.5, 0.0, 0.866, 0.0];
+        let mut position = Vector3::new(0.0, 0.0, 0.5);
+        physics.move_and_slide(&mut position, &Vector3::new(1.0, 0.0, 0.0), 0.5, &ramp, &[]);
+        assert!(position.z > 1.0 && position.y == 0.0);
+
+        // Snap down along Z
+        let mut position = Vector3::new(1.0, 0.0, 0.8);
+        assert!(physics.snap_sphere_to_ground(&mut position, &Vector3::new(0.0, 0.0, -1.0), 0.5, 0.2, &step));
+        assert!((position.z - 0.7).abs() < 1e-5);
+
+        // Horizontal speed is measured in the XY plane
+        let mut velocity = Vector3::new(3.0, 4.0, 10.0);
+        physics.clamp_velocity(&mut velocity, 1.0, 2.0);
+        assert!((Vector3::new(velocity.x, velocity.y, 0.0).length() - 1.0).abs() < 1e-5);
+        assert_eq!(velocity.z, 2.0);
+    }
+
+    #[test]
+    fn clamp_velocity_caps_horizontal_and_vertical_independently() {
+        let physics = PhysicsSystem::new();
+        let mut velocity = Vector3::new(6.0, -20.0, 8.0);
+        physics.clamp_velocity(&mut velocity, 5.0, 10.0);
+        assert!((velocity.x - 3.0).abs() < 1e-5);
+        assert!((velocity.z - 4.0).abs() < 1e-5);
+        assert_eq!(velocity.y, -10.0);
+
+        let mut unlimited = Vector3::new(6.0, -20.0, 8.0);
+        physics.clamp_velocity(&mut unlimited, -1.0, -1.0);
+        assert_eq!((unlimited.x, unlimited.y, unlimited.z), (6.0, -20.0, 8.0));
+    }
+
+    #[test]
+    fn sphere_plane_pushes_out_and_reflects_incoming_velocity() {
+        let physics = PhysicsSystem::new();
+        let up = Vector3::new(0.0, 1.0, 0.0);
+
+        let mut position = Vector3::new(0.0, 1.2, 0.0);
+        let mut velocity = Vector3::new(2.0, -4.0, 0.0);
+        physics.resolve_sphere_plane(&mut position, &mut velocity, &up, 1.5, 0.5);
+        assert!((position.y - 1.5).abs() < 1e-6);
+        assert!((velocity.y - 2.0).abs() < 1e-6);
+        assert_eq!(velocity.x, 2.0);
+
+        // Already leaving: pushed out but velocity untouched
+        let mut velocity = Vector3::new(0.0, 3.0, 0.0);
+        let mut position = Vector3::new(0.0, 1.2, 0.0);
+        physics.resolve_sphere_plane(&mut position, &mut velocity, &up, 1.5, 0.5);
+        assert_eq!(velocity.y, 3.0);
+
+        // Clear of the plane: nothing changes
+        let mut position = Vector3::new(0.0, 2.0, 0.0);
+        physics.resolve_sphere_plane(&mut position, &mut velocity, &up, 1.5, 0.5);
+        assert_eq!(position.y, 2.0);
+    }
+
+    #[test]
+    fn sphere_hitting_a_tilted_plane_lands_on_its_surface() {
+        let physics = PhysicsSystem::new();
+        let mut normal = Vector3::new(1.0, 1.0, 0.0);
+        normal.normalize();
+        let radius = 0.5;
+
+        // Sunk 0.2 into a 45 degree plane through the origin, falling straight down
+        let mut position = normal.multiply(radius - 0.2);
+        let mut velocity = Vector3::new(0.0, -2.0, 0.0);
+        physics.resolve_sphere_plane(&mut position, &mut velocity, &normal, radius, 1.0);
+        assert!((position.dot(&normal) - radius).abs() < 1e-6);
+
+        // Perfectly elastic: the normal component flips, the tangent is kept
+        assert!((velocity.x - 2.0).abs() < 1e-5);
+        assert!(velocity.y.abs() < 1e-5);
+        assert!((velocity.length() - 2.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn trigger_enter_fires_only_on_the_entering_frame() {
+        let physics = PhysicsSystem::new();
+        let trigger = Aabb::new(&Vector3::new(-1.0, -1.0, -1.0), &Vector3::new(1.0, 1.0, 1.0));
+        let outside = Vector3::new(5.0, 0.0, 0.0);
+        let inside = Vector3::new(0.0, 0.0, 0.0);
+        let also_inside = Vector3::new(0.5, 0.0, 0.0);
+
+        assert!(physics.check_trigger_enter(&outside, &inside, 0.5, &trigger));
+        assert!(!physics.check_trigger_enter(&inside, &also_inside, 0.5, &trigger));
+        assert!(!physics.check_trigger_enter(&inside, &outside, 0.5, &trigger));
+        // The radius counts: touching the face from outside is an entry
+        assert!(physics.check_trigger_enter(&outside, &Vector3::new(1.4, 0.0, 0.0), 0.5, &trigger));
+    }
+
+    #[test]
+    fn collision_state_walks_a_pair_through_enter_stay_exit() {
+        let mut state = CollisionState::new();
+        assert_eq!(state.update_pair(1, 2, false), ContactPhase::None);
+        assert_eq!(state.update_pair(1, 2, true), ContactPhase::Enter);
+        // Pair order does not matter
+        assert_eq!(state.update_pair(2, 1, true), ContactPhase::Stay);
+        assert_eq!(state.update_pair(1, 2, false), ContactPhase::Exit);
+        assert_eq!(state.update_pair(1, 2, false), ContactPhase::None);
+        assert_eq!(state.update_pair(1, 2, true), ContactPhase::Enter);
+    }
+
+    #[test]
+    fn zone_damage_applies_inside_and_on_the_boundary() {
+        let physics = PhysicsSystem::new();
+        let zone = Aabb::new(&Vector3::new(0.0, 0.0, 0.0), &Vector3::new(2.0, 2.0, 2.0));
+        assert_eq!(physics.apply_zone_damage(&Vector3::new(1.0, 1.0, 1.0), &zone, 10.0, 0.5), 5.0);
+        // The boundary is inclusive
+        assert_eq!(physics.apply_zone_damage(&Vector3::new(2.0, 1.0, 1.0), &zone, 10.0, 0.5), 5.0);
+        assert_eq!(physics.apply_zone_damage(&Vector3::new(2.1, 1.0, 1.0), &zone, 10.0, 0.5), 0.0);
+    }
+
+    #[test]
+    fn collect_manifold_reports_one_record_per_overlap() {
+        let physics = PhysicsSystem::new();
+        let positions = [0.0, 0.0, 0.0, 1.5, 0.0, 0.0, 10.0, 0.0, 0.0];
+        let radii = [1.0, 1.0, 1.0];
+        let manifold = physics.collect_manifold(&positions, &radii, 3);
+        assert_eq!(manifold.len(), 9);
+        assert_eq!(&manifold[0..2], &[0.0, 1.0]);
+        // Normal points from the first sphere to the second, 0.5 deep
+        assert_eq!(&manifold[5..8], &[1.0, 0.0, 0.0]);
+        assert!((manifold[8] - 0.5).abs() < 1e-6);
+        assert!((manifold[2] - 0.75).abs() < 1e-6);
+    }
+
+    #[test]
+    fn sweep_sphere_sphere_finds_first_touch_of_crossing_paths() {
+        let physics = PhysicsSystem::new();
+        let fraction = physics.sweep_sphere_sphere(
+            &Vector3::new(-5.0, 0.0, 0.0), &Vector3::new(5.0, 0.0, 0.0), 0.5,
+            &Vector3::new(0.0, 0.0, -5.0), &Vector3::new(0.0, 0.0, 5.0), 0.5,
+        );
+        let expected = 0.5 - 1.0 / (10.0 * 2.0_f32.sqrt());
+        assert!((fraction - expected).abs() < 1e-4);
+
+        let parallel = physics.sweep_sphere_sphere(
+            &Vector3::new(0.0, 0.0, 0.0), &Vector3::new(10.0, 0.0, 0.0), 0.5,
+            &Vector3::new(0.0, 0.0, 3.0), &Vector3::new(10.0, 0.0, 3.0), 0.5,
+        );
+        assert_eq!(parallel, 1.0);
+
+        let overlapping = physics.sweep_sphere_sphere(
+            &Vector3::new(0.0, 0.0, 0.0), &Vector3::new(1.0, 0.0, 0.0), 0.5,
+            &Vector3::new(0.5, 0.0, 0.0), &Vector3::new(1.5, 0.0, 0.0), 0.5,
+        );
+        assert_eq!(overlapping, 0.0);
+    }
+
+    fn separate(mass1: f32, mass2: f32) -> (f32, f32) {
+        let physics = PhysicsSystem::new();
+        let mut position1 = Vector3::new(0.0, 0.0, 0.0);
+        let mut position2 = Vector3::new(1.5, 0.0, 0.0);
+        let mut velocity1 = Vector3::new(0.0, 0.0, 0.0);
+        let mut velocity2 = Vector3::new(0.0, 0.0, 0.0);
+        physics.resolve_sphere_collision_with_correction(
+            &mut position1, &mut velocity1, 1.0, mass1,
+            &mut position2, &mut velocity2, 1.0, mass2,
+        );
+        assert!((position2.x - position1.x - 2.0).abs() < 1e-5);
+        (-position1.x, position2.x - 1.5)
+    }
+
+    #[test]
+    fn positional_correction_splits_overlap_by_inverse_mass() {
+        let (light, heavy) = separate(1.0, 10.0);
+        assert!((light - 10.0 * heavy).abs() < 1e-5);
+        assert!((light + heavy - 0.5).abs() < 1e-5);
+
+        let (first, second) = separate(2.0, 2.0);
+        assert!((first - 0.25).abs() < 1e-5);
+        assert!((second - 0.25).abs() < 1e-5);
+
+        // Immovable bodies take none of the correction
+        let (free, fixed) = separate(1.0, 0.0);
+        assert!((free - 0.5).abs() < 1e-5);
+        assert_eq!(fixed, 0.0);
+    }
+
+    #[test]
+    fn aabb_measures_and_containment() {
+        let unit = Aabb::new(&Vector3::new(0.0, 0.0, 0.0), &Vector3::new(1.0, 1.0, 1.0));
+        assert_eq!(unit.surface_area(), 6.0);
+        assert_eq!(unit.volume(), 1.0);
+
+        let large = Aabb::new(&Vector3::new(-2.0, -2.0, -2.0), &Vector3::new(2.0, 2.0, 2.0));
+        assert!(large.contains(&unit));
+        assert!(!unit.contains(&large));
+        let straddling = Aabb::new(&Vector3::new(1.0, 1.0, 1.0), &Vector3::new(3.0, 3.0, 3.0));
+        assert!(!large.contains(&straddling));
+    }
+
+    #[test]
+    fn platform_motion_carries_only_grounded_riders() {
+        let physics = PhysicsSystem::new();
+        let delta = Vector3::new(0.5, 0.25, 0.0);
+        let mut grounded = Vector3::new(1.0, 2.0, 3.0);
+        physics.apply_platform_motion(&mut grounded, &delta, true);
+        assert_eq!((grounded.x, grounded.y, grounded.z), (1.5, 2.25, 3.0));
+
+        let mut airborne = Vector3::new(1.0, 2.0, 3.0);
+        physics.apply_platform_motion(&mut airborne, &delta, false);
+        assert_eq!((airborne.x, airborne.y, airborne.z), (1.0, 2.0, 3.0));
+    }
+
+    #[test]
+    fn scatter_directions_are_unit_inside_the_cone_and_seeded() {
+        let forward = Vector3::new(0.0, 1.0, 1.0);
+        let mut axis = forward;
+        axis.normalize();
+        let half_angle = 0.3;
+        let directions = scatter_directions(64, &forward, half_angle, 42);
+        assert_eq!(directions.len(), 64 * 3);
+        for d in directions.chunks(3) {
+            let direction = Vector3::new(d[0], d[1], d[2]);
+            assert!((direction.length() - 1.0).abs() < 1e-5);
+            assert!(direction.dot(&axis) >= half_angle.cos() - 1e-5);
+        }
+        assert_eq!(scatter_directions(64, &forward, half_angle, 42), directions);
+        assert_ne!(scatter_directions(64, &forward, half_angle, 43), directions);
+
+        // A half angle of pi reaches behind the forward direction
+        let sphere = scatter_directions(64, &forward, std::f32::consts::PI, 42);
+        assert!(sphere.chunks(3).any(|d| Vector3::new(d[0], d[1], d[2]).dot(&axis) < 0.0));
+    }
+
+    #[test]
+    fn confine_to_bounds_stops_at_the_wall_and_keeps_other_axes() {
+        let physics = PhysicsSystem::new();
+        let bounds = Aabb::new(&Vector3::new(-10.0, 0.0, -10.0), &Vector3::new(10.0, 20.0, 10.0));
+        let mut position = Vector3::new(12.0, 5.0, 3.0);
+        let mut velocity = Vector3::new(4.0, -1.0, 2.0);
+        physics.confine_to_bounds(&mut position, &mut velocity, &bounds);
+        assert_eq!((position.x, position.y, position.z), (10.0, 5.0, 3.0));
+        assert_eq!((velocity.x, velocity.y, velocity.z), (0.0, -1.0, 2.0));
+
+        // Velocity already heading back inside is kept
+        let mut position = Vector3::new(12.0, 5.0, 3.0);
+        let mut velocity = Vector3::new(-4.0, 0.0, 0.0);
+        physics.confine_to_bounds(&mut position, &mut velocity, &bounds);
+        assert_eq!(velocity.x, -4.0);
+    }
+
+    #[test]
+    fn confine_to_sphere_returns_escapees_with_reflected_velocity() {
+        let physics = PhysicsSystem::new();
+        let center = Vector3::new(0.0, 0.0, 0.0);
+        let mut position = Vector3::new(12.0, 0.0, 0.0);
+        let mut velocity = Vector3::new(4.0, 1.0, 0.0);
+        physics.confine_to_sphere(&mut position, &mut velocity, &center, 10.0, 0.5);
+        assert_eq!((position.x, position.y, position.z), (10.0, 0.0, 0.0));
+        // Outward speed 4 comes back at half strength; tangential motion is kept
+        assert_eq!((velocity.x, velocity.y, velocity.z), (-2.0, 1.0, 0.0));
+
+        let mut inside = Vector3::new(3.0, 0.0, 0.0);
+        let mut velocity = Vector3::new(4.0, 0.0, 0.0);
+        physics.confine_to_sphere(&mut inside, &mut velocity, &center, 10.0, 0.5);
+        assert_eq!((inside.x, velocity.x), (3.0, 4.0));
+    }
+
+    #[test]
+    fn projectile_landing_solves_the_downward_crossing() {
+        let gravity = Vector3::new(0.0, -10.0, 0.0);
+        let up = projectile_landing(&Vector3::new(3.0, 0.0, -2.0), &Vector3::new(0.0, 10.0, 0.0), &gravity, 0.0);
+        assert_eq!(up.len(), 3);
+        assert_eq!((up[0], up[1]), (3.0, -2.0));
+        assert!((up[2] - 2.0).abs() < 1e-5);
+
+        // Thrown from 15 units up, 10 up and 5 forward: lands after 3 seconds
+        let angled = projectile_landing(&Vector3::new(0.0, 15.0, 0.0), &Vector3::new(5.0, 10.0, 0.0), &gravity, 0.0);
+        assert!((angled[0] - 15.0).abs() < 1e-4);
+        assert_eq!(angled[1], 0.0);
+        assert!((angled[2] - 3.0).abs() < 1e-5);
+
+        // Never comes down without gravity
+        assert!(projectile_landing(&Vector3::new(0.0, 1.0, 0.0), &Vector3::new(1.0, 1.0, 0.0), &Vector3::new(0.0, 0.0, 0.0), 0.0).is_empty());
+    }
+
+    #[test]
+    fn f64_gravity_drifts_less_than_f32_far_from_the_origin() {
+        let physics = PhysicsSystem::new();
+        let (base, dt, steps) = (10_000_000.0_f64, 1.0 / 60.0, 600);
+        let gravity = 9.8_f32 as f64;
+
+        // Reference semi-implicit Euler, relative to the base height
+        let (mut offset, mut speed) = (0.0_f64, 30.0_f64);
+        for _ in 0..steps {
+            speed -= gravity * dt;
+            offset += speed * dt;
+        }
+
+        let mut single = Vector3::new(0.0, base as f32, 0.0);
+        let mut single_velocity = Vector3::new(0.0, 30.0, 0.0);
+        let mut double = DVector3::new(0.0, base, 0.0);
+        let mut double_velocity = DVector3::new(0.0, 30.0, 0.0);
+        for _ in 0..steps {
+            physics.apply_gravity(&mut single, &mut single_velocity, dt as f32);
+            physics.apply_gravity_f64(&mut double, &mut double_velocity, dt);
+        }
+
+        let single_error = (single.y as f64 - base - offset).abs();
+        let double_error = (double.y - base - offset).abs();
+        assert!(double_error < 1e-6);
+        assert!(single_error > 1.0);
+    }
+
+    #[test]
+    fn k_nearest_indices_returns_closest_first() {
+        let from = Vector3::new(0.0, 0.0, 0.0);
+        let positions = [
+            9.0, 0.0, 0.0,
+            -2.0, 0.0, 0.0,
+            0.0, 5.0, 0.0,
+            0.0, 0.0, 1.0,
+            4.0, 4.0, 4.0,
+        ];
+        assert_eq!(k_nearest_indices(&from, &positions, 5, 2), vec![3, 1]);
+        assert_eq!(k_nearest_indices(&from, &positions, 5, 10), vec![3, 1, 2, 4, 0]);
+        assert!(k_nearest_indices(&from, &positions, 5, 0).is_empty());
+    }
+
+    fn standing_capsule(x: f32) -> Capsule {
+        Capsule::new(&Vector3::new(x, 0.0, 0.0), &Vector3::new(x, 2.0, 0.0), 1.0)
+    }
+
+    #[test]
+    fn capsule_capsule_resolution_pushes_apart_by_mass() {
+        let physics = PhysicsSystem::new();
+        let mut first = standing_capsule(0.0);
+        let mut second = standing_capsule(1.5);
+        let (mut v1, mut v2) = (Vector3::new(1.0, 0.0, 0.0), Vector3::new(-1.0, 0.0, 0.0));
+        physics.resolve_capsule_capsule(&mut first, &mut v1, 1.0, &mut second, &mut v2, 1.0);
+        assert!((second.a.x - first.a.x - 2.0).abs() < 1e-5);
+        assert!((first.a.x + 0.25).abs() < 1e-5);
+        assert!((first.b.x + 0.25).abs() < 1e-5);
+        assert!(v1.x <= 0.0 && v2.x >= 0.0);
+
+        // An immovable capsule stays put and the other takes all the correction
+        let mut mover = standing_capsule(0.0);
+        let mut wall = standing_capsule(1.5);
+        let (mut v1, mut v2) = (Vector3::new(1.0, 0.0, 0.0), Vector3::new(0.0, 0.0, 0.0));
+        physics.resolve_capsule_capsule(&mut mover, &mut v1, 1.0, &mut wall, &mut v2, 0.0);
+        assert_eq!((wall.a.x, wall.b.x, v2.x), (1.5, 1.5, 0.0));
+        assert!((mover.a.x + 0.5).abs() < 1e-5);
+    }
+
+    #[test]
+    fn sphere_collision_ignores_a_nan_contact_normal() {
+        let physics = PhysicsSystem::new();
+        let mut position1 = Vector3::new(f32::NAN, 0.0, 0.0);
+        let mut position2 = Vector3::new(0.0, 0.0, 0.0);
+        let mut velocity1 = Vector3::new(1.0, 0.0, 0.0);
+        let mut velocity2 = Vector3::new(-1.0, 0.0, 0.0);
+        physics.resolve_sphere_collision(&mut position1, &mut velocity1, 1.0, &mut position2, &mut velocity2, 1.0);
+        assert!(velocity1.is_finite() && velocity2.is_finite());
+        assert_eq!((velocity1.x, velocity2.x), (1.0, -1.0));
+    }
+
+    // Deepest overlap left in a three-sphere stack after two seconds at 60 Hz
+    fn stack_penetration(iterations: u32) -> f32 {
+        let mut physics = PhysicsSystem::new();
+        physics.set_iterations(iterations);
+        let mut positions = [0.0, 0.5, 0.0, 0.0, 1.5, 0.0, 0.0, 2.5, 0.0];
+        let mut velocities = [0.0; 9];
+        let (radii, masses) = ([0.5; 3], [1.0; 3]);
+        for _ in 0..120 {
+            for i in 0..3 {
+                velocities[i * 3 + 1] -= 9.8 / 60.0;
+                positions[i * 3 + 1] += velocities[i * 3 + 1] / 60.0;
+            }
+            physics.solve_contacts(&mut positions, &mut velocities, &radii, &masses, 3, 0.0);
+        }
+        let ground = 0.5 - positions[1];
+        let lower = 1.0 - (positions[4] - positions[1]);
+        let upper = 1.0 - (positions[7] - positions[4]);
+        ground.max(lower).max(upper).max(0.0)
+    }
+
+    #[test]
+    fn more_solver_iterations_settle_a_sphere_stack() {
+        assert!(stack_penetration(1) > 0.01);
+        assert!(stack_penetration(8) < 0.001);
+        // Zero is raised to a single pass
+        assert_eq!(stack_penetration(0), stack_penetration(1));
+    }
+
+    #[test]
+    fn teleport_sets_position_and_clears_or_keeps_velocity() {
+        let physics = PhysicsSystem::new();
+        let to = Vector3::new(100.0, 5.0, -3.0);
+        let mut position = Vector3::new(0.0, 0.0, 0.0);
+        let mut velocity = Vector3::new(4.0, -9.0, 1.0);
+        physics.teleport(&mut position, &mut velocity, &to);
+        assert_eq!((position.x, position.y, position.z), (100.0, 5.0, -3.0));
+        assert_eq!((velocity.x, velocity.y, velocity.z), (0.0, 0.0, 0.0));
+
+        let mut position = Vector3::new(0.0, 0.0, 0.0);
+        physics.teleport_keep_velocity(&mut position, &to);
+        assert_eq!((position.x, position.y, position.z), (100.0, 5.0, -3.0));
+    }
+
+    fn collide_head_on(mass1: f32, mass2: f32) -> (Vector3, Vector3) {
+        let physics = PhysicsSystem::new();
+        let mut position1 = Vector3::new(0.0, 0.0, 0.0);
+        let mut position2 = Vector3::new(1.0, 0.0, 0.0);
+        let mut velocity1 = Vector3::new(1.0, 0.0, 0.0);
+        let mut velocity2 = Vector3::new(-1.0, 0.0, 0.0);
+        physics.resolve_sphere_collision(&mut position1, &mut velocity1, mass1, &mut position2, &mut velocity2, mass2);
+        assert!(velocity1.is_finite() && velocity2.is_finite());
+        (velocity1, velocity2)
+    }
+
+    #[test]
+    fn zero_mass_spheres_are_immovable_without_nan() {
+        let (static1, moving2) = collide_head_on(0.0, 1.0);
+        assert_eq!(static1.x, 1.0);
+        assert!(moving2.x > 0.0);
+
+        let (moving1, static2) = collide_head_on(1.0, 0.0);
+        assert!(moving1.x < 0.0);
+        assert_eq!(static2.x, -1.0);
+
+        let (both1, both2) = collide_head_on(0.0, -2.0);
+        assert_eq!((both1.x, both2.x), (1.0, -1.0));
+    }
+
+    #[test]
+    fn thrust_above_gravity_lifts_off_the_ground() {
+        let physics = PhysicsSystem::new();
+        let mut position = Vector3::new(0.0, 0.0, 0.0);
+        let mut velocity = Vector3::new(0.0, 0.0, 0.0);
+        let thrust = Vector3::new(1.0, 12.0, 0.0);
+        let mut heights = Vec::new();
+        for _ in 0..10 {
+            physics.integrate_with_acceleration(&mut position, &mut velocity, &thrust, 0.1);
+            heights.push(position.y);
+        }
+        assert!(heights.windows(2).all(|pair| pair[1] > pair[0]));
+        assert!((velocity.y - (12.0 - 9.8)).abs() < 1e-4);
+        assert!(position.x > 0.0);
+
+        // Weaker than gravity: stays clamped on the ground
+        let mut grounded = Vector3::new(0.0, 0.0, 0.0);
+        let mut velocity = Vector3::new(0.0, 0.0, 0.0);
+        physics.integrate_with_acceleration(&mut grounded, &mut velocity, &Vector3::new(0.0, 5.0, 0.0), 0.1);
+        assert_eq!(grounded.y, 0.0);
+    }
+
+    #[test]
+    fn impulse_cap_bounds_velocity_change_at_extreme_speed() {
+        let collide = |max_impulse: f32| {
+            let mut physics = PhysicsSystem::new();
+            physics.set_max_impulse(max_impulse);
+            let mut position1 = Vector3::new(0.0, 0.0, 0.0);
+            let mut position2 = Vector3::new(1.0, 0.0, 0.0);
+            let mut velocity1 = Vector3::new(1000.0, 0.0, 0.0);
+            let mut velocity2 = Vector3::new(-1000.0, 0.0, 0.0);
+            physics.resolve_sphere_collision(&mut position1, &mut velocity1, 1.0, &mut position2, &mut velocity2, 2.0);
+            (velocity1, velocity2)
+        };
+
+        let (free1, _) = collide(-1.0);
+        assert!(free1.x < 0.0);
+
+        let (capped1, capped2) = collide(10.0);
+        assert!((capped1.x - 990.0).abs() < 1e-3);
+        assert!((capped2.x + 995.0).abs() < 1e-3);
+        assert!(capped1.length() <= 1000.0 && capped2.length() <= 1000.0);
+    }
+
+    #[test]
+    fn contact_resolver_classifies_by_normal_velocity() {
+        let physics = PhysicsSystem::new();
+        let mut resolver = ContactResolver::new(0.1);
+        let resolve = |resolver: &mut ContactResolver, speed1: f32, speed2: f32| {
+            let mut position1 = Vector3::new(0.0, 0.0, 0.0);
+            let mut position2 = Vector3::new(1.0, 0.0, 0.0);
+            let mut velocity1 = Vector3::new(speed1, 0.0, 0.0);
+            let mut velocity2 = Vector3::new(speed2, 0.0, 0.0);
+            resolver.resolve(&physics, &mut position1, &mut velocity1, 1.0, &mut position2, &mut velocity2, 1.0)
+        };
+
+        assert_eq!(resolve(&mut resolver, 5.0, -5.0), ContactKind::Colliding);
+        assert_eq!(resolver.last_normal_velocity(), -10.0);
+        assert_eq!(resolve(&mut resolver, 0.05, 0.0), ContactKind::Resting);
+        assert_eq!(resolve(&mut resolver, -2.0, 2.0), ContactKind::Separating);
+        assert_eq!(resolver.last_kind(), ContactKind::Separating);
+    }
+
+    #[test]
+    fn sleeping_entities_skip_integration_until_woken() {
+        let physics = PhysicsSystem::new();
+        let still = Vector3::new(0.01, 0.0, 0.0);
+        assert!(physics.should_sleep(&still, true, 0.05));
+        assert!(!physics.should_sleep(&still, false, 0.05));
+        assert!(!physics.should_sleep(&Vector3::new(1.0, 0.0, 0.0), true, 0.05));
+
+        // Entity 0 sleeps; entity 1 is airborne and falls
+        let mut positions = [0.0, 0.0, 0.0, 0.0, 5.0, 0.0];
+        let mut velocities = [0.01, 0.0, 0.0, 0.0, 0.0, 0.0];
+        let mut sleeping = [1, 0];
+        physics.integrate_batch(&mut positions, &mut velocities, &mut sleeping, 2, 0.1, 0.05);
+        assert_eq!(&positions[0..3], &[0.0, 0.0, 0.0]);
+        assert!(positions[4] < 5.0);
+        assert_eq!(sleeping, [1, 0]);
+
+        // An impulse above the threshold wakes it
+        velocities[0] = 2.0;
+        physics.integrate_batch(&mut positions, &mut velocities, &mut sleeping, 2, 0.1, 0.05);
+        assert!((positions[0] - 0.2).abs() < 1e-6);
+        assert_eq!(sleeping[0], 0);
+    }
+
+    #[test]
+    fn raycast_batch_reports_nearest_hit_per_ray() {
+        let physics = PhysicsSystem::new();
+        let centers = [10.0, 0.0, 0.0, 5.0, 0.0, 0.0, 0.0, 0.0, 10.0];
+        let radii = [1.0, 1.0, 2.0];
+        let origins = [0.0; 12];
+        let directions = [
+            1.0, 0.0, 0.0,
+            0.0, 0.0, 3.0,
+            0.0, 1.0, 0.0,
+            -1.0, 0.0, 0.0,
+        ];
+        let hits = physics.raycast_spheres_batch(&origins, &directions, 4, &centers, &radii, 3);
+        // Along +X sphere 1 is in front of sphere 0
+        assert_eq!(hits, vec![1, 2, -1, -1]);
+
+        let inside = physics.raycast_spheres_batch(&[5.0, 0.0, 0.0], &[0.0, 1.0, 0.0], 1, &centers, &radii, 3);
+        assert_eq!(inside, vec![1]);
+    }
+}
@@ -0,0 +1,258 @@
+use wasm_bindgen::prelude::*;
+use std::collections::HashMap;
+use crate::math::Vector3;
+use crate::physics::Aabb;
+
+type CellKey = (i32, i32, i32);
+
+// Most cells a shape is registered in along each axis; larger shapes are
+// kept whole in a separate list that every query checks
+const MAX_CELL_SPAN: i32 = 64;
+
+// Uniform-grid spatial hash for broadphase queries.
+// `clear` keeps bucket allocations around for reuse between frames;
+// `compact` releases this hash's own allocations when it has shrunk for good.
+#[wasm_bindgen]
+pub struct SpatialHash {
+    cell_size: f32,
+    buckets: HashMap<CellKey, Vec<u32>>,
+    // Shapes spanning more than MAX_CELL_SPAN cells on some axis, with bounds
+    oversized: Vec<(u32, Aabb)>,
+}
+
+#[wasm_bindgen]
+impl SpatialHash {
+    #[wasm_bindgen(constructor)]
+    pub fn new(cell_size: f32) -> SpatialHash {
+        SpatialHash {
+            cell_size: if cell_size > 0.0 { cell_size } else { 1.0 },
+            buckets: HashMap::new(),
+            oversized: Vec::new(),
+        }
+    }
+
+    // Register a sphere in every cell its bounds overlap
+    pub fn insert_sphere(&mut self, id: u32, center: &Vector3, radius: f32) {
+        let bounds = sphere_bounds(center, radius);
+        self.insert_bounds(id, &bounds);
+    }
+
+    // Candidate IDs whose cells overlap the query sphere, sorted and deduplicated
+    pub fn query_sphere(&self, center: &Vector3, radius: f32) -> Vec<u32> {
+        self.query_bounds(&sphere_bounds(center, radius))
+    }
+
+    // Register a box in every cell it overlaps. Boxes with non-finite or
+    // inverted bounds are ignored.
+    pub fn insert_aabb(&mut self, id: u32, aabb: &Aabb) {
+        self.insert_bounds(id, aabb);
+    }
+
+    // Candidate IDs whose cells overlap the query box, sorted and
+    // deduplicated. Empty for boxes with non-finite or inverted bounds.
+    pub fn query_aabb(&self, aabb: &Aabb) -> Vec<u32> {
+        self.query_bounds(aabb)
+    }
+
+    // Empty every bucket while keeping their allocations for the next frame
+    pub fn clear(&mut self) {
+        for bucket in self.buckets.values_mut() {
+            bucket.clear();
+        }
+        self.oversized.clear();
+    }
+
+    // Drop empty buckets and release spare capacity back to the allocator.
+    // Only this hash is affected, and the freed space stays inside linear
+    // memory for later allocations.
+    pub fn compact(&mut self) {
+        self.buckets.retain(|_, bucket| !bucket.is_empty());
+        for bucket in self.buckets.values_mut() {
+            bucket.shrink_to_fit();
+        }
+        self.buckets.shrink_to_fit();
+        self.oversized.shrink_to_fit();
+    }
+
+    pub fn bucket_count(&self) -> usize {
+        self.buckets.len()
+    }
+
+    // Approximate heap bytes reserved by this hash: bucket table slots plus
+    // ID and oversized shape storage, ignoring allocator and hash table
+    // control overhead
+    pub fn reserved_bytes(&self) -> usize {
+        let table = self.buckets.capacity() * std::mem::size_of::<(CellKey, Vec<u32>)>();
+        let ids: usize = self.buckets.values().map(|bucket| bucket.capacity()).sum();
+        let oversized = self.oversized.capacity() * std::mem::size_of::<(u32, Aabb)>();
+        table + ids * std::mem::size_of::<u32>() + oversized
+    }
+}
+
+impl SpatialHash {
+    fn cell_of(&self, x: f32, y: f32, z: f32) -> CellKey {
+        (
+            (x / self.cell_size).floor() as i32,
+            (y / self.cell_size).floor() as i32,
+            (z / self.cell_size).floor() as i32,
+        )
+    }
+
+    // Cells covered by `bounds`; None for non-finite or inverted bounds
+    fn cell_range(&self, bounds: &Aabb) -> Option<(CellKey, CellKey)> {
+        let (min, max) = (&bounds.min, &bounds.max);
+        if !min.is_finite() || !max.is_finite() || min.x > max.x || min.y > max.y || min.z > max.z {
+            return None;
+        }
+        Some((self.cell_of(min.x, min.y, min.z), self.cell_of(max.x, max.y, max.z)))
+    }
+
+    fn insert_bounds(&mut self, id: u32, bounds: &Aabb) {
+        match self.cell_range(bounds) {
+            Some(range) if is_oversized(range) => self.oversized.push((id, *bounds)),
+            Some((min, max)) => self.insert_cells(id, min, max),
+            None => {},
+        }
+    }
+
+    // Bucketed candidates in the cells of `bounds` plus every oversized shape
+    // overlapping them. A query spanning too many cells to walk scans the
+    // occupied buckets instead.
+    fn query_bounds(&self, bounds: &Aabb) -> Vec<u32> {
+        let (min, max) = match self.cell_range(bounds) {
+            Some(range) => range,
+            None => return Vec::new(),
+        };
+        let mut result = if is_oversized((min, max)) {
+            let in_range = |key: &CellKey| {
+                (min.0..=max.0).contains(&key.0) && (min.1..=max.1).contains(&key.1) && (min.2..=max.2).contains(&key.2)
+            };
+            self.buckets
+                .iter()
+                .filter(|(key, _)| in_range(key))
+                .flat_map(|(_, bucket)| bucket.iter().copied())
+                .collect()
+        } else {
+            self.query_cells(min, max)
+        };
+        result.extend(
+            self.oversized
+                .iter()
+                .filter(|(_, shape)| bounds_overlap(shape, bounds))
+                .map(|(id, _)| *id),
+        );
+        result.sort_unstable();
+        result.dedup();
+        result
+    }
+
+    fn insert_cells(&mut self, id: u32, min: CellKey, max: CellKey) {
+        for x in min.0..=max.0 {
+            for y in min.1..=max.1 {
+                for z in min.2..=max.2 {
+                    self.buckets.entry((x, y, z)).or_default().push(id);
+                }
+            }
+        }
+    }
+
+    fn query_cells(&self, min: CellKey, max: CellKey) -> Vec<u32> {
+        let mut result = Vec::new();
+        for x in min.0..=max.0 {
+            for y in min.1..=max.1 {
+                for z in min.2..=max.2 {
+                    if let Some(bucket) = self.buckets.get(&(x, y, z)) {
+                        result.extend_from_slice(bucket);
+                    }
+                }
+            }
+        }
+        result
+    }
+}
+
+fn sphere_bounds(center: &Vector3, radius: f32) -> Aabb {
+    let radius = radius.max(0.0);
+    let offset = Vector3::new(radius, radius, radius);
+    Aabb::new(&center.subtract(&offset), &center.add(&offset))
+}
+
+// Whether a cell range spans more than MAX_CELL_SPAN cells on some axis
+fn is_oversized((min, max): (CellKey, CellKey)) -> bool {
+    let span = |low: i32, high: i32| high as i64 - low as i64 + 1;
+    [span(min.0, max.0), span(min.1, max.1), span(min.2, max.2)]
+        .iter()
+        .any(|&cells| cells > MAX_CELL_SPAN as i64)
+}
+
+fn bounds_overlap(a: &Aabb, b: &Aabb) -> bool {
+    a.min.x <= b.max.x && b.min.x <= a.max.x
+        && a.min.y <= b.max.y && b.min.y <= a.max.y
+        && a.min.z <= b.max.z && b.min.z <= a.max.z
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compact_releases_buckets_emptied_by_clear() {
+        let mut hash = SpatialHash::new(1.0);
+        for id in 0..64 {
+            hash.insert_sphere(id, &Vector3::new(id as f32 * 3.0, 0.0, 0.0), 0.5);
+        }
+        let used = hash.reserved_bytes();
+        assert!(used >= 64 * std::mem::size_of::<u32>());
+
+        hash.clear();
+        assert_eq!(hash.reserved_bytes(), used);
+        assert!(hash.query_sphere(&Vector3::new(0.0, 0.0, 0.0), 1.0).is_empty());
+
+        hash.compact();
+        assert_eq!(hash.bucket_count(), 0);
+        assert!(hash.reserved_bytes() < used);
+    }
+
+    #[test]
+    fn aabb_queries_find_overlapping_boxes() {
+        let mut hash = SpatialHash::new(2.0);
+        hash.insert_aabb(1, &Aabb::new(&Vector3::new(0.0, 0.0, 0.0), &Vector3::new(3.0, 1.0, 1.0)));
+        hash.insert_aabb(2, &Aabb::new(&Vector3::new(10.0, 0.0, 0.0), &Vector3::new(11.0, 1.0, 1.0)));
+        hash.insert_sphere(3, &Vector3::new(3.5, 0.5, 0.5), 0.25);
+
+        let near = Aabb::new(&Vector3::new(2.5, 0.0, 0.0), &Vector3::new(3.5, 0.5, 0.5));
+        assert_eq!(hash.query_aabb(&near), vec![1, 3]);
+        let far = Aabb::new(&Vector3::new(9.0, 0.0, 0.0), &Vector3::new(12.0, 0.5, 0.5));
+        assert_eq!(hash.query_aabb(&far), vec![2]);
+    }
+
+    #[test]
+    fn degenerate_boxes_are_rejected() {
+        let mut hash = SpatialHash::new(1.0);
+        let infinite = Aabb::new(&Vector3::new(f32::NEG_INFINITY, 0.0, 0.0), &Vector3::new(f32::INFINITY, 1.0, 1.0));
+        hash.insert_aabb(1, &infinite);
+        let nan = Aabb::new(&Vector3::new(f32::NAN, 0.0, 0.0), &Vector3::new(1.0, 1.0, 1.0));
+        hash.insert_aabb(2, &nan);
+        hash.insert_sphere(3, &Vector3::new(f32::NAN, 0.0, 0.0), 1.0);
+        assert_eq!(hash.bucket_count(), 0);
+        assert!(hash.query_aabb(&infinite).is_empty());
+    }
+
+    #[test]
+    fn oversized_boxes_are_found_along_their_whole_length() {
+        let mut hash = SpatialHash::new(1.0);
+        let floor = Aabb::new(&Vector3::new(0.0, 0.0, 0.0), &Vector3::new(1.0e4, 0.5, 0.5));
+        hash.insert_aabb(4, &floor);
+        hash.insert_sphere(5, &Vector3::new(9000.5, 0.25, 0.25), 0.25);
+        assert_eq!(hash.bucket_count(), 1);
+
+        let far_end = Aabb::new(&Vector3::new(9999.0, 0.0, 0.0), &Vector3::new(9999.5, 0.5, 0.5));
+        assert_eq!(hash.query_aabb(&far_end), vec![4]);
+        assert_eq!(hash.query_sphere(&Vector3::new(9000.5, 0.25, 0.25), 0.5), vec![4, 5]);
+        assert_eq!(hash.query_aabb(&floor), vec![4, 5]);
+        assert!(hash.query_aabb(&Aabb::new(&Vector3::new(0.0, 5.0, 0.0), &Vector3::new(1.0, 6.0, 1.0))).is_empty());
+
+        hash.clear();
+        assert!(hash.query_aabb(&far_end).is_empty());
+    }
+}
@@ -0,0 +1,138 @@
+use wasm_bindgen::prelude::*;
+use crate::math::Vector3;
+use crate::rng::Rng;
+
+// Idle wandering: turn the current heading about Y by a random angle of at
+// most `jitter` radians and return a velocity of `max_speed` along it.
+// The same seed sequence always reproduces the same path.
+pub fn wander(
+    _position: &Vector3,
+    current_heading: &Vector3,
+    rng_seed: &mut u64,
+    jitter: f32,
+    max_speed: f32,
+) -> Vector3 {
+    let mut rng = Rng::new(*rng_seed);
+    let velocity = wander_with_rng(_position, current_heading, &mut rng, jitter, max_speed);
+    *rng_seed = rng.state();
+    velocity
+}
+
+// `wander` drawing from a caller-owned generator, for use from JS where the
+// seed cannot be passed by reference
+#[wasm_bindgen]
+pub fn wander_with_rng(
+    _position: &Vector3,
+    current_heading: &Vector3,
+    rng: &mut Rng,
+    jitter: f32,
+    max_speed: f32,
+) -> Vector3 {
+    let mut heading = Vector3::new(current_heading.x, 0.0, current_heading.z).sanitized(&Vector3::new(0.0, 0.0, 1.0));
+    heading.normalize();
+    if heading.length() == 0.0 {
+        heading = Vector3::new(0.0, 0.0, 1.0);
+    }
+
+    let angle = rng.range_f32(-1.0, 1.0) * jitter;
+    let (sin, cos) = angle.sin_cos();
+    let mut turned = Vector3::new(
+        heading.x * cos + heading.z * sin,
+        0.0,
+        -heading.x * sin + heading.z * cos,
+    );
+    turned.normalize();
+    turned.multiply(max_speed.max(0.0))
+}
+
+// Weighted sum of steering forces clamped to `max_force`. Extra entries in
+// the longer slice are ignored.
+pub fn blend(forces: &[Vector3], weights: &[f32], max_force: f32) -> Vector3 {
+    let sum = forces
+        .iter()
+        .zip(weights)
+        .fold(Vector3::new(0.0, 0.0, 0.0), |sum, (force, &weight)| sum.add(&force.multiply(weight)));
+
+    let max_force = max_force.max(0.0);
+    let length = sum.length();
+    if length > max_force {
+        sum.multiply(max_force / length)
+    } else {
+        sum
+    }
+}
+
+// `blend` over an xyz-interleaved force buffer, for use from JS
+#[wasm_bindgen]
+pub fn blend_flat(forces: &[f32], weights: &[f32], max_force: f32) -> Vector3 {
+    let forces: Vec<Vector3> = forces
+        .chunks_exact(3)
+        .map(|force| Vector3::new(force[0], force[1], force[2]))
+        .collect();
+    blend(&forces, weights, max_force)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wander_replays_from_the_same_seed() {
+        let position = Vector3::new(0.0, 0.0, 0.0);
+        let heading = Vector3::new(0.0, 0.0, 1.0);
+        let (mut seed_a, mut seed_b) = (42, 42);
+        for _ in 0..4 {
+            let a = wander(&position, &heading, &mut seed_a, 0.5, 3.0);
+            let b = wander(&position, &heading, &mut seed_b, 0.5, 3.0);
+            assert_eq!((a.x, a.z), (b.x, b.z));
+            assert!((a.length() - 3.0).abs() < 1e-4);
+            assert_eq!(a.y, 0.0);
+            // Turned by at most `jitter` from the heading
+            assert!(a.z / 3.0 >= 0.5f32.cos() - 1e-4);
+        }
+        assert_ne!(seed_a, 42);
+    }
+
+    #[test]
+    fn wander_with_rng_matches_seeded_wander() {
+        let position = Vector3::new(0.0, 0.0, 0.0);
+        let heading = Vector3::new(1.0, 0.0, 0.0);
+        let mut seed = 9;
+        let mut rng = Rng::new(9);
+        let seeded = wander(&position, &heading, &mut seed, 1.0, 2.0);
+        let shared = wander_with_rng(&position, &heading, &mut rng, 1.0, 2.0);
+        assert_eq!((seeded.x, seeded.z), (shared.x, shared.z));
+        assert_eq!(seed, rng.state());
+    }
+
+    #[test]
+    fn blend_weights_forces_and_clamps_to_max_force() {
+        let forces = [Vector3::new(1.0, 0.0, 0.0), Vector3::new(0.0, 0.0, 2.0)];
+        let sum = blend(&forces, &[2.0, 0.5], 10.0);
+        assert_eq!((sum.x, sum.y, sum.z), (2.0, 0.0, 1.0));
+
+        let clamped = blend(&forces, &[6.0, 4.0], 5.0);
+        assert!((clamped.length() - 5.0).abs() < 1e-5);
+        assert!((clamped.x / clamped.z - 6.0 / 8.0).abs() < 1e-5);
+
+        // The extra weight has no force to scale
+        let extra = blend(&forces[..1], &[1.0, 3.0], 10.0);
+        assert_eq!(extra.x, 1.0);
+    }
+
+    #[test]
+    fn blend_flat_matches_blend() {
+        let flat = blend_flat(&[1.0, 0.0, 0.0, 0.0, 0.0, 2.0], &[2.0, 0.5], 10.0);
+        assert_eq!((flat.x, flat.y, flat.z), (2.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn blend_cancels_equal_opposing_forces() {
+        let forces = [Vector3::new(3.0, 0.0, -1.0), Vector3::new(-3.0, 0.0, 1.0)];
+        let sum = blend(&forces, &[0.5, 0.5], 10.0);
+        assert_eq!((sum.x, sum.y, sum.z), (0.0, 0.0, 0.0));
+        for weight in [1.0, 10.0, 100.0] {
+            assert!(blend(&forces[..1], &[weight], 2.0).length() <= 2.0 + 1e-5);
+        }
+    }
+}
@@ -0,0 +1,92 @@
+use wasm_bindgen::prelude::*;
+use crate::math::Vector3;
+
+// Double-buffered entity positions. Systems read last frame's values from
+// the read half while writing this frame's into the write half, so entities
+// updated early in a frame do not affect ones updated later. `swap` publishes
+// the written values.
+#[wasm_bindgen]
+pub struct TransformBuffer {
+    count: usize,
+    read: Vec<f32>,
+    write: Vec<f32>,
+}
+
+#[wasm_bindgen]
+impl TransformBuffer {
+    #[wasm_bindgen(constructor)]
+    pub fn new(count: usize) -> TransformBuffer {
+        TransformBuffer {
+            count,
+            read: vec![0.0; count * 3],
+            write: vec![0.0; count * 3],
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    // Last frame's position of `index`; the origin when out of range
+    pub fn read(&self, index: usize) -> Vector3 {
+        match self.read.get(index * 3..index * 3 + 3) {
+            Some(xyz) => Vector3::new(xyz[0], xyz[1], xyz[2]),
+            None => Vector3::new(0.0, 0.0, 0.0),
+        }
+    }
+
+    // Stage this frame's position of `index`; out-of-range writes are ignored
+    pub fn write(&mut self, index: usize, position: &Vector3) {
+        if let Some(xyz) = self.write.get_mut(index * 3..index * 3 + 3) {
+            xyz.copy_from_slice(&[position.x, position.y, position.z]);
+        }
+    }
+
+    // Every position from the read half, xyz-interleaved
+    pub fn read_all(&self) -> Vec<f32> {
+        self.read.clone()
+    }
+
+    // Stage positions from an xyz-interleaved buffer, starting at entity 0.
+    // Entries beyond `count` are ignored.
+    pub fn write_all(&mut self, positions: &[f32]) {
+        let length = positions.len().min(self.write.len()) / 3 * 3;
+        self.write[..length].copy_from_slice(&positions[..length]);
+    }
+
+    // Make the staged positions readable. The write half starts the next
+    // frame as a copy of them, so entities not written keep their position.
+    pub fn swap(&mut self) {
+        std::mem::swap(&mut self.read, &mut self.write);
+        self.write.copy_from_slice(&self.read);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_see_last_frame_until_swap() {
+        let mut buffer = TransformBuffer::new(2);
+        buffer.write_all(&[1.0, 0.0, 0.0, 5.0, 0.0, 0.0]);
+        buffer.swap();
+
+        // Each entity steps halfway toward the other, reading last frame only
+        for (index, other) in [(0, 1), (1, 0)] {
+            let me = buffer.read(index);
+            let target = buffer.read(other);
+            buffer.write(index, &me.midpoint(&target));
+        }
+        assert_eq!(buffer.read_all(), vec![1.0, 0.0, 0.0, 5.0, 0.0, 0.0]);
+
+        buffer.swap();
+        assert_eq!(buffer.read_all(), vec![3.0, 0.0, 0.0, 3.0, 0.0, 0.0]);
+
+        // Unwritten entities keep their position across swaps
+        buffer.write(0, &Vector3::new(7.0, 0.0, 0.0));
+        buffer.swap();
+        assert_eq!(buffer.read_all(), vec![7.0, 0.0, 0.0, 3.0, 0.0, 0.0]);
+        assert_eq!(buffer.read(9).x, 0.0);
+    }
+}